@@ -0,0 +1,535 @@
+extern crate gridlib;
+
+use gridlib::{Direction, Grid, GridCoordinate, GridCoordinateInf, GridTraversable};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+pub type SparseCoord = GridCoordinateInf;
+pub type SparseSet = FxHashSet<SparseCoord>;
+
+/// When a simulation should stop running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopCondition {
+    /// Run until a step changes nothing. The unchanged step is counted.
+    NoChange,
+    /// Run exactly this many steps, even if nothing is changing.
+    Steps(usize),
+    /// Run until a step changes nothing, or this many steps, whichever is first.
+    NoChangeOrSteps(usize),
+}
+
+impl StopCondition {
+    fn should_stop(&self, steps_run: usize, changed: bool) -> bool {
+        return match self {
+            StopCondition::NoChange => !changed,
+            StopCondition::Steps(n) => steps_run >= *n,
+            StopCondition::NoChangeOrSteps(n) => !changed || steps_run >= *n,
+        };
+    }
+
+    fn stop_before_start(&self) -> bool {
+        return match self {
+            StopCondition::NoChange => false,
+            StopCondition::Steps(n) => *n == 0,
+            StopCondition::NoChangeOrSteps(n) => *n == 0,
+        };
+    }
+}
+
+/// Which cells count as neighbours of a cell on a dense grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighbourhoodKind {
+    /// North, east, south and west.
+    VonNeumann,
+    /// All eight surrounding cells.
+    Moore,
+}
+
+impl NeighbourhoodKind {
+    pub fn directions(&self) -> Vec<Direction> {
+        return match self {
+            NeighbourhoodKind::VonNeumann => vec![
+                Direction::NORTH,
+                Direction::EAST,
+                Direction::SOUTH,
+                Direction::WEST,
+            ],
+            NeighbourhoodKind::Moore => vec![
+                Direction::NORTH,
+                Direction::NORTHEAST,
+                Direction::EAST,
+                Direction::SOUTHEAST,
+                Direction::SOUTH,
+                Direction::SOUTHWEST,
+                Direction::WEST,
+                Direction::NORTHWEST,
+            ],
+        };
+    }
+}
+
+/// A cell and the values of its neighbours, as seen at the start of a step.
+///
+/// Neighbours that fall off the edge of the grid are missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Neighbourhood<T: Copy> {
+    pub pos: GridCoordinate,
+    pub center: T,
+    neighbours: Vec<(Direction, T)>,
+}
+
+impl<T: Copy> Neighbourhood<T> {
+    pub fn get(&self, direction: Direction) -> Option<T> {
+        return self
+            .neighbours
+            .iter()
+            .find(|(d, _)| *d == direction)
+            .map(|(_, v)| *v);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Direction, T)> {
+        return self.neighbours.iter();
+    }
+
+    /// Count the neighbours matching `pred`.
+    pub fn count<F: Fn(T) -> bool>(&self, pred: F) -> usize {
+        return self.neighbours.iter().filter(|(_, v)| pred(*v)).count();
+    }
+}
+
+/// A rule for a dense grid, where every cell is recomputed each step.
+pub trait CellRule {
+    type Cell: Copy + PartialEq;
+
+    fn neighbourhood(&self) -> NeighbourhoodKind {
+        return NeighbourhoodKind::Moore;
+    }
+
+    /// The value of the cell after this step.
+    ///
+    /// `step` is the number of steps already run, starting at 0.
+    fn next_state(&self, hood: &Neighbourhood<Self::Cell>, step: usize) -> Self::Cell;
+}
+
+/// Runs a `CellRule` over a bounded grid with synchronous updates.
+pub struct GridAutomaton<R: CellRule> {
+    rule: R,
+    grid: Grid<R::Cell>,
+    steps_run: usize,
+}
+
+impl<R: CellRule> GridAutomaton<R> {
+    pub fn new(rule: R, grid: Grid<R::Cell>) -> GridAutomaton<R> {
+        return GridAutomaton {
+            rule: rule,
+            grid: grid,
+            steps_run: 0,
+        };
+    }
+
+    pub fn grid(&self) -> &Grid<R::Cell> {
+        return &self.grid;
+    }
+
+    pub fn steps_run(&self) -> usize {
+        return self.steps_run;
+    }
+
+    fn neighbourhood_at(
+        &self,
+        pos: GridCoordinate,
+        kind: NeighbourhoodKind,
+    ) -> Neighbourhood<R::Cell> {
+        let neighbours = kind
+            .directions()
+            .into_iter()
+            .filter_map(|d| {
+                let n = self.grid.get_coordinate_by_direction(pos, d)?;
+                return Some((d, self.grid.get_value(n)?));
+            })
+            .collect();
+        return Neighbourhood {
+            pos: pos,
+            center: self.grid.get_value(pos).unwrap(),
+            neighbours: neighbours,
+        };
+    }
+
+    /// Run a single step, returns true if any cell changed.
+    pub fn step(&mut self) -> bool {
+        let kind = self.rule.neighbourhood();
+        let mut changed = false;
+        let mut values = Vec::with_capacity(self.grid.get_width() * self.grid.get_height());
+        for pos in self.grid.coord_iter() {
+            let hood = self.neighbourhood_at(pos, kind);
+            let next = self.rule.next_state(&hood, self.steps_run);
+            if next != hood.center {
+                changed = true;
+            }
+            values.push(next);
+        }
+        self.grid = Grid::new(self.grid.get_width(), self.grid.get_height(), values);
+        self.steps_run += 1;
+        return changed;
+    }
+
+    /// Run until `stop` is met, returns the number of steps run by this call.
+    pub fn run(&mut self, stop: StopCondition) -> usize {
+        return self.run_with_callback(stop, |_, _| ());
+    }
+
+    /// Like `run`, but calls `callback` with the step count and grid after every step.
+    pub fn run_with_callback<F>(&mut self, stop: StopCondition, mut callback: F) -> usize
+    where
+        F: FnMut(usize, &Grid<R::Cell>),
+    {
+        if stop.stop_before_start() {
+            return 0;
+        }
+        let mut count = 0;
+        loop {
+            let changed = self.step();
+            count += 1;
+            callback(self.steps_run, &self.grid);
+            if stop.should_stop(count, changed) {
+                return count;
+            }
+        }
+    }
+}
+
+/// A rule for agents moving on an unbounded sparse grid.
+///
+/// Every agent proposes a move based on the state at the start of the step,
+/// then all the moves are applied at once.
+pub trait MoveRule {
+    /// Where the agent at `pos` wants to go, or `None` to stay put.
+    ///
+    /// `step` is the number of steps already run, starting at 0.
+    fn propose(&self, pos: SparseCoord, occupied: &SparseSet, step: usize) -> Option<SparseCoord>;
+
+    /// Pick which of `contenders` gets to move into `target`.
+    ///
+    /// Contenders are sorted, and there are always at least two.
+    /// The default is that nobody moves.
+    fn resolve_conflict(
+        &self,
+        _target: SparseCoord,
+        _contenders: &[SparseCoord],
+    ) -> Option<SparseCoord> {
+        return None;
+    }
+}
+
+/// Runs a `MoveRule` over a set of agents.
+///
+/// An agent can only move into a cell that was empty at the start of the step,
+/// anyone who doesn't get to move stays where they were.
+pub struct SparseAutomaton<R: MoveRule> {
+    rule: R,
+    agents: SparseSet,
+    steps_run: usize,
+}
+
+impl<R: MoveRule> SparseAutomaton<R> {
+    pub fn new(rule: R, agents: SparseSet) -> SparseAutomaton<R> {
+        return SparseAutomaton {
+            rule: rule,
+            agents: agents,
+            steps_run: 0,
+        };
+    }
+
+    /// Start as if `steps_run` steps have already happened.
+    pub fn new_at_step(rule: R, agents: SparseSet, steps_run: usize) -> SparseAutomaton<R> {
+        return SparseAutomaton {
+            rule: rule,
+            agents: agents,
+            steps_run: steps_run,
+        };
+    }
+
+    pub fn agents(&self) -> &SparseSet {
+        return &self.agents;
+    }
+
+    pub fn into_agents(self) -> SparseSet {
+        return self.agents;
+    }
+
+    pub fn steps_run(&self) -> usize {
+        return self.steps_run;
+    }
+
+    /// Run a single step, returns true if any agent moved.
+    pub fn step(&mut self) -> bool {
+        let mut next = SparseSet::default();
+        let mut proposals: FxHashMap<SparseCoord, Vec<SparseCoord>> = FxHashMap::default();
+        for agent in self.agents.iter() {
+            match self.rule.propose(*agent, &self.agents, self.steps_run) {
+                Some(target) if target != *agent && !self.agents.contains(&target) => {
+                    proposals.entry(target).or_default().push(*agent);
+                }
+                _ => {
+                    next.insert(*agent);
+                }
+            }
+        }
+
+        let mut changed = false;
+        for (target, mut contenders) in proposals {
+            let winner = if contenders.len() == 1 {
+                Some(contenders[0])
+            } else {
+                contenders.sort_by_key(|c| (c.y, c.x));
+                self.rule
+                    .resolve_conflict(target, &contenders)
+                    .filter(|w| contenders.contains(w))
+            };
+            for contender in contenders {
+                if Some(contender) == winner {
+                    next.insert(target);
+                    changed = true;
+                } else {
+                    next.insert(contender);
+                }
+            }
+        }
+
+        self.agents = next;
+        self.steps_run += 1;
+        return changed;
+    }
+
+    /// Run until `stop` is met, returns the number of steps run by this call.
+    pub fn run(&mut self, stop: StopCondition) -> usize {
+        return self.run_with_callback(stop, |_, _| ());
+    }
+
+    /// Like `run`, but calls `callback` with the step count and agents after every step.
+    pub fn run_with_callback<F>(&mut self, stop: StopCondition, mut callback: F) -> usize
+    where
+        F: FnMut(usize, &SparseSet),
+    {
+        if stop.stop_before_start() {
+            return 0;
+        }
+        let mut count = 0;
+        loop {
+            let changed = self.step();
+            count += 1;
+            callback(self.steps_run, &self.agents);
+            if stop.should_stop(count, changed) {
+                return count;
+            }
+        }
+    }
+}
+
+/// Smallest (min, max) corners containing every agent, `None` if there are none.
+pub fn bounding_box(agents: &SparseSet) -> Option<(SparseCoord, SparseCoord)> {
+    let min_x = agents.iter().map(|c| c.x).min()?;
+    let min_y = agents.iter().map(|c| c.y).min()?;
+    let max_x = agents.iter().map(|c| c.x).max()?;
+    let max_y = agents.iter().map(|c| c.y).max()?;
+    return Some((
+        SparseCoord::new(min_x, min_y),
+        SparseCoord::new(max_x, max_y),
+    ));
+}
+
+/// Draw the bounding box of the agents, `#` for an agent and `.` for empty.
+pub fn render_sparse(agents: &SparseSet) -> String {
+    let mut s = String::new();
+    if let Some((min, max)) = bounding_box(agents) {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                if agents.contains(&SparseCoord::new(x, y)) {
+                    s.push('#');
+                } else {
+                    s.push('.');
+                }
+            }
+            s.push('\n');
+        }
+    }
+    return s;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Life;
+
+    impl CellRule for Life {
+        type Cell = bool;
+
+        fn next_state(&self, hood: &Neighbourhood<bool>, _step: usize) -> bool {
+            let alive = hood.count(|v| v);
+            return alive == 3 || (hood.center && alive == 2);
+        }
+    }
+
+    fn blinker() -> Grid<bool> {
+        let mut values = vec![false; 25];
+        values[11] = true;
+        values[12] = true;
+        values[13] = true;
+        return Grid::new(5, 5, values);
+    }
+
+    #[test]
+    fn test_grid_blinker() {
+        let mut automaton = GridAutomaton::new(Life, blinker());
+        assert!(automaton.step());
+        let vertical: Vec<GridCoordinate> = automaton
+            .grid()
+            .coord_iter()
+            .filter(|c| automaton.grid().get_value(*c).unwrap())
+            .collect();
+        assert_eq!(
+            vertical,
+            vec![
+                GridCoordinate::new(2, 1),
+                GridCoordinate::new(2, 2),
+                GridCoordinate::new(2, 3)
+            ]
+        );
+        automaton.step();
+        assert_eq!(automaton.grid().data_copy(), blinker().data_copy());
+    }
+
+    #[test]
+    fn test_grid_stop_conditions() {
+        let mut automaton = GridAutomaton::new(Life, blinker());
+        assert_eq!(automaton.run(StopCondition::Steps(4)), 4);
+        assert_eq!(automaton.steps_run(), 4);
+        // A blinker never settles
+        assert_eq!(automaton.run(StopCondition::NoChangeOrSteps(7)), 7);
+
+        let block = vec![
+            false, false, false, false, false, true, true, false, false, true, true, false, false,
+            false, false, false,
+        ];
+        let mut still = GridAutomaton::new(Life, Grid::new(4, 4, block));
+        assert_eq!(still.run(StopCondition::NoChange), 1);
+        assert_eq!(still.run(StopCondition::Steps(0)), 0);
+    }
+
+    #[test]
+    fn test_grid_callback() {
+        let mut automaton = GridAutomaton::new(Life, blinker());
+        let mut seen = vec![];
+        automaton.run_with_callback(StopCondition::Steps(3), |step, grid| {
+            seen.push((step, grid.data_copy().iter().filter(|v| **v).count()));
+        });
+        assert_eq!(seen, vec![(1, 3), (2, 3), (3, 3)]);
+    }
+
+    struct MoveEast;
+
+    impl MoveRule for MoveEast {
+        fn propose(&self, pos: SparseCoord, _: &SparseSet, _: usize) -> Option<SparseCoord> {
+            return Some(pos.move_dir(Direction::EAST));
+        }
+    }
+
+    // East on even steps, south on odd ones.
+    struct Zigzag;
+
+    impl MoveRule for Zigzag {
+        fn propose(&self, pos: SparseCoord, _: &SparseSet, step: usize) -> Option<SparseCoord> {
+            if step.is_multiple_of(2) {
+                return Some(pos.move_dir(Direction::EAST));
+            }
+            return Some(pos.move_dir(Direction::SOUTH));
+        }
+    }
+
+    struct MoveToOrigin {
+        first_wins: bool,
+    }
+
+    impl MoveRule for MoveToOrigin {
+        fn propose(&self, pos: SparseCoord, _: &SparseSet, _: usize) -> Option<SparseCoord> {
+            if pos.x.abs() + pos.y.abs() == 1 {
+                return Some(SparseCoord::new(0, 0));
+            }
+            return None;
+        }
+
+        fn resolve_conflict(
+            &self,
+            _: SparseCoord,
+            contenders: &[SparseCoord],
+        ) -> Option<SparseCoord> {
+            if self.first_wins {
+                return Some(contenders[0]);
+            }
+            return None;
+        }
+    }
+
+    fn make_set(coords: &[(i32, i32)]) -> SparseSet {
+        return coords
+            .iter()
+            .map(|(x, y)| SparseCoord::new(*x, *y))
+            .collect();
+    }
+
+    #[test]
+    fn test_sparse_blocked_by_occupied() {
+        // The front agent moves, the back one is blocked by where it started
+        let mut automaton = SparseAutomaton::new(MoveEast, make_set(&[(0, 0), (1, 0)]));
+        assert!(automaton.step());
+        assert_eq!(automaton.agents(), &make_set(&[(0, 0), (2, 0)]));
+        assert_eq!(automaton.run(StopCondition::Steps(2)), 2);
+        assert_eq!(automaton.agents(), &make_set(&[(2, 0), (4, 0)]));
+    }
+
+    #[test]
+    fn test_sparse_conflict_default() {
+        let start = make_set(&[(1, 0), (-1, 0), (0, 5)]);
+        let mut automaton = SparseAutomaton::new(MoveToOrigin { first_wins: false }, start.clone());
+        assert_eq!(automaton.run(StopCondition::NoChange), 1);
+        assert_eq!(automaton.agents(), &start);
+    }
+
+    #[test]
+    fn test_sparse_conflict_resolved() {
+        let start = make_set(&[(1, 0), (-1, 0), (0, 5)]);
+        let mut automaton = SparseAutomaton::new(MoveToOrigin { first_wins: true }, start);
+        let mut renders = vec![];
+        let steps = automaton.run_with_callback(StopCondition::NoChange, |_, agents| {
+            renders.push(render_sparse(agents));
+        });
+        assert_eq!(steps, 2);
+        assert_eq!(automaton.agents(), &make_set(&[(0, 0), (1, 0), (0, 5)]));
+        assert_eq!(renders[0], "##\n..\n..\n..\n..\n#.\n");
+    }
+
+    #[test]
+    fn test_sparse_new_at_step() {
+        let mut fresh = SparseAutomaton::new(Zigzag, make_set(&[(0, 0)]));
+        fresh.run(StopCondition::Steps(3));
+        // Picking up from where step 1 left off follows the same path as running from the start.
+        let mut resumed = SparseAutomaton::new_at_step(Zigzag, make_set(&[(1, 0)]), 1);
+        assert_eq!(resumed.steps_run(), 1);
+        assert!(resumed.step());
+        // Step 1 is odd, so the first move is south rather than east.
+        assert_eq!(resumed.agents(), &make_set(&[(1, 1)]));
+        let mut seen = vec![];
+        resumed.run_with_callback(StopCondition::Steps(1), |step, _| seen.push(step));
+        assert_eq!(seen, vec![3]);
+        assert_eq!(resumed.agents(), fresh.agents());
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        assert_eq!(bounding_box(&SparseSet::default()), None);
+        assert_eq!(
+            bounding_box(&make_set(&[(-3, -1), (-1, -3)])),
+            Some((SparseCoord::new(-3, -3), SparseCoord::new(-1, -1)))
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use rustc_hash::FxHashMap;
+use std::hash::Hash;
+
+/// A repeating section of a sequence of states.
+///
+/// State `start` is the first state that is seen again, and it comes back
+/// every `length` steps after that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    pub fn new(start: usize, length: usize) -> Cycle {
+        return Cycle {
+            start: start,
+            length: length,
+        };
+    }
+
+    /// The earliest step with the same state as `target`.
+    ///
+    /// ```
+    /// let cycle = cyclelib::Cycle::new(3, 4);
+    /// assert_eq!(cycle.equivalent_step(2), 2);
+    /// assert_eq!(cycle.equivalent_step(7), 3);
+    /// assert_eq!(cycle.equivalent_step(1_000_000_001), 5);
+    /// ```
+    pub fn equivalent_step(&self, target: usize) -> usize {
+        if target < self.start {
+            return target;
+        }
+        return self.start + (target - self.start) % self.length;
+    }
+
+    /// How many full loops happen between `start` and `target`.
+    pub fn loops_before(&self, target: usize) -> usize {
+        if target < self.start {
+            return 0;
+        }
+        return (target - self.start) / self.length;
+    }
+
+    /// Work out a value that changes by the same amount every loop, such as a height.
+    ///
+    /// `value_at` must be able to give the value for any step up to `start + length`.
+    /// ```
+    /// // Grows by 1 a step for 2 steps, then by 5 every 3 steps
+    /// let values = vec![0, 1, 2, 4, 5, 7, 9, 10];
+    /// let cycle = cyclelib::Cycle::new(2, 3);
+    /// assert_eq!(cycle.extrapolate(7, |i| values[i]), 10);
+    /// assert_eq!(cycle.extrapolate(2 + 3 * 1000, |i| values[i]), 5002);
+    /// ```
+    pub fn extrapolate<F>(&self, target: usize, value_at: F) -> i64
+    where
+        F: Fn(usize) -> i64,
+    {
+        let equivalent = self.equivalent_step(target);
+        if target < self.start {
+            return value_at(target);
+        }
+        let per_loop = value_at(self.start + self.length) - value_at(self.start);
+        let loops: i64 = self.loops_before(target).try_into().unwrap();
+        return value_at(equivalent) + loops * per_loop;
+    }
+}
+
+/// Find the cycle reached by repeatedly applying `step` to `initial`, using Floyd's tortoise and hare.
+///
+/// Only keeps two states in memory, but calls `step` about three times per state.
+/// Never returns if the states never repeat.
+/// ```
+/// let cycle = cyclelib::floyd(0, |x| (x * x + 1) % 255);
+/// assert_eq!(cycle, cyclelib::Cycle::new(2, 6));
+/// ```
+pub fn floyd<S, F>(initial: S, step: F) -> Cycle
+where
+    S: PartialEq + Clone,
+    F: Fn(&S) -> S,
+{
+    let mut tortoise = step(&initial);
+    let mut hare = step(&step(&initial));
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&step(&hare));
+    }
+
+    // Distance from the start to the cycle is the same as from the meeting point.
+    let mut start = 0;
+    tortoise = initial;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    let mut length = 1;
+    hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        length += 1;
+    }
+
+    return Cycle::new(start, length);
+}
+
+/// Find the cycle reached by repeatedly applying `step` to `initial`, using Brent's algorithm.
+///
+/// Same memory use as `floyd`, but usually needs fewer calls to `step`.
+/// Never returns if the states never repeat.
+/// ```
+/// let cycle = cyclelib::brent(0, |x| (x * x + 1) % 255);
+/// assert_eq!(cycle, cyclelib::Cycle::new(2, 6));
+/// ```
+pub fn brent<S, F>(initial: S, step: F) -> Cycle
+where
+    S: PartialEq + Clone,
+    F: Fn(&S) -> S,
+{
+    // Find the length first, by looking for the tortoise in power of two sized windows.
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = initial.clone();
+    let mut hare = step(&initial);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = step(&hare);
+        length += 1;
+    }
+
+    // Then walk two states `length` apart until they meet.
+    tortoise = initial.clone();
+    hare = initial;
+    for _ in 0..length {
+        hare = step(&hare);
+    }
+    let mut start = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    return Cycle::new(start, length);
+}
+
+/// Find the cycle reached by repeatedly applying `step` to `initial`, remembering every state.
+///
+/// Calls `step` once per state, and returns every state up to the end of the
+/// first loop, so `states[cycle.equivalent_step(n)]` is the state after `n` steps.
+/// Never returns if the states never repeat.
+/// ```
+/// let (cycle, states) = cyclelib::find_cycle_hashed(0, |x| (x * x + 1) % 255);
+/// assert_eq!(cycle, cyclelib::Cycle::new(2, 6));
+/// assert_eq!(states[..4], [0, 1, 2, 5]);
+/// ```
+pub fn find_cycle_hashed<S, F>(initial: S, mut step: F) -> (Cycle, Vec<S>)
+where
+    S: Hash + Eq + Clone,
+    F: FnMut(&S) -> S,
+{
+    let mut seen: FxHashMap<S, usize> = FxHashMap::default();
+    let mut states = vec![];
+    let mut cur = initial;
+    loop {
+        if let Some(prev) = seen.get(&cur) {
+            return (Cycle::new(*prev, states.len() - prev), states);
+        }
+        seen.insert(cur.clone(), states.len());
+        let next = step(&cur);
+        states.push(cur);
+        cur = next;
+    }
+}
+
+/// Find the first repeated key in a sequence.
+///
+/// Use this when the state is expensive to copy, by having the iterator advance a
+/// simulation and produce a key that fully describes its state.
+/// Returns `None` if the sequence ends without repeating.
+/// ```
+/// let keys = vec!['a', 'b', 'c', 'd', 'b', 'c', 'd'];
+/// assert_eq!(cyclelib::find_cycle_in_iter(keys), Some(cyclelib::Cycle::new(1, 3)));
+/// assert_eq!(cyclelib::find_cycle_in_iter(vec![1, 2, 3]), None);
+/// ```
+pub fn find_cycle_in_iter<K, I>(keys: I) -> Option<Cycle>
+where
+    K: Hash + Eq,
+    I: IntoIterator<Item = K>,
+{
+    let mut seen: FxHashMap<K, usize> = FxHashMap::default();
+    for (i, key) in keys.into_iter().enumerate() {
+        if let Some(prev) = seen.get(&key) {
+            return Some(Cycle::new(*prev, i - prev));
+        }
+        seen.insert(key, i);
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotate(v: &[u8; 5]) -> [u8; 5] {
+        let mut next = *v;
+        next.rotate_left(1);
+        return next;
+    }
+
+    #[test]
+    fn test_pure_loop() {
+        let start = [1, 2, 3, 4, 5];
+        assert_eq!(floyd(start, rotate), Cycle::new(0, 5));
+        assert_eq!(brent(start, rotate), Cycle::new(0, 5));
+        let (cycle, states) = find_cycle_hashed(start, rotate);
+        assert_eq!(cycle, Cycle::new(0, 5));
+        assert_eq!(states.len(), 5);
+    }
+
+    #[test]
+    fn test_fixed_point() {
+        let step = |x: &u32| if *x > 10 { *x - 1 } else { 10 };
+        assert_eq!(floyd(15, step), Cycle::new(5, 1));
+        assert_eq!(brent(15, step), Cycle::new(5, 1));
+        assert_eq!(find_cycle_hashed(15, step).0, Cycle::new(5, 1));
+    }
+
+    #[test]
+    fn test_algorithms_agree() {
+        for seed in 0..50u64 {
+            let step = |x: &u64| (x * x + seed) % 1009;
+            let expected = find_cycle_hashed(seed, step).0;
+            assert_eq!(floyd(seed, step), expected);
+            assert_eq!(brent(seed, step), expected);
+        }
+    }
+
+    #[test]
+    fn test_extrapolate_matches_simulation() {
+        // Add the state to a total every step, and check the total against the real thing.
+        let step = |x: &u64| (x * 7 + 3) % 40;
+        let (cycle, states) = find_cycle_hashed(1, step);
+        let mut totals = vec![0i64];
+        for i in 0..200 {
+            let state = states[cycle.equivalent_step(i)] as i64;
+            totals.push(totals[i] + state);
+        }
+        for target in 0..200 {
+            assert_eq!(cycle.extrapolate(target, |i| totals[i]), totals[target]);
+        }
+    }
+
+    #[test]
+    fn test_before_start() {
+        let cycle = Cycle::new(10, 3);
+        assert_eq!(cycle.loops_before(4), 0);
+        assert_eq!(cycle.loops_before(16), 2);
+        assert_eq!(cycle.extrapolate(4, |i| i as i64 * 100), 400);
+    }
+}
@@ -2,183 +2,462 @@ extern crate filelib;
 extern crate mathlib;
 
 pub use filelib::load_no_blanks;
+use std::fmt::{Display, Formatter};
 
-// Logic solution
-#[derive(PartialEq, Debug, Copy, Clone)]
-pub enum RockPaperScissors {
-    Rock,
-    Paper,
-    Scissors,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Lose,
+    Draw,
+    Win,
 }
 
-const WIN_POINTS: i32 = 6;
-const DRAW_POINTS: i32 = 3;
-const LOSE_POINTS: i32 = 0;
-
-fn parse_input_puzzle_1(input: &Vec<String>) -> Vec<(RockPaperScissors, RockPaperScissors)> {
-    return input.iter().map(|s| parse_line_puzzle_1(s)).collect();
+impl Outcome {
+    pub const ALL: [Outcome; 3] = [Outcome::Lose, Outcome::Draw, Outcome::Win];
 }
 
-fn parse_line_puzzle_1(s: &String) -> (RockPaperScissors, RockPaperScissors) {
-    let mut split_value = s.split_whitespace();
-    let first = abc_to_rps(split_value.next().unwrap_or(""));
-    let second = xyz_to_rps(split_value.next().unwrap_or(""));
-    return (first, second);
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Outcome::Lose => "lose",
+            Outcome::Draw => "draw",
+            Outcome::Win => "win",
+        };
+        return write!(f, "{}", s);
+    }
 }
 
-fn abc_to_rps(i: &str) -> RockPaperScissors {
-    return match i {
-        "A" => RockPaperScissors::Rock,
-        "B" => RockPaperScissors::Paper,
-        "C" => RockPaperScissors::Scissors,
-        _ => RockPaperScissors::Rock,
-    };
+/// The moves that can be thrown, which beats which, and what each is worth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveSet {
+    names: Vec<String>,
+    // beats[a][b] is true when a beats b.
+    beats: Vec<Vec<bool>>,
+    scores: Vec<i32>,
 }
 
-fn xyz_to_rps(i: &str) -> RockPaperScissors {
-    return match i {
-        "X" => RockPaperScissors::Rock,
-        "Y" => RockPaperScissors::Paper,
-        "Z" => RockPaperScissors::Scissors,
-        _ => RockPaperScissors::Rock,
-    };
-}
+impl MoveSet {
+    /// Moves scored 1, 2, 3 and so on, where each beats the ones it is listed against.
+    ///
+    /// Pairs not listed either way are a draw. Panics on a name that isn't a move.
+    pub fn from_rules(names: &[&str], wins: &[(&str, &str)]) -> MoveSet {
+        let index = |name: &str| match names.iter().position(|n| *n == name) {
+            Some(i) => i,
+            None => panic!("Can't find a move called {}", name),
+        };
+        let mut beats = vec![vec![false; names.len()]; names.len()];
+        for (winner, loser) in wins {
+            beats[index(winner)][index(loser)] = true;
+        }
+        return MoveSet {
+            names: names.iter().map(|n| n.to_string()).collect(),
+            beats: beats,
+            scores: (1..=names.len() as i32).collect(),
+        };
+    }
+
+    /// An odd number of moves in a circle, where each beats the half of the circle just before it.
+    ///
+    /// Uses the same modulo trick as the two move maths solution: with `d = (you - them) mod n`,
+    /// 0 is a draw and 1 to n/2 is a win. Panics on an even number of moves, where the move
+    /// straight across the circle would beat and be beaten by the same move.
+    pub fn cyclic(names: &[&str]) -> MoveSet {
+        if names.len().is_multiple_of(2) {
+            panic!("Can't make a fair circle of {} moves", names.len());
+        }
+        let n = names.len() as i32;
+        let mut beats = vec![vec![false; names.len()]; names.len()];
+        for (you, row) in beats.iter_mut().enumerate() {
+            for (them, cell) in row.iter_mut().enumerate() {
+                let d = mathlib::modulus(you as i32 - them as i32, n);
+                *cell = d != 0 && d <= n / 2;
+            }
+        }
+        return MoveSet {
+            names: names.iter().map(|n| n.to_string()).collect(),
+            beats: beats,
+            scores: (1..=n).collect(),
+        };
+    }
+
+    /// Rock, Paper and Scissors, worth 1, 2 and 3.
+    pub fn rock_paper_scissors() -> MoveSet {
+        return MoveSet::from_rules(
+            &["Rock", "Paper", "Scissors"],
+            &[
+                ("Rock", "Scissors"),
+                ("Scissors", "Paper"),
+                ("Paper", "Rock"),
+            ],
+        );
+    }
+
+    /// Rock, Paper, Scissors, Lizard and Spock, worth 1 to 5.
+    pub fn rock_paper_scissors_lizard_spock() -> MoveSet {
+        return MoveSet::from_rules(
+            &["Rock", "Paper", "Scissors", "Lizard", "Spock"],
+            &[
+                ("Scissors", "Paper"),
+                ("Paper", "Rock"),
+                ("Rock", "Lizard"),
+                ("Lizard", "Spock"),
+                ("Spock", "Scissors"),
+                ("Scissors", "Lizard"),
+                ("Lizard", "Paper"),
+                ("Paper", "Spock"),
+                ("Spock", "Rock"),
+                ("Rock", "Scissors"),
+            ],
+        );
+    }
+
+    /// Give each move its own score instead of 1, 2, 3...
+    pub fn with_scores(mut self, scores: &[i32]) -> MoveSet {
+        if scores.len() != self.names.len() {
+            panic!(
+                "Can't score {} moves with {} scores",
+                self.names.len(),
+                scores.len()
+            );
+        }
+        self.scores = scores.to_vec();
+        return self;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.names.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.names.is_empty();
+    }
+
+    pub fn name(&self, index: usize) -> &str {
+        return &self.names[index];
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        return self.names.iter().position(|n| n == name);
+    }
+
+    pub fn score(&self, index: usize) -> i32 {
+        return self.scores[index];
+    }
 
-fn get_match_point(opponent: &RockPaperScissors, you: &RockPaperScissors) -> i32 {
-    if *opponent == *you {
-        return DRAW_POINTS;
-    } else if *opponent == RockPaperScissors::Rock && *you == RockPaperScissors::Scissors {
-        return LOSE_POINTS;
-    } else if *opponent == RockPaperScissors::Scissors && *you == RockPaperScissors::Paper {
-        return LOSE_POINTS;
-    } else if *opponent == RockPaperScissors::Paper && *you == RockPaperScissors::Rock {
-        return LOSE_POINTS;
-    }
-    return WIN_POINTS;
+    /// How it goes for you, throwing `you` against `them`.
+    pub fn outcome(&self, them: usize, you: usize) -> Outcome {
+        if self.beats[you][them] {
+            return Outcome::Win;
+        }
+        if self.beats[them][you] {
+            return Outcome::Lose;
+        }
+        return Outcome::Draw;
+    }
+
+    /// The best scoring move that gets the outcome against `them`, if there is one.
+    pub fn move_for(&self, them: usize, outcome: Outcome) -> Option<usize> {
+        return (0..self.len())
+            .filter(|you| self.outcome(them, *you) == outcome)
+            .max_by_key(|you| self.scores[*you]);
+    }
 }
 
-fn get_throw_points(throw: &RockPaperScissors) -> i32 {
-    return match throw {
-        RockPaperScissors::Rock => 1,
-        RockPaperScissors::Paper => 2,
-        RockPaperScissors::Scissors => 3,
-    };
+/// What the second column of the strategy guide is taken to mean.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Meaning {
+    /// Throw this move.
+    Throw(usize),
+    /// Throw whatever gets this outcome.
+    Aim(Outcome),
 }
 
-/// Get the score for the puzzle
-/// ```
-/// let vec1: Vec<String> = vec!["A Y", "B X", "C Z"].iter().map(|s| s.to_string()).collect();
-/// assert_eq!(day02::puzzle_a(&vec1), 15);
-/// ```
-pub fn puzzle_a(raw_matches: &Vec<String>) -> i32 {
-    let matches = parse_input_puzzle_1(raw_matches);
-    return matches
-        .iter()
-        .map(|(a, b)| get_match_point(a, b) + get_throw_points(b))
-        .sum();
+/// What the letters in each column of the strategy guide stand for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrategyGuide {
+    opponent: Vec<(String, usize)>,
+    response: Vec<(String, Meaning)>,
 }
 
-fn match_result_to_rps(opponent: &RockPaperScissors, you: &str) -> RockPaperScissors {
-    if you == "Y" {
-        return *opponent;
-    } else if you == "X" {
-        // Lose
-        return match opponent {
-            RockPaperScissors::Rock => RockPaperScissors::Scissors,
-            RockPaperScissors::Paper => RockPaperScissors::Rock,
-            RockPaperScissors::Scissors => RockPaperScissors::Paper,
-        };
-    } else if you == "Z" {
-        // Win
-        return match opponent {
-            RockPaperScissors::Rock => RockPaperScissors::Paper,
-            RockPaperScissors::Paper => RockPaperScissors::Scissors,
-            RockPaperScissors::Scissors => RockPaperScissors::Rock,
+impl StrategyGuide {
+    /// Letters for the opponent's moves, in move order, and what each response letter means.
+    pub fn new(opponent: &[&str], response: &[(&str, Meaning)]) -> StrategyGuide {
+        return StrategyGuide {
+            opponent: opponent
+                .iter()
+                .enumerate()
+                .map(|(i, l)| (l.to_string(), i))
+                .collect(),
+            response: response.iter().map(|(l, m)| (l.to_string(), *m)).collect(),
         };
     }
-    // No error handling only rock.
-    return RockPaperScissors::Rock;
+
+    /// A, B, C for the opponent and X, Y, Z for your moves.
+    pub fn puzzle_a() -> StrategyGuide {
+        return StrategyGuide::new(
+            &["A", "B", "C"],
+            &[
+                ("X", Meaning::Throw(0)),
+                ("Y", Meaning::Throw(1)),
+                ("Z", Meaning::Throw(2)),
+            ],
+        );
+    }
+
+    /// A, B, C for the opponent and X, Y, Z to lose, draw or win.
+    pub fn puzzle_b() -> StrategyGuide {
+        return StrategyGuide::new(
+            &["A", "B", "C"],
+            &[
+                ("X", Meaning::Aim(Outcome::Lose)),
+                ("Y", Meaning::Aim(Outcome::Draw)),
+                ("Z", Meaning::Aim(Outcome::Win)),
+            ],
+        );
+    }
+
+    pub fn meaning(&self, letter: &str) -> Option<Meaning> {
+        return self
+            .response
+            .iter()
+            .find(|(l, _)| l == letter)
+            .map(|(_, m)| *m);
+    }
+
+    /// The response letters, in order.
+    pub fn response_letters(&self) -> Vec<&str> {
+        return self.response.iter().map(|(l, _)| l.as_str()).collect();
+    }
 }
 
-fn parse_line_puzzle_2(s: &String) -> (RockPaperScissors, RockPaperScissors) {
-    let mut split_value = s.split_whitespace();
-    let first = abc_to_rps(split_value.next().unwrap_or(""));
-    let second = match_result_to_rps(&first, split_value.next().unwrap_or(""));
-    return (first, second);
+/// Why a round of the strategy guide couldn't be played, with the line counting from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuideError {
+    BadLine {
+        line: usize,
+        text: String,
+    },
+    UnknownLetter {
+        line: usize,
+        letter: String,
+    },
+    /// No move gets the outcome the guide asks for.
+    Impossible {
+        line: usize,
+        outcome: Outcome,
+    },
 }
 
-pub fn parse_input_puzzle_2(input: &Vec<String>) -> Vec<(RockPaperScissors, RockPaperScissors)> {
-    return input.iter().map(|s| parse_line_puzzle_2(s)).collect();
+impl Display for GuideError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            GuideError::BadLine { line, text } => {
+                write!(f, "line {}: expected two letters, found '{}'", line, text)
+            }
+            GuideError::UnknownLetter { line, letter } => {
+                write!(
+                    f,
+                    "line {}: the guide doesn't say what {} means",
+                    line, letter
+                )
+            }
+            GuideError::Impossible { line, outcome } => {
+                write!(f, "line {}: no move can {}", line, outcome)
+            }
+        };
+    }
 }
 
-/// Get the score for the puzzle
-/// ```
-/// let vec1: Vec<String> = vec!["A Y", "B X", "C Z"].iter().map(|s| s.to_string()).collect();
-/// assert_eq!(day02::puzzle_b(&vec1), 12);
-/// ```
-pub fn puzzle_b(raw_matches: &Vec<String>) -> i32 {
-    let matches = parse_input_puzzle_2(raw_matches);
-    return matches
-        .iter()
-        .map(|(a, b)| get_match_point(a, b) + get_throw_points(b))
-        .sum();
+/// A move set with points for each outcome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    pub moves: MoveSet,
+    pub lose_points: i32,
+    pub draw_points: i32,
+    pub win_points: i32,
 }
 
-// ---------------- Alternative solution start -------------------
-const ROCK: i32 = 0;
-const PAPER: i32 = 1;
-const SCISSORS: i32 = 2;
-
-fn abc_to_rps_i(i: &str) -> i32 {
-    return match i {
-        "A" => ROCK,
-        "B" => PAPER,
-        "C" => SCISSORS,
-        _ => 999999999,
-    };
+impl Game {
+    /// 0, 3 and 6 points for losing, drawing and winning.
+    pub fn new(moves: MoveSet) -> Game {
+        return Game {
+            moves: moves,
+            lose_points: 0,
+            draw_points: 3,
+            win_points: 6,
+        };
+    }
+
+    pub fn outcome_points(&self, outcome: Outcome) -> i32 {
+        return match outcome {
+            Outcome::Lose => self.lose_points,
+            Outcome::Draw => self.draw_points,
+            Outcome::Win => self.win_points,
+        };
+    }
+
+    /// Your score for throwing `you` against `them`.
+    pub fn round_score(&self, them: usize, you: usize) -> i32 {
+        return self.outcome_points(self.moves.outcome(them, you)) + self.moves.score(you);
+    }
+
+    /// Both moves of every round, read with the guide.
+    pub fn rounds(
+        &self,
+        guide: &StrategyGuide,
+        lines: &[String],
+    ) -> Result<Vec<(usize, usize)>, GuideError> {
+        let mut rounds = vec![];
+        for (i, text) in lines.iter().enumerate() {
+            let line = i + 1;
+            let letters: Vec<&str> = text.split_whitespace().collect();
+            if letters.len() != 2 {
+                return Err(GuideError::BadLine {
+                    line: line,
+                    text: text.to_string(),
+                });
+            }
+            let unknown = |letter: &str| GuideError::UnknownLetter {
+                line: line,
+                letter: letter.to_string(),
+            };
+            let them = guide
+                .opponent
+                .iter()
+                .find(|(l, m)| l == letters[0] && *m < self.moves.len())
+                .map(|(_, m)| *m)
+                .ok_or_else(|| unknown(letters[0]))?;
+            let you = match guide
+                .meaning(letters[1])
+                .ok_or_else(|| unknown(letters[1]))?
+            {
+                Meaning::Throw(m) if m < self.moves.len() => m,
+                Meaning::Throw(_) => return Err(unknown(letters[1])),
+                Meaning::Aim(outcome) => {
+                    self.moves
+                        .move_for(them, outcome)
+                        .ok_or(GuideError::Impossible {
+                            line: line,
+                            outcome: outcome,
+                        })?
+                }
+            };
+            rounds.push((them, you));
+        }
+        return Ok(rounds);
+    }
+
+    /// Your total score from following the guide.
+    /// ```
+    /// let game = day02::Game::new(day02::MoveSet::rock_paper_scissors());
+    /// let lines: Vec<String> = vec!["A Y", "B X", "C Z"].iter().map(|s| s.to_string()).collect();
+    /// assert_eq!(game.score(&day02::StrategyGuide::puzzle_a(), &lines), Ok(15));
+    /// ```
+    pub fn score(&self, guide: &StrategyGuide, lines: &[String]) -> Result<i32, GuideError> {
+        let rounds = self.rounds(guide, lines)?;
+        return Ok(rounds
+            .iter()
+            .map(|(them, you)| self.round_score(*them, *you))
+            .sum());
+    }
+
+    /// Try every way of giving the response letters different meanings, and keep the
+    /// one that scores most. Ties go to the first found, trying moves before outcomes.
+    /// ```
+    /// let game = day02::Game::new(day02::MoveSet::rock_paper_scissors());
+    /// let lines: Vec<String> = vec!["A Y", "B X", "C Z"].iter().map(|s| s.to_string()).collect();
+    /// let (guide, score) = game.best_interpretation(&day02::StrategyGuide::puzzle_a(), &lines).unwrap();
+    /// assert_eq!(score, 24);
+    /// assert_eq!(guide.meaning("Y"), Some(day02::Meaning::Throw(1)));
+    /// ```
+    pub fn best_interpretation(
+        &self,
+        guide: &StrategyGuide,
+        lines: &[String],
+    ) -> Option<(StrategyGuide, i32)> {
+        let mut choices: Vec<Meaning> = (0..self.moves.len()).map(Meaning::Throw).collect();
+        choices.extend(Outcome::ALL.iter().map(|o| Meaning::Aim(*o)));
+        let letters = guide.response_letters();
+        let mut best: Option<(StrategyGuide, i32)> = None;
+        let mut picked: Vec<Meaning> = vec![];
+        self.try_meanings(guide, lines, &letters, &choices, &mut picked, &mut best);
+        return best;
+    }
+
+    fn try_meanings(
+        &self,
+        guide: &StrategyGuide,
+        lines: &[String],
+        letters: &[&str],
+        choices: &[Meaning],
+        picked: &mut Vec<Meaning>,
+        best: &mut Option<(StrategyGuide, i32)>,
+    ) {
+        if picked.len() == letters.len() {
+            let candidate = StrategyGuide {
+                opponent: guide.opponent.clone(),
+                response: letters
+                    .iter()
+                    .map(|l| l.to_string())
+                    .zip(picked.iter().copied())
+                    .collect(),
+            };
+            if let Ok(score) = self.score(&candidate, lines) {
+                if best.as_ref().is_none_or(|(_, s)| score > *s) {
+                    *best = Some((candidate, score));
+                }
+            }
+            return;
+        }
+        for choice in choices {
+            if picked.contains(choice) {
+                continue;
+            }
+            picked.push(*choice);
+            self.try_meanings(guide, lines, letters, choices, picked, best);
+            picked.pop();
+        }
+    }
 }
 
-fn xyz_to_rps_i(i: &str) -> i32 {
-    return match i {
-        "X" => ROCK,
-        "Y" => PAPER,
-        "Z" => SCISSORS,
-        _ => 999999,
+fn play(game: &Game, guide: &StrategyGuide, lines: &Vec<String>) -> Vec<(usize, usize)> {
+    return match game.rounds(guide, lines) {
+        Ok(rounds) => rounds,
+        Err(e) => panic!("Can't follow the strategy guide: {}", e),
     };
 }
 
-fn xyz_to_rps_i_b(a: i32, i: &str) -> i32 {
-    return (match i {
-        // Losing: All loses are you being one less than the opponent, looping back to 2 when a = 0.
-        "X" => mathlib::modulus(a - 1, 3),
-        // Tie: Return same value
-        "Y" => a,
-        // Winning: All wins are you being one ahead of the opponent, looping back to 0 when a = 2.
-        "Z" => mathlib::modulus(a + 1, 3),
-        _ => 99999,
-    }) % 3;
+fn total(game: &Game, rounds: &[(usize, usize)]) -> i32 {
+    return rounds
+        .iter()
+        .map(|(them, you)| game.round_score(*them, *you))
+        .sum();
 }
 
-fn get_round_value(opponent: i32, you: i32) -> i32 {
-    // (you - opponent + 1) % 3 gets 0 on loss, 1 on tie, 2 on win
-    // multiply by 3 gets points.
-    let match_points = mathlib::modulus(you - opponent + 1, 3) * 3;
-    // you + 1 = correct points
-    return match_points + you + 1;
+/// Get the score for the puzzle
+/// ```
+/// let vec1: Vec<String> = vec!["A Y", "B X", "C Z"].iter().map(|s| s.to_string()).collect();
+/// assert_eq!(day02::puzzle_a(&vec1), 15);
+/// ```
+pub fn puzzle_a(raw_matches: &Vec<String>) -> i32 {
+    let game = Game::new(MoveSet::rock_paper_scissors());
+    return total(&game, &play(&game, &StrategyGuide::puzzle_a(), raw_matches));
 }
 
-fn parse_line_puzzle_1_sol2(s: &String) -> (i32, i32) {
-    let mut split_value = s.split_whitespace();
-    let first = abc_to_rps_i(split_value.next().unwrap_or(""));
-    let second = xyz_to_rps_i(split_value.next().unwrap_or(""));
-    return (first, second);
+/// Get the score for the puzzle
+/// ```
+/// let vec1: Vec<String> = vec!["A Y", "B X", "C Z"].iter().map(|s| s.to_string()).collect();
+/// assert_eq!(day02::puzzle_b(&vec1), 12);
+/// ```
+pub fn puzzle_b(raw_matches: &Vec<String>) -> i32 {
+    let game = Game::new(MoveSet::rock_paper_scissors());
+    return total(&game, &play(&game, &StrategyGuide::puzzle_b(), raw_matches));
 }
 
-fn parse_line_puzzle_2_sol2(s: &String) -> (i32, i32) {
-    let mut split_value = s.split_whitespace();
-    let first = abc_to_rps_i(split_value.next().unwrap_or(""));
-    let second = xyz_to_rps_i_b(first, split_value.next().unwrap_or(""));
-    return (first, second);
+// ---------------- Alternative solution start -------------------
+// The same engine, with who beats who worked out by modular arithmetic.
+
+fn cyclic_game() -> Game {
+    return Game::new(MoveSet::cyclic(&["Rock", "Paper", "Scissors"]));
 }
 
 /// Get the score for the puzzle
@@ -187,11 +466,8 @@ fn parse_line_puzzle_2_sol2(s: &String) -> (i32, i32) {
 /// assert_eq!(day02::puzzle_a_sol2(&vec1), 15);
 /// ```
 pub fn puzzle_a_sol2(raw_matches: &Vec<String>) -> i32 {
-    return raw_matches
-        .iter()
-        .map(|s| parse_line_puzzle_1_sol2(s))
-        .map(|(a, b)| get_round_value(a, b))
-        .sum();
+    let game = cyclic_game();
+    return total(&game, &play(&game, &StrategyGuide::puzzle_a(), raw_matches));
 }
 
 /// Get the score for the puzzle
@@ -200,68 +476,166 @@ pub fn puzzle_a_sol2(raw_matches: &Vec<String>) -> i32 {
 /// assert_eq!(day02::puzzle_b_sol2(&vec1), 12);
 /// ```
 pub fn puzzle_b_sol2(raw_matches: &Vec<String>) -> i32 {
-    return raw_matches
-        .iter()
-        .map(|s| parse_line_puzzle_2_sol2(s))
-        .map(|(a, b)| get_round_value(a, b))
-        .sum();
+    let game = cyclic_game();
+    return total(&game, &play(&game, &StrategyGuide::puzzle_b(), raw_matches));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn to_lines(lines: &[&str]) -> Vec<String> {
+        return lines.iter().map(|s| s.to_string()).collect();
+    }
+
     // Logic solution unit tests
     #[test]
-    fn test_parse_input_1() {
-        let vec1: Vec<String> = vec!["A Y", "B X", "C Z"]
-            .iter()
-            .map(|s| s.to_string())
-            .collect();
-        let vec2 = vec![
-            (RockPaperScissors::Rock, RockPaperScissors::Paper),
-            (RockPaperScissors::Paper, RockPaperScissors::Rock),
-            (RockPaperScissors::Scissors, RockPaperScissors::Scissors),
-        ];
-        assert_eq!(parse_input_puzzle_1(&vec1), vec2);
+    fn test_rounds_puzzle_a() {
+        let game = Game::new(MoveSet::rock_paper_scissors());
+        let rounds = game.rounds(
+            &StrategyGuide::puzzle_a(),
+            &to_lines(&["A Y", "B X", "C Z"]),
+        );
+        // Rock against Paper, Paper against Rock, Scissors against Scissors.
+        assert_eq!(rounds, Ok(vec![(0, 1), (1, 0), (2, 2)]));
     }
 
     #[test]
-    fn test_parse_input_2() {
-        let vec1: Vec<String> = vec!["A Y", "B X", "C Z"]
-            .iter()
-            .map(|s| s.to_string())
-            .collect();
-        let vec2 = vec![
-            (RockPaperScissors::Rock, RockPaperScissors::Rock),
-            (RockPaperScissors::Paper, RockPaperScissors::Rock),
-            (RockPaperScissors::Scissors, RockPaperScissors::Rock),
-        ];
-        assert_eq!(parse_input_puzzle_2(&vec1), vec2);
+    fn test_rounds_puzzle_b() {
+        let game = Game::new(MoveSet::rock_paper_scissors());
+        let rounds = game.rounds(
+            &StrategyGuide::puzzle_b(),
+            &to_lines(&["A Y", "B X", "C Z"]),
+        );
+        // Draw with Rock, lose to Paper with Rock, beat Scissors with Rock.
+        assert_eq!(rounds, Ok(vec![(0, 0), (1, 0), (2, 0)]));
     }
 
     // Alternative solution unit tests
     #[test]
-    fn test_parse_line_puzzle_2_sol2_first_line() {
-        let s = "A Y".to_string();
-        assert_eq!(parse_line_puzzle_2_sol2(&s), (ROCK, ROCK));
+    fn test_cyclic_rounds_puzzle_b() {
+        let game = cyclic_game();
+        let (rock, paper, scissors) = (0, 1, 2);
+        let lines = to_lines(&["A Y", "B X", "C Z", "A X"]);
+        assert_eq!(
+            game.rounds(&StrategyGuide::puzzle_b(), &lines),
+            Ok(vec![
+                (rock, rock),
+                (paper, rock),
+                (scissors, rock),
+                (rock, scissors)
+            ])
+        );
     }
 
+    // Engine unit tests
     #[test]
-    fn test_parse_line_puzzle_2_sol2_second_line() {
-        let s = "B X".to_string();
-        assert_eq!(parse_line_puzzle_2_sol2(&s), (PAPER, ROCK));
+    fn test_cyclic_matches_rules() {
+        let rules = MoveSet::rock_paper_scissors();
+        let cyclic = MoveSet::cyclic(&["Rock", "Paper", "Scissors"]);
+        assert_eq!(rules, cyclic);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't make a fair circle of 4 moves")]
+    fn test_cyclic_even_moves() {
+        MoveSet::cyclic(&["Rock", "Paper", "Scissors", "Well"]);
+    }
+
+    #[test]
+    fn test_lizard_spock() {
+        let moves = MoveSet::rock_paper_scissors_lizard_spock();
+        for m in 0..moves.len() {
+            let wins = (0..moves.len())
+                .filter(|o| moves.outcome(*o, m) == Outcome::Win)
+                .count();
+            let losses = (0..moves.len())
+                .filter(|o| moves.outcome(*o, m) == Outcome::Lose)
+                .count();
+            assert_eq!((wins, losses), (2, 2), "{}", moves.name(m));
+        }
+        let spock = moves.index_of("Spock").unwrap();
+        let rock = moves.index_of("Rock").unwrap();
+        assert_eq!(moves.outcome(rock, spock), Outcome::Win);
+        // Of the moves that beat Spock (Paper, Lizard) and lose to Rock (Scissors, Lizard),
+        // Lizard scores most.
+        assert_eq!(
+            moves.move_for(spock, Outcome::Win),
+            moves.index_of("Lizard")
+        );
+        assert_eq!(
+            moves.move_for(rock, Outcome::Lose),
+            moves.index_of("Lizard")
+        );
+
+        let game = Game::new(moves);
+        let guide = StrategyGuide::new(
+            &["A", "B", "C", "D", "E"],
+            &[
+                ("V", Meaning::Throw(0)),
+                ("W", Meaning::Throw(1)),
+                ("X", Meaning::Throw(2)),
+                ("Y", Meaning::Throw(3)),
+                ("Z", Meaning::Throw(4)),
+            ],
+        );
+        // Spock vaporizes Rock (6 + 5), then Lizard against Lizard (3 + 4).
+        assert_eq!(game.score(&guide, &to_lines(&["A Z", "D Y"])), Ok(18));
     }
 
     #[test]
-    fn test_parse_line_puzzle_2_sol2_third_line() {
-        let s = "C Z".to_string();
-        assert_eq!(parse_line_puzzle_2_sol2(&s), (SCISSORS, ROCK));
+    fn test_guide_errors() {
+        let game = Game::new(MoveSet::rock_paper_scissors());
+        let guide = StrategyGuide::puzzle_a();
+        assert_eq!(
+            game.score(&guide, &to_lines(&["A Y", "A"])),
+            Err(GuideError::BadLine {
+                line: 2,
+                text: "A".to_string()
+            })
+        );
+        assert_eq!(
+            game.score(&guide, &to_lines(&["D Y"]))
+                .unwrap_err()
+                .to_string(),
+            "line 1: the guide doesn't say what D means"
+        );
+        // Nothing beats a move that beats everything.
+        let bully = Game::new(MoveSet::from_rules(&["Big", "Small"], &[("Big", "Small")]));
+        assert_eq!(
+            bully.score(&StrategyGuide::puzzle_b(), &to_lines(&["A Z"])),
+            Err(GuideError::Impossible {
+                line: 1,
+                outcome: Outcome::Win
+            })
+        );
     }
 
     #[test]
-    fn test_parse_line_puzzle_2_sol2_rock_lose() {
-        let s = "A X".to_string();
-        assert_eq!(parse_line_puzzle_2_sol2(&s), (ROCK, SCISSORS));
+    fn test_best_interpretation_is_the_optimum() {
+        let game = Game::new(MoveSet::rock_paper_scissors());
+        let lines = to_lines(&["A Y", "B X", "C Z", "A X", "A X", "B Z", "C Y"]);
+        let (best, score) = game
+            .best_interpretation(&StrategyGuide::puzzle_a(), &lines)
+            .unwrap();
+        assert_eq!(game.score(&best, &lines), Ok(score));
+        // Score all 6 * 5 * 4 ways of giving X, Y and Z different meanings.
+        let mut choices: Vec<Meaning> = (0..3).map(Meaning::Throw).collect();
+        choices.extend(Outcome::ALL.iter().map(|o| Meaning::Aim(*o)));
+        let mut scores = vec![];
+        for x in &choices {
+            for y in choices.iter().filter(|y| *y != x) {
+                for z in choices.iter().filter(|z| *z != x && *z != y) {
+                    let guide =
+                        StrategyGuide::new(&["A", "B", "C"], &[("X", *x), ("Y", *y), ("Z", *z)]);
+                    scores.push(game.score(&guide, &lines).unwrap());
+                }
+            }
+        }
+        assert_eq!(scores.len(), 120);
+        assert_eq!(scores.iter().max(), Some(&score));
+        // By hand: X to win (25), Y for Rock (11) and Z for Scissors (15), which ties with
+        // X for Paper (21), Y to win (15) and Z for Scissors (15).
+        assert_eq!(score, 51);
     }
 }
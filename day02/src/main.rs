@@ -3,11 +3,36 @@ use day02::puzzle_a;
 use day02::puzzle_a_sol2;
 use day02::puzzle_b;
 use day02::puzzle_b_sol2;
+use day02::Game;
+use day02::Meaning;
+use day02::MoveSet;
+use day02::StrategyGuide;
+use std::env;
 
 fn main() {
     let filename = "input";
     let rounds = load_no_blanks(filename);
 
+    // --best prints the reading of X, Y and Z that scores most.
+    let args: Vec<String> = env::args().collect();
+    if args.iter().any(|a| a == "--best") {
+        let game = Game::new(MoveSet::rock_paper_scissors());
+        match game.best_interpretation(&StrategyGuide::puzzle_a(), &rounds) {
+            Some((guide, score)) => {
+                for letter in guide.response_letters() {
+                    match guide.meaning(letter) {
+                        Some(Meaning::Throw(m)) => println!("{}: {}", letter, game.moves.name(m)),
+                        Some(Meaning::Aim(o)) => println!("{}: {}", letter, o),
+                        None => {}
+                    }
+                }
+                println!("Best score: {}", score);
+            }
+            None => println!("No reading of the guide can be played"),
+        }
+        return;
+    }
+
     let value = puzzle_a(&rounds);
     println!("Answer to 1st question: {}", value);
 
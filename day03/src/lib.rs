@@ -2,48 +2,241 @@ extern crate filelib;
 
 pub use filelib::load_no_blanks;
 
-use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
 
-fn to_set(s: &String) -> HashSet<char> {
-    let mut set = HashSet::new();
-    for c in s.chars() {
-        set.insert(c);
+/// Why a rucksack or group couldn't be checked, with lines counting from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RucksackError {
+    /// Only a-z and A-Z are items.
+    BadItem { line: usize, item: char },
+    /// The rucksack doesn't split evenly into the compartments.
+    UnevenCompartments {
+        line: usize,
+        length: usize,
+        compartments: usize,
+    },
+    /// The last group is short of rucksacks.
+    IncompleteGroup { size: usize, left: usize },
+    /// Groups and compartments need at least one member.
+    ZeroSize,
+}
+
+impl Display for RucksackError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            RucksackError::BadItem { line, item } => {
+                write!(f, "line {}: '{}' isn't an item", line, item)
+            }
+            RucksackError::UnevenCompartments {
+                line,
+                length,
+                compartments,
+            } => write!(
+                f,
+                "line {}: {} items don't split into {} compartments",
+                line, length, compartments
+            ),
+            RucksackError::IncompleteGroup { size, left } => write!(
+                f,
+                "the last group has {} rucksacks instead of {}",
+                left, size
+            ),
+            RucksackError::ZeroSize => write!(f, "can't split into groups of 0"),
+        };
+    }
+}
+
+/// A set of items as a bitmask, with priority p stored in bit p - 1.
+/// ```
+/// let a = day03::ItemSet::from_items("abcZ").unwrap();
+/// let b = day03::ItemSet::from_items("cZz").unwrap();
+/// assert_eq!(a.intersect(b).items(), vec!['c', 'Z']);
+/// assert_eq!(a.intersect(b).priority_sum(), 3 + 52);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ItemSet(u64);
+
+impl ItemSet {
+    pub fn new() -> ItemSet {
+        return ItemSet(0);
+    }
+
+    /// Every item, a to Z.
+    pub fn all() -> ItemSet {
+        return ItemSet((1 << 52) - 1);
+    }
+
+    /// The set of items in a string, or the first character that isn't an item.
+    pub fn from_items(items: &str) -> Result<ItemSet, char> {
+        let mut set = ItemSet::new();
+        for c in items.chars() {
+            if !set.insert(c) {
+                return Err(c);
+            }
+        }
+        return Ok(set);
+    }
+
+    /// Adds the item, returning false if it isn't one.
+    pub fn insert(&mut self, item: char) -> bool {
+        return match priority(item) {
+            Some(p) => {
+                self.0 |= 1 << (p - 1);
+                true
+            }
+            None => false,
+        };
+    }
+
+    pub fn contains(&self, item: char) -> bool {
+        return match priority(item) {
+            Some(p) => self.0 & (1 << (p - 1)) != 0,
+            None => false,
+        };
+    }
+
+    pub fn intersect(self, other: ItemSet) -> ItemSet {
+        return ItemSet(self.0 & other.0);
+    }
+
+    pub fn union(self, other: ItemSet) -> ItemSet {
+        return ItemSet(self.0 | other.0);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.0.count_ones() as usize;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.0 == 0;
+    }
+
+    /// The items in priority order.
+    pub fn items(&self) -> Vec<char> {
+        return (1..=52)
+            .filter(|p| self.0 & (1 << (p - 1)) != 0)
+            .map(item_for_priority)
+            .collect();
+    }
+
+    pub fn priority_sum(&self) -> u32 {
+        return (1..=52).filter(|p| self.0 & (1 << (p - 1)) != 0).sum();
+    }
+}
+
+/// a through z are 1 through 26, and A through Z are 27 through 52.
+/// ```
+/// assert_eq!(day03::priority('p'), Some(16));
+/// assert_eq!(day03::priority('L'), Some(38));
+/// assert_eq!(day03::priority('!'), None);
+/// ```
+pub fn priority(item: char) -> Option<u32> {
+    return match item {
+        'a'..='z' => Some(item as u32 - 'a' as u32 + 1),
+        'A'..='Z' => Some(item as u32 - 'A' as u32 + 27),
+        _ => None,
+    };
+}
+
+fn item_for_priority(p: u32) -> char {
+    if p <= 26 {
+        return (b'a' + (p - 1) as u8) as char;
     }
-    return set;
+    return (b'A' + (p - 27) as u8) as char;
 }
 
-fn to_set_from_str(s: &str) -> HashSet<char> {
-    let mut set = HashSet::new();
-    for c in s.chars() {
-        set.insert(c);
+fn items_on_line(items: &str, line: usize) -> Result<ItemSet, RucksackError> {
+    return ItemSet::from_items(items).map_err(|c| RucksackError::BadItem {
+        line: line,
+        item: c,
+    });
+}
+
+/// Split a rucksack into equally sized compartments. `line` is only used for errors.
+pub fn compartments(
+    rucksack: &str,
+    count: usize,
+    line: usize,
+) -> Result<Vec<ItemSet>, RucksackError> {
+    if count == 0 {
+        return Err(RucksackError::ZeroSize);
+    }
+    let items: Vec<char> = rucksack.chars().collect();
+    if !items.len().is_multiple_of(count) {
+        return Err(RucksackError::UnevenCompartments {
+            line: line,
+            length: items.len(),
+            compartments: count,
+        });
     }
-    return set;
+    let size = (items.len() / count).max(1);
+    let mut sets: Vec<ItemSet> = vec![];
+    for part in items.chunks(size) {
+        sets.push(items_on_line(&part.iter().collect::<String>(), line)?);
+    }
+    sets.resize(count, ItemSet::new());
+    return Ok(sets);
 }
 
-fn split_rutsack(rutsack: &String) -> (HashSet<char>, HashSet<char>) {
-    let half_index = rutsack.len() / 2;
-    let first = &rutsack[..half_index];
-    let second = &rutsack[half_index..];
-    let compartment_a = to_set_from_str(first);
-    let compartment_b = to_set_from_str(second);
-    return (compartment_a, compartment_b);
+fn common(sets: &[ItemSet]) -> ItemSet {
+    return sets.iter().fold(ItemSet::all(), |acc, s| acc.intersect(*s));
 }
 
-fn find_common_item(compartment_a: &HashSet<char>, compartment_b: &HashSet<char>) -> char {
-    let mut intersection = compartment_a.intersection(compartment_b);
-    return *(intersection.next().unwrap());
+/// The items found in every compartment of each rucksack.
+/// ```
+/// let input: Vec<String> = vec!["abcabc", "aaZbZc"].iter().map(|s| s.to_string()).collect();
+/// let common = day03::common_in_compartments(&input, 3).unwrap();
+/// assert_eq!(common[0].items(), vec![]);
+/// assert_eq!(common[1].items(), vec![]);
+/// let common = day03::common_in_compartments(&input, 2).unwrap();
+/// assert_eq!(common[0].items(), vec!['a', 'b', 'c']);
+/// assert_eq!(common[1].items(), vec!['Z']);
+/// ```
+pub fn common_in_compartments(
+    input: &Vec<String>,
+    count: usize,
+) -> Result<Vec<ItemSet>, RucksackError> {
+    return input
+        .iter()
+        .enumerate()
+        .map(|(i, rucksack)| Ok(common(&compartments(rucksack, count, i + 1)?)))
+        .collect();
 }
 
-fn get_char_point(c: char) -> i32 {
-    let i = c as u32;
-    if i >= 97 {
-        // 1 through 26 lower case
-        // little A starts at 97
-        return i32::try_from(i - 96).unwrap_or(-999);
+/// The items found in every rucksack of each group of `size` rucksacks.
+/// ```
+/// let input: Vec<String> = vec!["abX", "bXc", "Xbd", "zz"].iter().map(|s| s.to_string()).collect();
+/// let common = day03::common_in_groups(&input, 2).unwrap();
+/// assert_eq!(common[0].items(), vec!['b', 'X']);
+/// assert_eq!(common[1].items(), vec![]);
+/// assert!(day03::common_in_groups(&input, 3).is_err());
+/// ```
+pub fn common_in_groups(input: &Vec<String>, size: usize) -> Result<Vec<ItemSet>, RucksackError> {
+    if size == 0 {
+        return Err(RucksackError::ZeroSize);
+    }
+    if !input.len().is_multiple_of(size) {
+        return Err(RucksackError::IncompleteGroup {
+            size: size,
+            left: input.len() % size,
+        });
     }
-    // Capital A starts at 65
-    // 27 through 52 upper case
-    return i32::try_from(i - 64 + 26).unwrap_or(-999);
+    let mut groups = vec![];
+    for (g, chunk) in input.chunks(size).enumerate() {
+        let mut sets = vec![];
+        for (i, rucksack) in chunk.iter().enumerate() {
+            sets.push(items_on_line(rucksack, g * size + i + 1)?);
+        }
+        groups.push(common(&sets));
+    }
+    return Ok(groups);
+}
+
+fn total_priority(sets: Result<Vec<ItemSet>, RucksackError>) -> u32 {
+    return match sets {
+        Ok(sets) => sets.iter().map(|s| s.priority_sum()).sum(),
+        Err(e) => panic!("Can't check rucksacks: {}", e),
+    };
 }
 
 /// Solution to puzzle_a entry point
@@ -53,13 +246,7 @@ fn get_char_point(c: char) -> i32 {
 /// assert_eq!(day03::puzzle_a(&vec1), 157);
 /// ```
 pub fn puzzle_a(input: &Vec<String>) -> i32 {
-    return input
-        .iter()
-        .map(|rutsack| {
-            let (a, b) = split_rutsack(rutsack);
-            get_char_point(find_common_item(&a, &b))
-        })
-        .sum();
+    return total_priority(common_in_compartments(input, 2)) as i32;
 }
 
 /// Solution to puzzle_b entry point
@@ -69,19 +256,7 @@ pub fn puzzle_a(input: &Vec<String>) -> i32 {
 /// assert_eq!(day03::puzzle_b(&vec1), 70);
 /// ```
 pub fn puzzle_b(input: &Vec<String>) -> i32 {
-    let chunks = input.chunks(3);
-    return chunks
-        .map(|chunk| get_char_point(process_chunk(chunk)))
-        .sum();
-}
-
-fn process_chunk(chunk: &[String]) -> char {
-    let mut first: HashSet<char> = to_set(&chunk[0]);
-    for new_item in chunk.iter().skip(1) {
-        let next: HashSet<char> = to_set(new_item);
-        first.retain(|&k| next.contains(&k));
-    }
-    return *(first.iter().next().unwrap());
+    return total_priority(common_in_groups(input, 3)) as i32;
 }
 
 #[cfg(test)]
@@ -89,44 +264,100 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_split_rutsack() {
-        let s = "vJrwpWtwJgWrhcsFMMfFFhFp".to_string();
-        let (one, two) = split_rutsack(&s);
+    fn test_compartments_in_half() {
+        let sets = compartments("vJrwpWtwJgWrhcsFMMfFFhFp", 2, 1).unwrap();
         // vJrwpWtwJgWr remove duplicates = 8 letters
-        assert_eq!(one.len(), 8);
+        assert_eq!(sets[0].len(), 8);
         // hcsFMMfFFhFp remove duplicates = 7 letters
-        assert_eq!(two.len(), 7);
+        assert_eq!(sets[1].len(), 7);
     }
 
     #[test]
-    fn test_find_common_item() {
-        let s = "vJrwpWtwJgWrhcsFMMfFFhFp".to_string();
-        let (one, two) = split_rutsack(&s);
-        let c = find_common_item(&one, &two);
-        assert_eq!(c, 'p');
+    fn test_common_in_compartments() {
+        let input = vec!["vJrwpWtwJgWrhcsFMMfFFhFp".to_string()];
+        let common = common_in_compartments(&input, 2).unwrap();
+        assert_eq!(common[0].items(), vec!['p']);
     }
 
     #[test]
-    fn test_get_char_point() {
-        assert_eq!(get_char_point('a'), 1);
-        assert_eq!(get_char_point('j'), 10);
-        assert_eq!(get_char_point('z'), 26);
-        assert_eq!(get_char_point('A'), 27);
-        assert_eq!(get_char_point('O'), 41);
-        assert_eq!(get_char_point('Z'), 52);
+    fn test_priority() {
+        assert_eq!(priority('a'), Some(1));
+        assert_eq!(priority('j'), Some(10));
+        assert_eq!(priority('z'), Some(26));
+        assert_eq!(priority('A'), Some(27));
+        assert_eq!(priority('O'), Some(41));
+        assert_eq!(priority('Z'), Some(52));
+        assert_eq!(priority('1'), None);
     }
 
     #[test]
-    fn test_process_chunk() {
+    fn test_common_in_group_of_three() {
         let s = vec![
             "vJrwpWtwJgWrhcsFMMfFFhFp".to_string(),
             "jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL".to_string(),
             "PmmdzqPrVvPwwTWBwg".to_string(),
         ];
-        let c = s.chunks(3);
-        for chunk in c {
-            let new_char = process_chunk(chunk);
-            assert_eq!(new_char, 'r');
+        let common = common_in_groups(&s, 3).unwrap();
+        assert_eq!(common.len(), 1);
+        assert_eq!(common[0].items(), vec!['r']);
+    }
+
+    #[test]
+    fn test_item_for_priority() {
+        for p in 1..=52 {
+            assert_eq!(priority(item_for_priority(p)), Some(p));
         }
+        assert_eq!(ItemSet::all().len(), 52);
+        assert_eq!(ItemSet::all().priority_sum(), (1..=52).sum());
+    }
+
+    #[test]
+    fn test_compartments() {
+        let sets = compartments("abcabd", 3, 1).unwrap();
+        assert_eq!(
+            sets.iter().map(|s| s.items()).collect::<Vec<_>>(),
+            vec![vec!['a', 'b'], vec!['a', 'c'], vec!['b', 'd']]
+        );
+        assert_eq!(
+            compartments("abcab", 2, 4),
+            Err(RucksackError::UnevenCompartments {
+                line: 4,
+                length: 5,
+                compartments: 2
+            })
+        );
+        assert_eq!(
+            compartments("ab1c", 2, 2),
+            Err(RucksackError::BadItem { line: 2, item: '1' })
+        );
+        // An empty rucksack still has its compartments, all empty.
+        assert_eq!(compartments("", 2, 1), Ok(vec![ItemSet::new(); 2]));
+    }
+
+    #[test]
+    fn test_several_common_items() {
+        let input: Vec<String> = ["aBcaBc", "xyzxyz"].iter().map(|s| s.to_string()).collect();
+        let common = common_in_compartments(&input, 2).unwrap();
+        assert_eq!(common[0].items(), vec!['a', 'c', 'B']);
+        assert_eq!(common[1].priority_sum(), 24 + 25 + 26);
+        assert_eq!(total_priority(Ok(common)), 1 + 3 + 28 + 24 + 25 + 26);
+    }
+
+    #[test]
+    fn test_group_errors() {
+        let input: Vec<String> = ["ab", "bc", "c d"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            common_in_groups(&input, 3),
+            Err(RucksackError::BadItem { line: 3, item: ' ' })
+        );
+        assert_eq!(
+            common_in_groups(&input, 2).unwrap_err().to_string(),
+            "the last group has 1 rucksacks instead of 2"
+        );
+        assert_eq!(common_in_groups(&input, 0), Err(RucksackError::ZeroSize));
+        assert_eq!(
+            common_in_groups(&input, 1).unwrap_err().to_string(),
+            "line 3: ' ' isn't an item"
+        );
     }
 }
@@ -1,11 +1,42 @@
+use day03::common_in_compartments;
+use day03::common_in_groups;
 use day03::load_no_blanks;
 use day03::puzzle_a;
 use day03::puzzle_b;
+use std::env;
 
 fn main() {
     let filename = "input";
     let template = load_no_blanks(filename);
 
+    // --compartments N or --group N lists the common items with that split, and their priorities.
+    let args: Vec<String> = env::args().collect();
+    for flag in ["--compartments", "--group"] {
+        if let Some(i) = args.iter().position(|a| a == flag) {
+            let n = args
+                .get(i + 1)
+                .and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or(if flag == "--group" { 3 } else { 2 });
+            let common = if flag == "--group" {
+                common_in_groups(&template, n)
+            } else {
+                common_in_compartments(&template, n)
+            };
+            match common {
+                Ok(sets) => {
+                    for set in &sets {
+                        let items: String = set.items().iter().collect();
+                        println!("{} ({})", items, set.priority_sum());
+                    }
+                    let total: u32 = sets.iter().map(|s| s.priority_sum()).sum();
+                    println!("Total: {}", total);
+                }
+                Err(e) => println!("{}", e),
+            }
+            return;
+        }
+    }
+
     let value = puzzle_a(&template);
     println!("Answer to 1st question: {}", value);
 
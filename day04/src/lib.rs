@@ -1,16 +1,14 @@
 extern crate filelib;
 
 pub use filelib::load_no_blanks;
+use rangelib::Interval;
 
 pub type DoublePair = (u32, u32, u32, u32);
 
-fn fully_contains(a_lower: u32, a_higher: u32, b_lower: u32, b_higher: u32) -> bool {
-    return a_lower <= b_lower && a_higher >= b_higher;
-}
-
 fn pairwise_fully_contains(a_lower: u32, a_higher: u32, b_lower: u32, b_higher: u32) -> bool {
-    return fully_contains(a_lower, a_higher, b_lower, b_higher)
-        || fully_contains(b_lower, b_higher, a_lower, a_higher);
+    let a = Interval::new(a_lower, a_higher);
+    let b = Interval::new(b_lower, b_higher);
+    return a.contains_interval(&b) || b.contains_interval(&a);
 }
 
 /// Parse the line to just the lows and highs.
@@ -46,23 +44,10 @@ pub fn puzzle_a(input: &Vec<DoublePair>) -> usize {
         .count();
 }
 
-fn overlap_at_all(a_lower: u32, a_higher: u32, b_lower: u32, b_higher: u32) -> bool {
-    // Implemented only from a's perspective, call also with arguments reversed (pairwise)
-    // first check the lower cases, where a_lower is between b_lower and b_higher
-    if b_lower <= a_lower && b_higher >= a_lower {
-        return true;
-    }
-    // Check the higher cases, where a_higher is between b_lower and b_higher
-    if b_lower <= a_higher && b_higher >= a_higher {
-        return true;
-    }
-    // Finally check if one fully contains the other.
-    return fully_contains(a_lower, a_higher, b_lower, b_higher);
-}
-
 fn pairwise_overlap_at_all(a_lower: u32, a_higher: u32, b_lower: u32, b_higher: u32) -> bool {
-    return overlap_at_all(a_lower, a_higher, b_lower, b_higher)
-        || overlap_at_all(b_lower, b_higher, a_lower, a_higher);
+    let a = Interval::new(a_lower, a_higher);
+    let b = Interval::new(b_lower, b_higher);
+    return a.overlaps(&b);
 }
 
 /// Solution to puzzle_b entry point
@@ -12,7 +12,7 @@ pub struct Crate {
 
 impl Display for Crate {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        return writeln!(f, "[{}]", self.label);
+        return write!(f, "[{}]", self.label);
     }
 }
 
@@ -22,141 +22,442 @@ impl Crate {
     }
 }
 
+/// What's wrong with a drawing or the moves after it.
+///
+/// Drawing lines are counted from 1 at the top of the drawing, and moves from 1 at the first move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// No line of stack labels under the drawing.
+    MissingLabels,
+    /// A stack label that isn't a number.
+    BadLabel(String),
+    DuplicateLabel(usize),
+    /// Something other than `[X]` or blanks in a crate slot.
+    BadCrate {
+        line: usize,
+        column: usize,
+    },
+    /// A crate further right than the last labelled stack.
+    UnlabelledCrate {
+        line: usize,
+        column: usize,
+    },
+    /// A crate with nothing underneath it.
+    FloatingCrate {
+        line: usize,
+        column: usize,
+    },
+    /// A move that doesn't read `move N from A to B`.
+    BadMove {
+        number: usize,
+        text: String,
+    },
+    UnknownStack {
+        number: usize,
+        label: usize,
+    },
+    /// A move asking for more crates than the stack has at that point.
+    NotEnoughCrates {
+        number: usize,
+        label: usize,
+        wanted: usize,
+        available: usize,
+    },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            ParseError::MissingLabels => write!(f, "no stack labels under the drawing"),
+            ParseError::BadLabel(label) => write!(f, "stack label '{}' isn't a number", label),
+            ParseError::DuplicateLabel(label) => write!(f, "stack {} is labelled twice", label),
+            ParseError::BadCrate { line, column } => {
+                write!(f, "line {} column {}: expected a crate", line, column)
+            }
+            ParseError::UnlabelledCrate { line, column } => {
+                write!(
+                    f,
+                    "line {} column {}: crate isn't over a stack",
+                    line, column
+                )
+            }
+            ParseError::FloatingCrate { line, column } => {
+                write!(
+                    f,
+                    "line {} column {}: crate has nothing under it",
+                    line, column
+                )
+            }
+            ParseError::BadMove { number, text } => {
+                write!(f, "move {}: can't understand '{}'", number, text)
+            }
+            ParseError::UnknownStack { number, label } => {
+                write!(f, "move {}: there is no stack {}", number, label)
+            }
+            ParseError::NotEnoughCrates {
+                number,
+                label,
+                wanted,
+                available,
+            } => write!(
+                f,
+                "move {}: stack {} has {} crates, not {}",
+                number, label, available, wanted
+            ),
+        };
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct OverallState {
-    stacks: [Vec<Crate>; 9],
+    labels: Vec<usize>,
+    stacks: Vec<Vec<Crate>>,
 }
 
 impl OverallState {
-    pub fn get_stack_clone(&self, index: usize) -> Vec<Crate> {
-        return self.stacks[index - 1].clone();
+    /// Position of the stack with this label.
+    pub fn stack_index(&self, label: usize) -> Option<usize> {
+        return self.labels.iter().position(|l| *l == label);
+    }
+
+    /// The labels, left to right.
+    pub fn labels(&self) -> &[usize] {
+        return &self.labels;
+    }
+
+    pub fn get_stack_clone(&self, label: usize) -> Vec<Crate> {
+        return self.stacks[self.index(label)].clone();
+    }
+
+    fn index(&self, label: usize) -> usize {
+        return match self.stack_index(label) {
+            Some(i) => i,
+            None => panic!("Can't find stack {}", label),
+        };
     }
 
-    fn remove_from_stack(&mut self, index: usize, number: usize) -> Vec<Crate> {
-        let stack = &mut self.stacks[index - 1];
+    fn remove_from_stack(&mut self, label: usize, number: usize) -> Vec<Crate> {
+        let i = self.index(label);
+        let stack = &mut self.stacks[i];
         return stack.split_off(stack.len() - number);
     }
 
-    fn add_to_stack(&mut self, index: usize, mut crates: Vec<Crate>) {
-        let stack = &mut self.stacks[index - 1];
-        stack.append(&mut crates);
+    fn add_to_stack(&mut self, label: usize, mut crates: Vec<Crate>) {
+        let i = self.index(label);
+        self.stacks[i].append(&mut crates);
     }
 
-    fn add_individual_item_to_stack(&mut self, index: usize, c: Crate) {
-        let stack = &mut self.stacks[index - 1];
-        stack.push(c);
+    /// Label of the crate on top of each stack, left to right.
+    pub fn get_top_of_stacks(&self) -> String {
+        let tops: String = self
+            .stacks
+            .iter()
+            .map(|s| s.last().map_or(' ', |c| c.get_label()))
+            .collect();
+        return tops.trim().to_string();
     }
 
-    fn get_top_of_stacks(self) -> String {
-        let mut s_builder = "".to_string();
-        s_builder.push(self.stacks[0].last().map_or(' ', |c| c.get_label()));
-        s_builder.push(self.stacks[1].last().map_or(' ', |c| c.get_label()));
-        s_builder.push(self.stacks[2].last().map_or(' ', |c| c.get_label()));
-        s_builder.push(self.stacks[3].last().map_or(' ', |c| c.get_label()));
-        s_builder.push(self.stacks[4].last().map_or(' ', |c| c.get_label()));
-        s_builder.push(self.stacks[5].last().map_or(' ', |c| c.get_label()));
-        s_builder.push(self.stacks[6].last().map_or(' ', |c| c.get_label()));
-        s_builder.push(self.stacks[7].last().map_or(' ', |c| c.get_label()));
-        s_builder.push(self.stacks[8].last().map_or(' ', |c| c.get_label()));
-        return s_builder.trim().to_string();
+    /// Do one move with the given crane.
+    ///
+    /// Panics if the move isn't possible, which `check_moves` can rule out beforehand.
+    pub fn apply_move<C: Crane + ?Sized>(&mut self, crane: &C, mv: &Move) {
+        let (move_num, from_num, to_num) = *mv;
+        let lifted = self.remove_from_stack(from_num, move_num);
+        self.add_to_stack(to_num, crane.lift(lifted));
     }
 
-    fn reverse_stacks(&mut self) {
-        for i in 0..9 {
-            self.stacks[i] = self.stacks[i].clone().into_iter().rev().collect();
+    /// Make sure every move names real stacks and never takes more crates than are there.
+    ///
+    /// Only the number of crates in each stack matters, so this holds however the crates are moved.
+    /// ```
+    /// let state = day05::create_example_state();
+    /// assert!(state.check_moves(&vec![(1, 2, 1), (3, 1, 3)]).is_ok());
+    /// assert_eq!(
+    ///     state.check_moves(&vec![(4, 1, 3)]).unwrap_err().to_string(),
+    ///     "move 1: stack 1 has 2 crates, not 4"
+    /// );
+    /// ```
+    pub fn check_moves(&self, moves: &[Move]) -> Result<(), ParseError> {
+        let mut heights: Vec<usize> = self.stacks.iter().map(|s| s.len()).collect();
+        for (i, (move_num, from_num, to_num)) in moves.iter().enumerate() {
+            let number = i + 1;
+            let find = |label: usize| {
+                self.stack_index(label).ok_or(ParseError::UnknownStack {
+                    number: number,
+                    label: label,
+                })
+            };
+            let from = find(*from_num)?;
+            let to = find(*to_num)?;
+            if heights[from] < *move_num {
+                return Err(ParseError::NotEnoughCrates {
+                    number: number,
+                    label: *from_num,
+                    wanted: *move_num,
+                    available: heights[from],
+                });
+            }
+            heights[from] -= move_num;
+            heights[to] += move_num;
         }
+        return Ok(());
     }
 }
 
-fn create_state() -> OverallState {
-    return OverallState {
-        stacks: [
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-        ],
-    };
-}
-
+/// Draws the stacks the same way as the puzzle input, labels and all.
 impl Display for OverallState {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for i in 0..9 {
-            match writeln!(f, "{} {:?}", i + 1, self.stacks[i]) {
-                Ok(_) => continue,
-                Err(err) => return Err(err),
-            }
+        let height = self.stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let cells: Vec<String> = self
+                .stacks
+                .iter()
+                .map(|s| s.get(level).map_or("   ".to_string(), |c| c.to_string()))
+                .collect();
+            writeln!(f, "{}", cells.join(" "))?;
         }
-        return Ok(());
+        let labels: Vec<String> = self.labels.iter().map(|l| format!("{:^3}", l)).collect();
+        return writeln!(f, "{}", labels.join(" "));
+    }
+}
+
+/// How a crane puts down the crates it picked up.
+pub trait Crane {
+    /// Given the crates lifted off a stack, bottom first, return them in the order
+    /// they end up on the new stack, bottom first.
+    fn lift(&self, crates: Vec<Crate>) -> Vec<Crate>;
+}
+
+/// Moves one crate at a time, so they land in reverse order.
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn lift(&self, mut crates: Vec<Crate>) -> Vec<Crate> {
+        crates.reverse();
+        return crates;
+    }
+}
+
+/// Moves all the crates at once, so they keep their order.
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn lift(&self, crates: Vec<Crate>) -> Vec<Crate> {
+        return crates;
     }
 }
 
+/// Moves at most `max_lift` crates at a time, taking them off the top.
+///
+/// A limit of 1 behaves like the 9000, and a big enough limit like the 9001.
+/// ```
+/// use day05::Crane;
+/// let state = day05::parse_stacks("[A]\n[B]\n[C]\n 1   2").unwrap();
+/// let crane = day05::LimitedCrane { max_lift: 2 };
+/// let after = day05::run_crane(&state, &vec![(3, 1, 2)], &crane);
+/// assert_eq!(after.to_string(), "    [C]\n    [A]\n    [B]\n 1   2 \n");
+/// ```
+pub struct LimitedCrane {
+    pub max_lift: usize,
+}
+
+impl Crane for LimitedCrane {
+    fn lift(&self, crates: Vec<Crate>) -> Vec<Crate> {
+        return crates
+            .rchunks(self.max_lift.max(1))
+            .flat_map(|chunk| chunk.iter().copied())
+            .collect();
+    }
+}
+
+/// Steps through the moves, giving each move along with the state after it.
+pub struct CraneSteps<'a, C: Crane + ?Sized> {
+    state: OverallState,
+    moves: std::slice::Iter<'a, Move>,
+    crane: &'a C,
+}
+
+impl<C: Crane + ?Sized> Iterator for CraneSteps<'_, C> {
+    type Item = (Move, OverallState);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mv = self.moves.next()?;
+        self.state.apply_move(self.crane, mv);
+        return Some((*mv, self.state.clone()));
+    }
+}
+
+/// Iterate over the state after each move.
+/// ```
+/// let state = day05::create_example_state();
+/// let moves = vec![(1, 2, 1), (3, 1, 3)];
+/// let steps: Vec<String> = day05::crane_steps(&state, &moves, &day05::CrateMover9000)
+///     .map(|(_, s)| s.get_top_of_stacks())
+///     .collect();
+/// assert_eq!(steps, vec!["DCP", "CZ"]);
+/// ```
+pub fn crane_steps<'a, C: Crane + ?Sized>(
+    state: &OverallState,
+    moves: &'a [Move],
+    crane: &'a C,
+) -> CraneSteps<'a, C> {
+    return CraneSteps {
+        state: state.clone(),
+        moves: moves.iter(),
+        crane: crane,
+    };
+}
+
+/// Do every move and return where the crates end up.
+pub fn run_crane<C: Crane + ?Sized>(
+    state: &OverallState,
+    moves: &[Move],
+    crane: &C,
+) -> OverallState {
+    let mut result = state.clone();
+    for mv in moves {
+        result.apply_move(crane, mv);
+    }
+    return result;
+}
+
 /// Parse columns into stacks
+///
+/// Any number of stacks works, as long as each is labelled under the drawing.
+/// Trailing spaces can be left off the lines.
 /// ```
-/// let columns = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
-/// let state = day05::parse_stacks(columns);
+/// let columns = "    [D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3";
+/// let state = day05::parse_stacks(columns).unwrap();
 /// let expected_state = day05::create_example_state();
+/// assert_eq!(state.labels(), [1, 2, 3]);
 /// assert_eq!(state.get_stack_clone(1), expected_state.get_stack_clone(1));
 /// assert_eq!(state.get_stack_clone(2), expected_state.get_stack_clone(2));
 /// assert_eq!(state.get_stack_clone(3), expected_state.get_stack_clone(3));
-/// assert_eq!(state.get_stack_clone(4), expected_state.get_stack_clone(4));
-/// assert_eq!(state.get_stack_clone(5), expected_state.get_stack_clone(5));
-/// assert_eq!(state.get_stack_clone(6), expected_state.get_stack_clone(6));
-/// assert_eq!(state.get_stack_clone(7), expected_state.get_stack_clone(7));
-/// assert_eq!(state.get_stack_clone(8), expected_state.get_stack_clone(8));
-/// assert_eq!(state.get_stack_clone(9), expected_state.get_stack_clone(9));
 /// ```
-pub fn parse_stacks(stacks: &str) -> OverallState {
-    let mut state = create_state();
-    for l in stacks.lines() {
-        if l.chars().nth(1).unwrap() == '1' {
-            break;
+pub fn parse_stacks(stacks: &str) -> Result<OverallState, ParseError> {
+    let lines: Vec<&str> = stacks.lines().filter(|l| !l.trim().is_empty()).collect();
+    let (label_line, crate_lines) = lines.split_last().ok_or(ParseError::MissingLabels)?;
+    let mut labels = vec![];
+    for token in label_line.split_whitespace() {
+        let label = token
+            .parse::<usize>()
+            .map_err(|_| ParseError::BadLabel(token.to_string()))?;
+        if labels.contains(&label) {
+            return Err(ParseError::DuplicateLabel(label));
         }
+        labels.push(label);
+    }
+    if labels.is_empty() {
+        return Err(ParseError::MissingLabels);
+    }
+
+    let mut stacks: Vec<Vec<Crate>> = vec![vec![]; labels.len()];
+    for (line_index, l) in crate_lines.iter().enumerate() {
         //                01234567890  01234567890  01234567890
         // let columns = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
-        // character will always be at  1, 5, 9, etc.
-        let num_columns = (l.len() + 1) / 4;
-        for i in 0..num_columns {
-            let c = l.chars().nth(1 + 4 * i).unwrap();
-            if c != ' ' {
-                state.add_individual_item_to_stack(i + 1, Crate { label: c });
+        // each stack takes 4 characters, with the crate's label in the middle of the first 3.
+        let chars: Vec<char> = l.trim_end().chars().collect();
+        for (i, slot) in chars.chunks(4).enumerate() {
+            let line = line_index + 1;
+            let column = 4 * i + 1;
+            let cell = &slot[..slot.len().min(3)];
+            if cell.iter().all(|c| *c == ' ') {
+                if !stacks.get(i).is_none_or(|s| s.is_empty()) {
+                    return Err(ParseError::FloatingCrate {
+                        line: line - 1,
+                        column: column,
+                    });
+                }
+                continue;
+            }
+            if cell.len() < 3 || cell[0] != '[' || cell[2] != ']' {
+                return Err(ParseError::BadCrate {
+                    line: line,
+                    column: column,
+                });
+            }
+            let stack = stacks.get_mut(i).ok_or(ParseError::UnlabelledCrate {
+                line: line,
+                column: column,
+            })?;
+            stack.push(Crate { label: cell[1] });
+        }
+        // Lines can be cut short, which also leaves a gap under anything above.
+        for (i, stack) in stacks.iter().enumerate().skip(chars.len().div_ceil(4)) {
+            if !stack.is_empty() {
+                return Err(ParseError::FloatingCrate {
+                    line: line_index,
+                    column: 4 * i + 1,
+                });
             }
         }
     }
-    state.reverse_stacks();
+    for stack in stacks.iter_mut() {
+        stack.reverse();
+    }
 
-    return state;
+    return Ok(OverallState {
+        labels: labels,
+        stacks: stacks,
+    });
 }
 
 /// Parse text into moves
 /// ```
 /// let moves = "move 1 from 2 to 1\nmove 3 from 1 to 3\nmove 2 from 2 to 1\nmove 1 from 1 to 2";
-/// assert_eq!(day05::parse_moves(moves), vec![(1, 2, 1), (3, 1, 3), (2, 2, 1), (1, 1, 2)])
+/// assert_eq!(day05::parse_moves(moves).unwrap(), vec![(1, 2, 1), (3, 1, 3), (2, 2, 1), (1, 1, 2)])
 /// ```
-pub fn parse_moves(moves: &str) -> Vec<Move> {
+pub fn parse_moves(moves: &str) -> Result<Vec<Move>, ParseError> {
     return moves
         .lines()
-        .map(|s| {
-            let (movefrom_str, to_str) = s.split_once(" to ").unwrap();
-            let (move_unparsed_str, from_str) = movefrom_str.split_once(" from ").unwrap();
-            let (_, move_str) = move_unparsed_str.split_once("move ").unwrap();
-            let to_num = to_str.parse::<usize>().unwrap();
-            let from_num = from_str.parse::<usize>().unwrap();
-            let move_num = move_str.parse::<usize>().unwrap();
-            return (move_num, from_num, to_num);
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .enumerate()
+        .map(|(i, s)| {
+            let bad_move = || ParseError::BadMove {
+                number: i + 1,
+                text: s.to_string(),
+            };
+            let words: Vec<&str> = s.split_whitespace().collect();
+            if words.len() != 6 || words[0] != "move" || words[2] != "from" || words[4] != "to" {
+                return Err(bad_move());
+            }
+            let number = |w: &str| w.parse::<usize>().map_err(|_| bad_move());
+            return Ok((number(words[1])?, number(words[3])?, number(words[5])?));
         })
         .collect();
 }
 
+/// Parse the whole input: the drawing, a blank line, then the moves.
+///
+/// Windows line endings are fine. The moves are checked against the drawing, so
+/// none of them can take crates from an empty stack.
+/// ```
+/// let input = "    [D]    \r\n[N] [C]    \r\n[Z] [M] [P]\r\n 1   2   3 \r\n\r\nmove 1 from 2 to 1\r\n";
+/// let (state, moves) = day05::parse_input(input).unwrap();
+/// assert_eq!(day05::puzzle_a(&state, &moves), "DCP");
+/// ```
+pub fn parse_input(input: &str) -> Result<(OverallState, Vec<Move>), ParseError> {
+    let lines: Vec<&str> = input.lines().collect();
+    let drawing_start = lines
+        .iter()
+        .position(|l| !l.trim().is_empty())
+        .unwrap_or(lines.len());
+    let drawing_end = lines[drawing_start..]
+        .iter()
+        .position(|l| l.trim().is_empty())
+        .map_or(lines.len(), |i| drawing_start + i);
+    let state = parse_stacks(&lines[drawing_start..drawing_end].join("\n"))?;
+    let moves = parse_moves(&lines[drawing_end..].join("\n"))?;
+    state.check_moves(&moves)?;
+    return Ok((state, moves));
+}
+
 // For doc tests
 pub fn create_example_state() -> OverallState {
     return OverallState {
-        stacks: [
+        labels: vec![1, 2, 3],
+        stacks: vec![
             vec![Crate { label: 'Z' }, Crate { label: 'N' }],
             vec![
                 Crate { label: 'M' },
@@ -164,12 +465,6 @@ pub fn create_example_state() -> OverallState {
                 Crate { label: 'D' },
             ],
             vec![Crate { label: 'P' }],
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-            vec![],
         ],
     };
 }
@@ -181,17 +476,7 @@ pub fn create_example_state() -> OverallState {
 /// assert_eq!(day05::puzzle_a(&state, &moves), "CMZ");
 /// ```
 pub fn puzzle_a(state: &OverallState, moves: &Vec<Move>) -> String {
-    let mut puzzle_state = state.clone();
-
-    for (move_num, from_num, to_num) in moves {
-        // In puzzle a, crates are moved 1 by 1, which I missed, so move_num is always 1:
-        for _ in 1..=*move_num {
-            let v = puzzle_state.remove_from_stack(*from_num, 1);
-            puzzle_state.add_to_stack(*to_num, v);
-        }
-    }
-
-    return puzzle_state.get_top_of_stacks();
+    return run_crane(state, moves, &CrateMover9000).get_top_of_stacks();
 }
 
 /// Solution to puzzle_b entry point
@@ -201,28 +486,21 @@ pub fn puzzle_a(state: &OverallState, moves: &Vec<Move>) -> String {
 /// assert_eq!(day05::puzzle_b(&state, &moves), "MCD");
 /// ```
 pub fn puzzle_b(state: &OverallState, moves: &Vec<Move>) -> String {
-    let mut puzzle_state = state.clone();
-
-    for (move_num, from_num, to_num) in moves {
-        let v = puzzle_state.remove_from_stack(*from_num, *move_num);
-        puzzle_state.add_to_stack(*to_num, v);
-    }
-
-    return puzzle_state.get_top_of_stacks();
+    return run_crane(state, moves, &CrateMover9001).get_top_of_stacks();
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn labels(stack: &[Crate]) -> String {
+        return stack.iter().map(|c| c.get_label()).collect();
+    }
+
     #[test]
     fn test_move_state() {
-        let mut state = create_state();
-        state.add_individual_item_to_stack(1, Crate { label: 'A' });
+        let mut state = parse_stacks("    [C]\n[A] [B]\n 1   2").unwrap();
         assert_eq!(state.get_stack_clone(1).len(), 1);
-        state.add_individual_item_to_stack(2, Crate { label: 'B' });
-        assert_eq!(state.get_stack_clone(2).len(), 1);
-        state.add_individual_item_to_stack(2, Crate { label: 'C' });
         assert_eq!(state.get_stack_clone(2).len(), 2);
         let grabbed = state.remove_from_stack(2, 2);
         assert_eq!(state.get_stack_clone(2).len(), 0);
@@ -236,4 +514,136 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_many_stacks_with_long_labels() {
+        let drawing = [
+            "                                            [L]",
+            "[A] [B] [C] [D] [E] [F] [G] [H] [I] [J] [K] [M]",
+            " 1   2   3   4   5   6   7   8   9  10  11  12",
+        ];
+        let (state, moves) =
+            parse_input(&(drawing.join("\n") + "\n\nmove 2 from 12 to 10\n")).unwrap();
+        assert_eq!(state.labels(), (1..=12).collect::<Vec<usize>>());
+        assert_eq!(labels(&state.get_stack_clone(12)), "ML");
+        assert_eq!(puzzle_a(&state, &moves), "ABCDEFGHIMK");
+        assert_eq!(puzzle_b(&state, &moves), "ABCDEFGHILK");
+    }
+
+    #[test]
+    fn test_labels_need_not_be_in_order() {
+        let state = parse_stacks("[X]     [Y]\n 7   3   5").unwrap();
+        assert_eq!(state.stack_index(3), Some(1));
+        assert!(state.get_stack_clone(3).is_empty());
+        assert_eq!(labels(&state.get_stack_clone(5)), "Y");
+        assert_eq!(state.stack_index(1), None);
+    }
+
+    #[test]
+    fn test_drawing_errors() {
+        assert_eq!(parse_stacks("").unwrap_err(), ParseError::MissingLabels);
+        assert_eq!(
+            parse_stacks("[A]\n a").unwrap_err(),
+            ParseError::BadLabel("a".to_string())
+        );
+        assert_eq!(
+            parse_stacks(" 1   1").unwrap_err(),
+            ParseError::DuplicateLabel(1)
+        );
+        assert_eq!(
+            parse_stacks("[A] (B)\n 1   2").unwrap_err(),
+            ParseError::BadCrate { line: 1, column: 5 }
+        );
+        assert_eq!(
+            parse_stacks("[A] [B]\n 1").unwrap_err(),
+            ParseError::UnlabelledCrate { line: 1, column: 5 }
+        );
+        assert_eq!(
+            parse_stacks("[A] [B]\n    [C]\n 1   2").unwrap_err(),
+            ParseError::FloatingCrate { line: 1, column: 1 }
+        );
+        assert_eq!(
+            parse_stacks("[A] [B]\n[C]\n 1   2").unwrap_err(),
+            ParseError::FloatingCrate { line: 1, column: 5 }
+        );
+    }
+
+    #[test]
+    fn test_move_errors() {
+        assert_eq!(
+            parse_moves("move 1 from 2 to 1\nmove one from 2 to 1").unwrap_err(),
+            ParseError::BadMove {
+                number: 2,
+                text: "move one from 2 to 1".to_string()
+            }
+        );
+        let input = "[A]\n 1   2\n\nmove 1 from 1 to 2\nmove 1 from 1 to 2\n";
+        assert_eq!(
+            parse_input(input).unwrap_err(),
+            ParseError::NotEnoughCrates {
+                number: 2,
+                label: 1,
+                wanted: 1,
+                available: 0
+            }
+        );
+        assert_eq!(
+            parse_input("[A]\n 1\n\nmove 1 from 1 to 4").unwrap_err(),
+            ParseError::UnknownStack {
+                number: 1,
+                label: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_render_round_trips() {
+        let drawing = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n";
+        let state = parse_stacks(drawing).unwrap();
+        assert_eq!(state.to_string(), drawing);
+        let wide = parse_stacks("[A]                                     [B]\n 1   2   3   4   5   6   7   8   9  10  11")
+            .unwrap();
+        assert_eq!(
+            parse_stacks(&wide.to_string()).unwrap().to_string(),
+            wide.to_string()
+        );
+        assert!(wide.to_string().ends_with(" 9  10  11 \n"));
+    }
+
+    #[test]
+    fn test_limited_crane_matches_models() {
+        let state = create_example_state();
+        let moves = vec![(1, 2, 1), (3, 1, 3), (2, 2, 1), (1, 1, 2)];
+        let one = run_crane(&state, &moves, &LimitedCrane { max_lift: 1 });
+        assert_eq!(
+            one.to_string(),
+            run_crane(&state, &moves, &CrateMover9000).to_string()
+        );
+        let big = run_crane(&state, &moves, &LimitedCrane { max_lift: 3 });
+        assert_eq!(
+            big.to_string(),
+            run_crane(&state, &moves, &CrateMover9001).to_string()
+        );
+        let two = run_crane(&state, &moves, &LimitedCrane { max_lift: 2 });
+        assert_eq!(two.get_stack_clone(3).len(), 4);
+        assert_eq!(labels(&two.get_stack_clone(3)), "PNDZ");
+    }
+
+    #[test]
+    fn test_steps_end_where_run_does() {
+        let state = create_example_state();
+        let moves = vec![(1, 2, 1), (3, 1, 3), (2, 2, 1), (1, 1, 2)];
+        let crane: &dyn Crane = &CrateMover9001;
+        let steps: Vec<(Move, OverallState)> = crane_steps(&state, &moves, crane).collect();
+        assert_eq!(steps.len(), 4);
+        assert_eq!(steps[1].0, (3, 1, 3));
+        assert_eq!(
+            steps[3].1.to_string(),
+            run_crane(&state, &moves, crane).to_string()
+        );
+        assert_eq!(
+            steps[0].1.to_string(),
+            "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n"
+        );
+    }
 }
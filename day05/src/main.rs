@@ -1,15 +1,39 @@
+use day05::crane_steps;
 use day05::load;
-use day05::parse_moves;
-use day05::parse_stacks;
+use day05::parse_input;
 use day05::puzzle_a;
 use day05::puzzle_b;
+use day05::{Crane, CrateMover9000, CrateMover9001, LimitedCrane};
+use std::env;
 
 fn main() {
     let filename = "input";
     let file_input = load(filename);
-    let (stacks_raw, moves_raw) = file_input.split_once("\n\n").unwrap();
-    let stacks = parse_stacks(stacks_raw);
-    let moves = parse_moves(moves_raw);
+    let (stacks, moves) = match parse_input(&file_input) {
+        Ok(parsed) => parsed,
+        Err(e) => panic!("Can't parse input: {}", e),
+    };
+
+    // --steps [9000|9001|lift size] draws the stacks after every move.
+    let args: Vec<String> = env::args().collect();
+    if let Some(i) = args.iter().position(|a| a == "--steps") {
+        let crane: Box<dyn Crane> = match args.get(i + 1).map(|s| s.as_str()) {
+            None | Some("9000") => Box::new(CrateMover9000),
+            Some("9001") => Box::new(CrateMover9001),
+            Some(n) => match n.parse::<usize>() {
+                Ok(max_lift) => Box::new(LimitedCrane { max_lift: max_lift }),
+                Err(e) => panic!("Can't use {} as a crane: {}", n, e),
+            },
+        };
+        println!("{}", stacks);
+        for ((move_num, from_num, to_num), state) in crane_steps(&stacks, &moves, crane.as_ref()) {
+            println!(
+                "move {} from {} to {}\n{}",
+                move_num, from_num, to_num, state
+            );
+        }
+        return;
+    }
 
     let value = puzzle_a(&stacks, &moves);
     println!("Answer to 1st question: {}", value);
@@ -2,7 +2,192 @@ extern crate filelib;
 
 pub use filelib::load;
 
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+/// Looks for runs of `window` distinct symbols, one symbol at a time.
+///
+/// Remembers where each symbol was last seen and where the current run of distinct
+/// symbols starts, so each symbol costs O(1) whatever the window. Bytes and ASCII
+/// live in a table, other characters in a map.
+/// ```
+/// let mut finder = day06::MarkerFinder::new(3);
+/// let found: Vec<Option<usize>> = "abcab".chars().map(|c| finder.push(c)).collect();
+/// assert_eq!(found, vec![None, None, Some(3), Some(4), Some(5)]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MarkerFinder {
+    window: usize,
+    // How many symbols have been pushed.
+    count: usize,
+    // How many symbols come before the current run of distinct symbols.
+    start: usize,
+    // Last position + 1 of each symbol below 256, 0 if not seen.
+    low: [usize; 256],
+    high: HashMap<u32, usize>,
+}
+
+impl MarkerFinder {
+    pub fn new(window: usize) -> MarkerFinder {
+        if window == 0 {
+            panic!("Can't look for markers of length 0");
+        }
+        return MarkerFinder {
+            window: window,
+            count: 0,
+            start: 0,
+            low: [0; 256],
+            high: HashMap::new(),
+        };
+    }
+
+    pub fn window(&self) -> usize {
+        return self.window;
+    }
+
+    /// How many symbols have been pushed so far.
+    pub fn position(&self) -> usize {
+        return self.count;
+    }
+
+    /// Add the next symbol, returning the number of symbols read so far if the
+    /// last `window` of them are all different.
+    pub fn push<T: Into<u32>>(&mut self, symbol: T) -> Option<usize> {
+        let symbol = symbol.into();
+        self.count += 1;
+        let last = if symbol < 256 {
+            std::mem::replace(&mut self.low[symbol as usize], self.count)
+        } else {
+            self.high.insert(symbol, self.count).unwrap_or(0)
+        };
+        // last is a 1 based position, so this also skips symbols never seen.
+        if last > self.start {
+            self.start = last;
+        }
+        if self.count - self.start >= self.window {
+            return Some(self.count);
+        }
+        return None;
+    }
+}
+
+/// Iterator over every marker position in a run of symbols, see `markers`.
+pub struct Markers<I> {
+    symbols: I,
+    finder: MarkerFinder,
+}
+
+impl<T: Into<u32>, I: Iterator<Item = T>> Iterator for Markers<I> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        for symbol in self.symbols.by_ref() {
+            if let Some(position) = self.finder.push(symbol) {
+                return Some(position);
+            }
+        }
+        return None;
+    }
+}
+
+/// Every position where the last `window` symbols are all different.
+///
+/// Works on anything that turns into a `u32`, so bytes and chars both do.
+/// ```
+/// let bytes: Vec<usize> = day06::markers("aabcb".bytes(), 2).collect();
+/// assert_eq!(bytes, vec![3, 4, 5]);
+/// // Positions count characters, not bytes.
+/// let chars: Vec<usize> = day06::markers("ééèê".chars(), 3).collect();
+/// assert_eq!(chars, vec![4]);
+/// ```
+pub fn markers<T: Into<u32>, I: IntoIterator<Item = T>>(
+    symbols: I,
+    window: usize,
+) -> Markers<I::IntoIter> {
+    return Markers {
+        symbols: symbols.into_iter(),
+        finder: MarkerFinder::new(window),
+    };
+}
+
+/// Whether a reader is read as raw bytes or as UTF-8 characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Bytes,
+    Chars,
+}
+
+/// Every marker position in a stream, read a buffer at a time.
+///
+/// With `Unit::Chars` the stream must be UTF-8, and characters split across
+/// buffers are put back together.
+/// ```
+/// let stream = "mjqjpqmgbljsphdztnvjfqwrcgsmlb".as_bytes();
+/// let found = day06::markers_in_reader(stream, 14, day06::Unit::Bytes).unwrap();
+/// assert_eq!(found[0], 19);
+/// let found = day06::markers_in_reader("añaño".as_bytes(), 3, day06::Unit::Chars).unwrap();
+/// assert_eq!(found, vec![5]);
+/// // One byte at a time still puts each ñ back together.
+/// let one_byte = std::io::BufReader::with_capacity(1, "añaño".as_bytes());
+/// assert_eq!(day06::markers_in_reader(one_byte, 3, day06::Unit::Chars).unwrap(), vec![5]);
+/// assert!(day06::markers_in_reader(&[0xffu8, 0x61][..], 1, day06::Unit::Chars).is_err());
+/// ```
+pub fn markers_in_reader<R: BufRead>(
+    mut reader: R,
+    window: usize,
+    unit: Unit,
+) -> io::Result<Vec<usize>> {
+    let mut finder = MarkerFinder::new(window);
+    let mut found = vec![];
+    // The start of a character cut off at the end of the last buffer.
+    let mut partial: Vec<u8> = vec![];
+    loop {
+        let buffer = reader.fill_buf()?;
+        let used = buffer.len();
+        if used == 0 {
+            break;
+        }
+        match unit {
+            Unit::Bytes => found.extend(buffer.iter().filter_map(|b| finder.push(*b))),
+            Unit::Chars => {
+                partial.extend_from_slice(buffer);
+                let valid = match std::str::from_utf8(&partial) {
+                    Ok(text) => text.len(),
+                    Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                    Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                };
+                // Safe to unwrap, the bytes up to valid were just checked.
+                let text = std::str::from_utf8(&partial[..valid]).unwrap();
+                found.extend(text.chars().filter_map(|c| finder.push(c)));
+                partial.drain(..valid);
+            }
+        }
+        reader.consume(used);
+    }
+    if !partial.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "stream ends part way through a character",
+        ));
+    }
+    return Ok(found);
+}
+
+/// The first marker position, counting characters.
+/// ```
+/// assert_eq!(day06::first_marker("bvwbjplbgvbhsrlpgdmjqwftvncz", 4), Some(5));
+/// assert_eq!(day06::first_marker("aaaa", 2), None);
+/// ```
+pub fn first_marker(input: &str, window: usize) -> Option<usize> {
+    return markers(input.chars(), window).next();
+}
+
+fn find_unique_char_pos(input: &str, num_unique: usize) -> usize {
+    return match first_marker(input, num_unique) {
+        Some(position) => position,
+        None => panic!("Can't find a marker of {} different characters", num_unique),
+    };
+}
 
 /// Solution to puzzle_a entry point
 /// ```
@@ -13,19 +198,6 @@ pub fn puzzle_a(input: &str) -> usize {
     return find_unique_char_pos(input, 4);
 }
 
-fn find_unique_char_pos(input: &str, num_unique: usize) -> usize {
-    // for 0 indexing
-    let num_unique_offset = num_unique - 1;
-    for i in num_unique_offset..input.len() {
-        let chars: Vec<char> = input[i - num_unique_offset..=i].chars().collect();
-        let set: HashSet<char> = chars.into_iter().collect();
-        if set.len() == num_unique {
-            return i + 1;
-        }
-    }
-    panic!("Never found");
-}
-
 /// Solution to puzzle_b entry point
 /// ```
 /// let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
@@ -34,3 +206,101 @@ fn find_unique_char_pos(input: &str, num_unique: usize) -> usize {
 pub fn puzzle_b(input: &str) -> usize {
     return find_unique_char_pos(input, 14);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::hash::Hash;
+
+    // Small LCG, so the comparisons cover a spread of strings without extra dependencies.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, below: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            return (self.0 >> 33) as usize % below;
+        }
+    }
+
+    // Build a set for every window, the slow way.
+    fn brute_force<T: Hash + Eq>(symbols: &[T], window: usize) -> Vec<usize> {
+        return (window..=symbols.len())
+            .filter(|end| {
+                symbols[end - window..*end]
+                    .iter()
+                    .collect::<HashSet<_>>()
+                    .len()
+                    == window
+            })
+            .collect();
+    }
+
+    #[test]
+    fn test_bytes_match_brute_force() {
+        let mut random = Lcg(6);
+        for _ in 0..500 {
+            let length = random.next(60);
+            // Few symbols for lots of repeats, and some above 127.
+            let alphabet = 1 + random.next(8);
+            let bytes: Vec<u8> = (0..length)
+                .map(|_| (random.next(alphabet) * 37 % 256) as u8)
+                .collect();
+            for window in 1..=10 {
+                let fast: Vec<usize> = markers(bytes.iter().copied(), window).collect();
+                assert_eq!(fast, brute_force(&bytes, window), "{:?} {}", bytes, window);
+                let read = markers_in_reader(&bytes[..], window, Unit::Bytes).unwrap();
+                assert_eq!(read, fast);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chars_match_brute_force() {
+        // ASCII, Latin-1 (below 256 but two bytes in UTF-8) and beyond.
+        let alphabet = ['a', 'b', 'é', 'ÿ', 'ā', '€', '😀', '中'];
+        let mut random = Lcg(14);
+        for _ in 0..500 {
+            let length = random.next(40);
+            let used = 1 + random.next(alphabet.len());
+            let chars: Vec<char> = (0..length).map(|_| alphabet[random.next(used)]).collect();
+            let text: String = chars.iter().collect();
+            for window in 1..=alphabet.len() + 1 {
+                let fast: Vec<usize> = markers(text.chars(), window).collect();
+                assert_eq!(fast, brute_force(&chars, window), "{} {}", text, window);
+                // Small buffers cut characters in half.
+                let reader = std::io::BufReader::with_capacity(3, text.as_bytes());
+                assert_eq!(
+                    markers_in_reader(reader, window, Unit::Chars).unwrap(),
+                    fast
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_window_longer_than_input() {
+        assert_eq!(markers("abc".chars(), 4).count(), 0);
+        assert_eq!(first_marker("abc", 3), Some(3));
+        assert_eq!(first_marker("", 1), None);
+        assert!(markers_in_reader("abc".as_bytes(), 5, Unit::Chars)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_repeat_at_window_edge() {
+        // The d at 5 repeats the one just outside the window ending there.
+        let found: Vec<usize> = markers("dabcd".chars(), 4).collect();
+        assert_eq!(found, vec![4, 5]);
+        // The a at 5 repeats the first one in the window, so only the next window counts.
+        let found: Vec<usize> = markers("abcda".chars(), 5).collect();
+        assert!(found.is_empty());
+        let found: Vec<usize> = markers("abcdae".chars(), 5).collect();
+        assert_eq!(found, vec![6]);
+        assert_eq!(brute_force(&[1, 2, 3, 4, 1, 5], 5), vec![6]);
+    }
+}
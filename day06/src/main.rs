@@ -1,9 +1,37 @@
 use day06::load;
+use day06::markers_in_reader;
 use day06::puzzle_a;
 use day06::puzzle_b;
+use day06::Unit;
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
 
 fn main() {
     let filename = "input";
+
+    // --all N streams the input and prints every position with N different characters before it.
+    let args: Vec<String> = env::args().collect();
+    if let Some(i) = args.iter().position(|a| a == "--all") {
+        let window = args
+            .get(i + 1)
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or(4);
+        let file = match File::open(filename) {
+            Ok(f) => f,
+            Err(e) => panic!("Can't open {} : {}", filename, e),
+        };
+        match markers_in_reader(BufReader::new(file), window, Unit::Chars) {
+            Ok(found) => {
+                for position in found {
+                    println!("{}", position);
+                }
+            }
+            Err(e) => panic!("Can't read {} : {}", filename, e),
+        }
+        return;
+    }
+
     let buffer = load(filename);
 
     let value = puzzle_a(&buffer);
@@ -1,8 +1,7 @@
 extern crate filelib;
 
 pub use filelib::load_no_blanks;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::fmt::{Display, Formatter};
 
 // Function for use with doctests.
 pub fn get_puzzle_sample() -> Vec<String> {
@@ -36,143 +35,483 @@ pub fn get_puzzle_sample() -> Vec<String> {
     .collect();
 }
 
-// Representive of the file, has the label and size, we only actually use the size.
-// By using lifetimes here, we can use &str, instead of strings that we need to clone.
-#[derive(Debug)]
-struct PuzzleFile<'a> {
-    label: &'a str,
-    size: usize,
+/// Index of an entry in a `FileSystem`.
+pub type EntryId = usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    File { size: usize },
+    Dir { children: Vec<EntryId> },
+}
+
+/// A file or directory, with a link back to the directory it is in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    /// None for the root.
+    pub parent: Option<EntryId>,
+    pub kind: EntryKind,
+}
+
+/// Why a terminal transcript doesn't make sense, with the line it went wrong on, counting from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalError {
+    /// `cd ..` from the root.
+    AboveRoot { line: usize },
+    /// `cd` into something that is a file.
+    NotADirectory { line: usize, path: String },
+    /// A listing that disagrees with an earlier one about this path.
+    ConflictingEntry { line: usize, path: String },
+    /// A listing that leaves out something already known to be in the directory.
+    MissingEntry { line: usize, path: String },
+    /// A line that isn't a command, or isn't listing output after `ls`.
+    BadLine { line: usize, text: String },
 }
 
-impl<'a> PuzzleFile<'a> {
-    fn get_label(&self) -> &'a str {
-        return self.label;
+impl Display for TerminalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            TerminalError::AboveRoot { line } => write!(f, "line {}: cd .. from /", line),
+            TerminalError::NotADirectory { line, path } => {
+                write!(f, "line {}: {} is not a directory", line, path)
+            }
+            TerminalError::ConflictingEntry { line, path } => {
+                write!(
+                    f,
+                    "line {}: {} doesn't match an earlier listing",
+                    line, path
+                )
+            }
+            TerminalError::MissingEntry { line, path } => {
+                write!(f, "line {}: listing is missing {}", line, path)
+            }
+            TerminalError::BadLine { line, text } => {
+                write!(f, "line {}: can't understand '{}'", line, text)
+            }
+        };
     }
 }
 
-// We need to make a graph, which requires the Rc RefCell trick.
-// Two ways to think of this
-// 1: Just a bunch of syntax sugar, as long as you use it consistently,
-// and use Clone() where required, and .borrow() or .borrow_mut() whenever
-// you touch the internals.
-// 2: Rc is a pointer with shared ownership
-// RefCell provides interior mutability.
-// Essentially this is a shared box that the owner can edit the contents of.
-// You need the RefCell, because without it, rust gets mad at PuzzleDir in
-// PuzzleDir. The RefCell provides a "box" to hide that.
-// You then need Rc, which acts as a reference counter, to share it between
-// subdirs.
-#[derive(Debug)]
-struct PuzzleDir<'a> {
-    files: Vec<Rc<RefCell<PuzzleFile<'a>>>>,
-    subdirs: Vec<Rc<RefCell<PuzzleDir<'a>>>>,
-    label: &'a str,
+/// A directory tree, with every entry stored in one list and referred to by index.
+///
+/// The root is always `FileSystem::ROOT`, and entries are only ever added after their parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSystem {
+    entries: Vec<Entry>,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        return FileSystem::new();
+    }
 }
 
-impl<'a> PuzzleDir<'a> {
-    pub fn new(label: &'a str) -> PuzzleDir<'a> {
-        return PuzzleDir {
-            files: vec![],
-            subdirs: vec![],
-            label: label,
+impl FileSystem {
+    pub const ROOT: EntryId = 0;
+
+    /// Just an empty root directory.
+    pub fn new() -> FileSystem {
+        return FileSystem {
+            entries: vec![Entry {
+                name: "/".to_string(),
+                parent: None,
+                kind: EntryKind::Dir { children: vec![] },
+            }],
         };
     }
 
-    pub fn get_dir_size(&self) -> usize {
-        let file_sizes: usize = self.files.iter().map(|f| f.borrow().size).sum();
-        let subdir_sizes: usize = self.subdirs.iter().map(|d| d.borrow().get_dir_size()).sum();
+    /// Rebuild the tree from the commands and output in a terminal transcript.
+    ///
+    /// Handles `cd /`, `cd ..`, `cd` into directories not listed yet, and listing
+    /// a directory more than once, as long as the listings agree.
+    /// ```
+    /// let fs = day07::FileSystem::from_transcript(&day07::get_puzzle_sample()).unwrap();
+    /// let e = fs.lookup("/a/e").unwrap();
+    /// assert_eq!(fs.size(e), 584);
+    /// assert_eq!(fs.path(fs.parent(e).unwrap()), "/a");
+    /// ```
+    pub fn from_transcript(lines: &Vec<String>) -> Result<FileSystem, TerminalError> {
+        let mut fs = FileSystem::new();
+        let mut cur_dir = FileSystem::ROOT;
+        // Directory being listed, and what has been listed in it so far.
+        let mut listing: Option<Vec<EntryId>> = None;
+        for (i, line) in lines.iter().enumerate() {
+            let line_number = i + 1;
+            if line.starts_with('$') {
+                if let Some(listed) = listing.take() {
+                    fs.check_listing_complete(cur_dir, &listed, line_number)?;
+                }
+            }
+            if let Some(listed) = listing.as_mut() {
+                listed.push(fs.add_listed(cur_dir, line, line_number)?);
+                continue;
+            }
+            if line == "$ ls" {
+                listing = Some(vec![]);
+            } else if let Some(target) = line.strip_prefix("$ cd ") {
+                cur_dir = match target {
+                    "/" => FileSystem::ROOT,
+                    ".." => fs
+                        .parent(cur_dir)
+                        .ok_or(TerminalError::AboveRoot { line: line_number })?,
+                    name => fs.dir_for_cd(cur_dir, name, line_number)?,
+                };
+            } else {
+                return Err(TerminalError::BadLine {
+                    line: line_number,
+                    text: line.to_string(),
+                });
+            }
+        }
+        if let Some(listed) = listing {
+            fs.check_listing_complete(cur_dir, &listed, lines.len() + 1)?;
+        }
+        return Ok(fs);
+    }
 
-        // This line is just here to make some "dead code" warnings go away, so I keep debug info in.
-        let _ = self
-            .files
-            .iter()
-            .map(|f| f.borrow().get_label())
-            .collect::<Vec<&str>>();
+    // The directory `cd name` goes into, adding it if it hasn't been listed yet.
+    fn dir_for_cd(
+        &mut self,
+        dir: EntryId,
+        name: &str,
+        line: usize,
+    ) -> Result<EntryId, TerminalError> {
+        return match self.child(dir, name) {
+            Some(id) if self.is_dir(id) => Ok(id),
+            Some(id) => Err(TerminalError::NotADirectory {
+                line: line,
+                path: self.path(id),
+            }),
+            None => Ok(self.add_entry(dir, name, EntryKind::Dir { children: vec![] })),
+        };
+    }
+
+    // Add one line of `ls` output, or check it against what is already known.
+    fn add_listed(
+        &mut self,
+        dir: EntryId,
+        text: &str,
+        line: usize,
+    ) -> Result<EntryId, TerminalError> {
+        let bad_line = || TerminalError::BadLine {
+            line: line,
+            text: text.to_string(),
+        };
+        let (first, name) = text.split_once(' ').ok_or_else(bad_line)?;
+        let kind = if first == "dir" {
+            EntryKind::Dir { children: vec![] }
+        } else {
+            let size = first.parse::<usize>().map_err(|_| bad_line())?;
+            EntryKind::File { size: size }
+        };
+        let existing = match self.child(dir, name) {
+            Some(id) => id,
+            None => return Ok(self.add_entry(dir, name, kind)),
+        };
+        let matches = match (&self.entries[existing].kind, &kind) {
+            (EntryKind::Dir { .. }, EntryKind::Dir { .. }) => true,
+            (EntryKind::File { size: a }, EntryKind::File { size: b }) => a == b,
+            _ => false,
+        };
+        if !matches {
+            return Err(TerminalError::ConflictingEntry {
+                line: line,
+                path: self.path(existing),
+            });
+        }
+        return Ok(existing);
+    }
 
-        return file_sizes + subdir_sizes;
+    fn check_listing_complete(
+        &self,
+        dir: EntryId,
+        listed: &[EntryId],
+        line: usize,
+    ) -> Result<(), TerminalError> {
+        if let Some(missing) = self.children(dir).iter().find(|c| !listed.contains(c)) {
+            return Err(TerminalError::MissingEntry {
+                line: line,
+                path: self.path(*missing),
+            });
+        }
+        return Ok(());
     }
 
-    fn add_file(&mut self, f: PuzzleFile<'a>) {
-        self.files.push(Rc::new(RefCell::new(f)));
+    /// Add a directory or file, returning its id.
+    ///
+    /// Panics if `parent` is a file.
+    pub fn add_entry(&mut self, parent: EntryId, name: &str, kind: EntryKind) -> EntryId {
+        let id = self.entries.len();
+        match &mut self.entries[parent].kind {
+            EntryKind::Dir { children } => children.push(id),
+            EntryKind::File { .. } => panic!("Can't add {} to a file", name),
+        };
+        self.entries.push(Entry {
+            name: name.to_string(),
+            parent: Some(parent),
+            kind: kind,
+        });
+        return id;
     }
 
-    fn add_directory(&mut self, d: Rc<RefCell<PuzzleDir<'a>>>) {
-        self.subdirs.push(d);
+    pub fn entry(&self, id: EntryId) -> &Entry {
+        return &self.entries[id];
     }
 
-    fn find_subdir_by_label(&self, l: &str) -> Rc<RefCell<PuzzleDir<'a>>> {
-        let matching: Rc<RefCell<PuzzleDir>> = self
-            .subdirs
-            .iter()
-            .filter(|&d| d.borrow().label == l)
-            .nth(0)
-            .unwrap()
-            .clone();
-        return matching;
+    pub fn parent(&self, id: EntryId) -> Option<EntryId> {
+        return self.entries[id].parent;
+    }
+
+    pub fn is_dir(&self, id: EntryId) -> bool {
+        return matches!(self.entries[id].kind, EntryKind::Dir { .. });
+    }
+
+    /// Everything directly inside a directory, nothing for a file.
+    pub fn children(&self, id: EntryId) -> &[EntryId] {
+        return match &self.entries[id].kind {
+            EntryKind::Dir { children } => children,
+            EntryKind::File { .. } => &[],
+        };
     }
-}
 
-// A consquence of this is its best to always pass around Rc RefCells, you can do it without, but this is
-// honestly more intuitive.
-fn parse_terminal_output(termtext: &Vec<String>) -> Rc<RefCell<PuzzleDir>> {
-    let mut directory_stack: Vec<Rc<RefCell<PuzzleDir>>> = vec![];
+    pub fn child(&self, dir: EntryId, name: &str) -> Option<EntryId> {
+        return self
+            .children(dir)
+            .iter()
+            .copied()
+            .find(|c| self.entries[*c].name == name);
+    }
 
-    // cd / is first
-    let top_dir = Rc::new(RefCell::new(PuzzleDir::new("/")));
-    let mut cur_dir = top_dir.clone();
+    /// Every directory, starting with the root.
+    pub fn dirs(&self) -> impl Iterator<Item = EntryId> + '_ {
+        return (0..self.entries.len()).filter(|id| self.is_dir(*id));
+    }
 
-    let mut is_ls = false;
-    for line in termtext.into_iter().skip(1) {
-        if is_ls && line.starts_with("$") {
-            // end if is_ls
-            is_ls = false;
+    /// Absolute path, such as "/a/e".
+    pub fn path(&self, id: EntryId) -> String {
+        let mut names = vec![];
+        let mut cur = id;
+        while let Some(parent) = self.entries[cur].parent {
+            names.push(self.entries[cur].name.as_str());
+            cur = parent;
         }
-        if is_ls {
-            if line.starts_with("dir") {
-                let (_, label) = line.split_once("dir ").unwrap();
-                let new_dir = Rc::new(RefCell::new(PuzzleDir::new(label)));
-                cur_dir.borrow_mut().add_directory(new_dir);
-            } else {
-                let (size, label) = line.split_once(" ").unwrap();
-                let s = size.parse::<usize>().unwrap();
-                let f = PuzzleFile {
-                    label: label,
-                    size: s,
-                };
-                cur_dir.borrow_mut().add_file(f);
-            }
-            continue;
+        names.reverse();
+        return format!("/{}", names.join("/"));
+    }
+
+    /// Find an entry by its absolute path.
+    pub fn lookup(&self, path: &str) -> Option<EntryId> {
+        let mut cur = FileSystem::ROOT;
+        for name in path.split('/').filter(|n| !n.is_empty()) {
+            cur = self.child(cur, name)?;
         }
-        if line.starts_with("$ ls") {
-            is_ls = true;
-            continue;
+        return Some(cur);
+    }
+
+    /// Size of a file, or everything in a directory.
+    pub fn size(&self, id: EntryId) -> usize {
+        return match &self.entries[id].kind {
+            EntryKind::File { size } => *size,
+            EntryKind::Dir { children } => children.iter().map(|c| self.size(*c)).sum(),
+        };
+    }
+
+    // Size of every entry, found in one pass since children always come after their parent.
+    fn sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<usize> = self
+            .entries
+            .iter()
+            .map(|e| match e.kind {
+                EntryKind::File { size } => size,
+                EntryKind::Dir { .. } => 0,
+            })
+            .collect();
+        for id in (1..self.entries.len()).rev() {
+            let parent = self.entries[id].parent.unwrap();
+            sizes[parent] += sizes[id];
         }
-        if line.starts_with("$ cd ") {
-            let (_, cmd_dir) = line.split_once("$ cd ").unwrap();
-            if cmd_dir == ".." {
-                cur_dir = directory_stack.pop().unwrap().clone();
-            } else {
-                let found_dir = cur_dir.borrow().find_subdir_by_label(cmd_dir);
-                directory_stack.push(cur_dir.clone());
-                cur_dir = found_dir.clone();
+        return sizes;
+    }
+
+    /// Every directory with a size of at most `max_size`.
+    /// ```
+    /// let fs = day07::FileSystem::from_transcript(&day07::get_puzzle_sample()).unwrap();
+    /// let small: Vec<String> = fs.dirs_at_most(100000).iter().map(|d| fs.path(*d)).collect();
+    /// assert_eq!(small, vec!["/a", "/a/e"]);
+    /// ```
+    pub fn dirs_at_most(&self, max_size: usize) -> Vec<EntryId> {
+        let sizes = self.sizes();
+        return self.dirs().filter(|d| sizes[*d] <= max_size).collect();
+    }
+
+    /// The smallest directory that would leave `needed` space free on a disk of `disk_size` if deleted.
+    /// ```
+    /// let fs = day07::FileSystem::from_transcript(&day07::get_puzzle_sample()).unwrap();
+    /// let d = fs.smallest_dir_freeing(70000000, 30000000).unwrap();
+    /// assert_eq!(fs.path(d), "/d");
+    /// ```
+    pub fn smallest_dir_freeing(&self, disk_size: usize, needed: usize) -> Option<EntryId> {
+        let sizes = self.sizes();
+        let free = disk_size.saturating_sub(sizes[FileSystem::ROOT]);
+        let to_delete = needed.saturating_sub(free);
+        return self
+            .dirs()
+            .filter(|d| sizes[*d] >= to_delete)
+            .min_by_key(|d| sizes[*d]);
+    }
+
+    // Children sorted by name, so output doesn't depend on listing order.
+    fn sorted_children(&self, id: EntryId) -> Vec<EntryId> {
+        let mut children = self.children(id).to_vec();
+        children.sort_by(|a, b| self.entries[*a].name.cmp(&self.entries[*b].name));
+        return children;
+    }
+
+    /// Draw the tree the way the puzzle does, with entries sorted by name.
+    /// ```
+    /// let fs = day07::FileSystem::from_transcript(&day07::get_puzzle_sample()).unwrap();
+    /// let tree = fs.render_tree();
+    /// assert!(tree.starts_with("- / (dir)\n  - a (dir)\n    - e (dir)\n      - i (file, size=584)\n"));
+    /// ```
+    pub fn render_tree(&self) -> String {
+        let mut out = String::new();
+        self.render_entry(FileSystem::ROOT, 0, &mut out);
+        return out;
+    }
+
+    fn render_entry(&self, id: EntryId, depth: usize, out: &mut String) {
+        let entry = &self.entries[id];
+        let indent = "  ".repeat(depth);
+        match entry.kind {
+            EntryKind::File { size } => {
+                out.push_str(&format!(
+                    "{}- {} (file, size={})\n",
+                    indent, entry.name, size
+                ));
+            }
+            EntryKind::Dir { .. } => {
+                out.push_str(&format!("{}- {} (dir)\n", indent, entry.name));
+                for child in self.sorted_children(id) {
+                    self.render_entry(child, depth + 1, out);
+                }
             }
         }
     }
 
-    return top_dir;
+    /// Size and path of every directory, biggest first, like `du -h | sort -rh`.
+    /// ```
+    /// let fs = day07::FileSystem::from_transcript(&day07::get_puzzle_sample()).unwrap();
+    /// assert_eq!(fs.du_report(), "47M\t/\n24M\t/d\n93K\t/a\n584\t/a/e\n");
+    /// ```
+    pub fn du_report(&self) -> String {
+        let sizes = self.sizes();
+        let mut dirs: Vec<(usize, String)> =
+            self.dirs().map(|d| (sizes[d], self.path(d))).collect();
+        dirs.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        return dirs
+            .iter()
+            .map(|(size, path)| format!("{}\t{}\n", human_size(*size), path))
+            .collect();
+    }
+
+    /// The whole tree as JSON, with sizes worked out for directories too.
+    ///
+    /// Every entry is an object with `name`, `type` ("dir" or "file") and `size`,
+    /// and directories also have `children`, sorted by name.
+    /// ```
+    /// let lines = vec!["$ ls".to_string(), "dir a".to_string(), "5 \"q\"".to_string()];
+    /// let fs = day07::FileSystem::from_transcript(&lines).unwrap();
+    /// assert_eq!(
+    ///     fs.to_json(),
+    ///     r#"{"name":"/","type":"dir","size":5,"children":[{"name":"\"q\"","type":"file","size":5},{"name":"a","type":"dir","size":0,"children":[]}]}"#
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let sizes = self.sizes();
+        let mut out = String::new();
+        self.write_json(FileSystem::ROOT, &sizes, &mut out);
+        return out;
+    }
+
+    fn write_json(&self, id: EntryId, sizes: &[usize], out: &mut String) {
+        let entry = &self.entries[id];
+        let kind = if self.is_dir(id) { "dir" } else { "file" };
+        out.push_str(&format!(
+            "{{\"name\":{},\"type\":\"{}\",\"size\":{}",
+            json_string(&entry.name),
+            kind,
+            sizes[id]
+        ));
+        if self.is_dir(id) {
+            out.push_str(",\"children\":[");
+            for (i, child) in self.sorted_children(id).iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                self.write_json(*child, sizes, out);
+            }
+            out.push(']');
+        }
+        out.push('}');
+    }
 }
 
-fn sum_matching_folders(root: Rc<RefCell<PuzzleDir>>, max_size: usize) -> usize {
-    let mut sum = 0;
+// Quote and escape a string for JSON.
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    return out;
+}
 
-    let this_size = root.borrow().get_dir_size();
-    if this_size <= max_size {
-        sum += this_size;
+/// Size in powers of 1024, rounded up like `du -h`: one decimal place below 10, none above.
+/// ```
+/// assert_eq!(day07::human_size(584), "584");
+/// assert_eq!(day07::human_size(1024), "1.0K");
+/// assert_eq!(day07::human_size(94853), "93K");
+/// assert_eq!(day07::human_size(10 * 1024 - 1), "10K");
+/// ```
+pub fn human_size(bytes: usize) -> String {
+    let units = ["K", "M", "G", "T", "P"];
+    if bytes < 1024 {
+        return bytes.to_string();
     }
-    for child in &root.borrow().subdirs {
-        sum += sum_matching_folders(child.clone(), max_size);
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    loop {
+        let tenths = (value * 10.0).ceil() / 10.0;
+        let rounded = if tenths < 10.0 { tenths } else { value.ceil() };
+        // Rounding up can reach the next unit, as du shows 1048575 as 1.0M, not 1024K.
+        if rounded >= 1024.0 && unit + 1 < units.len() {
+            value /= 1024.0;
+            unit += 1;
+            continue;
+        }
+        if tenths < 10.0 {
+            return format!("{:.1}{}", tenths, units[unit]);
+        }
+        return format!("{}{}", rounded, units[unit]);
     }
-    return sum;
+}
+
+fn parse_terminal_output(termtext: &Vec<String>) -> FileSystem {
+    return match FileSystem::from_transcript(termtext) {
+        Ok(fs) => fs,
+        Err(e) => panic!("Can't follow the terminal: {}", e),
+    };
 }
 
 /// Solution to puzzle_a entry point
@@ -182,24 +521,8 @@ fn sum_matching_folders(root: Rc<RefCell<PuzzleDir>>, max_size: usize) -> usize
 /// ```
 pub fn puzzle_a(input: &Vec<String>) -> usize {
     let max_size = 100000;
-    let root = parse_terminal_output(input);
-    return sum_matching_folders(root, max_size);
-}
-
-fn find_smallest_folder_of_min_size(root: Rc<RefCell<PuzzleDir>>, min_size: usize) -> usize {
-    let mut cur_smallest = usize::MAX;
-
-    let this_size = root.borrow().get_dir_size();
-    if this_size >= min_size && this_size < cur_smallest {
-        cur_smallest = this_size;
-    }
-    for child in &root.borrow().subdirs {
-        let child_size = find_smallest_folder_of_min_size(child.clone(), min_size);
-        if child_size < cur_smallest {
-            cur_smallest = child_size;
-        }
-    }
-    return cur_smallest;
+    let fs = parse_terminal_output(input);
+    return fs.dirs_at_most(max_size).iter().map(|d| fs.size(*d)).sum();
 }
 
 /// Solution to puzzle_b entry point
@@ -210,23 +533,156 @@ fn find_smallest_folder_of_min_size(root: Rc<RefCell<PuzzleDir>>, min_size: usiz
 pub fn puzzle_b(input: &Vec<String>) -> usize {
     let total_size = 70000000;
     let required_size = 30000000;
-    let root = parse_terminal_output(input);
-    let needed_space = total_size - root.borrow().get_dir_size();
-    let min_size = required_size - needed_space;
-    return find_smallest_folder_of_min_size(root, min_size);
+    let fs = parse_terminal_output(input);
+    let dir = fs.smallest_dir_freeing(total_size, required_size).unwrap();
+    return fs.size(dir);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    // Remove dead code warning on label by just calling it
+    fn to_lines(lines: &[&str]) -> Vec<String> {
+        return lines.iter().map(|s| s.to_string()).collect();
+    }
+
     #[test]
-    fn test_get_label() {
-        let f = PuzzleFile {
-            label: "foo",
-            size: 5,
-        };
-        assert_eq!(f.get_label(), "foo");
+    fn test_sample_tree() {
+        let fs = FileSystem::from_transcript(&get_puzzle_sample()).unwrap();
+        let paths: Vec<String> = fs.dirs().map(|d| fs.path(d)).collect();
+        assert_eq!(paths, vec!["/", "/a", "/d", "/a/e"]);
+        assert_eq!(fs.size(FileSystem::ROOT), 48381165);
+        let i = fs.lookup("/a/e/i").unwrap();
+        assert_eq!(fs.entry(i).kind, EntryKind::File { size: 584 });
+        assert_eq!(fs.lookup("/a/x"), None);
+        assert_eq!(fs.lookup("/"), Some(FileSystem::ROOT));
+        assert!(fs.children(i).is_empty());
+    }
+
+    #[test]
+    fn test_cd_root_and_repeated_ls() {
+        let lines = to_lines(&[
+            "$ cd /", "$ cd x", "$ ls", "10 f", "$ cd /", "$ ls", "dir x", "5 g", "$ ls", "5 g",
+            "dir x", "$ cd x", "$ cd ..",
+        ]);
+        let fs = FileSystem::from_transcript(&lines).unwrap();
+        assert_eq!(fs.size(FileSystem::ROOT), 15);
+        assert_eq!(fs.children(FileSystem::ROOT).len(), 2);
+        assert_eq!(fs.path(fs.lookup("/x/f").unwrap()), "/x/f");
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |lines: &[&str]| FileSystem::from_transcript(&to_lines(lines)).unwrap_err();
+        assert_eq!(
+            err(&["$ cd /", "$ cd .."]),
+            TerminalError::AboveRoot { line: 2 }
+        );
+        assert_eq!(
+            err(&["$ ls", "5 f", "$ cd f"]),
+            TerminalError::NotADirectory {
+                line: 3,
+                path: "/f".to_string()
+            }
+        );
+        assert_eq!(
+            err(&["$ ls", "5 f", "$ ls", "6 f"]),
+            TerminalError::ConflictingEntry {
+                line: 4,
+                path: "/f".to_string()
+            }
+        );
+        assert_eq!(
+            err(&["$ ls", "dir a", "5 f", "$ ls", "dir a"]),
+            TerminalError::MissingEntry {
+                line: 6,
+                path: "/f".to_string()
+            }
+        );
+        assert_eq!(
+            err(&["$ cd a", "$ cd ..", "$ ls", "1 b", "$ cd /"]).to_string(),
+            "line 5: listing is missing /a"
+        );
+        assert_eq!(
+            err(&["$ rm -rf /"]),
+            TerminalError::BadLine {
+                line: 1,
+                text: "$ rm -rf /".to_string()
+            }
+        );
+        assert_eq!(
+            err(&["$ ls", "big f"]).to_string(),
+            "line 2: can't understand 'big f'"
+        );
+    }
+
+    #[test]
+    fn test_queries_match_brute_force() {
+        let fs = FileSystem::from_transcript(&get_puzzle_sample()).unwrap();
+        for limit in [0, 584, 94853, 100000, 24933642, 48381165] {
+            let expected: Vec<EntryId> = fs.dirs().filter(|d| fs.size(*d) <= limit).collect();
+            assert_eq!(fs.dirs_at_most(limit), expected);
+        }
+        assert_eq!(
+            fs.smallest_dir_freeing(48381165, 1),
+            Some(fs.lookup("/a/e").unwrap())
+        );
+        assert_eq!(
+            fs.smallest_dir_freeing(48381165, 48381165),
+            Some(FileSystem::ROOT)
+        );
+        assert_eq!(fs.smallest_dir_freeing(48381165, 48381166), None);
+        assert_eq!(fs.smallest_dir_freeing(100000000, 10), fs.lookup("/a/e"));
+        assert_eq!(FileSystem::new().smallest_dir_freeing(0, 10), None);
+    }
+
+    #[test]
+    fn test_render_sample_like_puzzle() {
+        let fs = FileSystem::from_transcript(&get_puzzle_sample()).unwrap();
+        let expected = [
+            "- / (dir)",
+            "  - a (dir)",
+            "    - e (dir)",
+            "      - i (file, size=584)",
+            "    - f (file, size=29116)",
+            "    - g (file, size=2557)",
+            "    - h.lst (file, size=62596)",
+            "  - b.txt (file, size=14848514)",
+            "  - c.dat (file, size=8504156)",
+            "  - d (dir)",
+            "    - d.ext (file, size=5626152)",
+            "    - d.log (file, size=8033020)",
+            "    - j (file, size=4060174)",
+            "    - k (file, size=7214296)",
+        ];
+        assert_eq!(fs.render_tree(), expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn test_json_escapes_and_sizes() {
+        assert_eq!(json_string("a\\b\n\u{1}"), "\"a\\\\b\\n\\u0001\"");
+        let fs = FileSystem::from_transcript(&get_puzzle_sample()).unwrap();
+        let json = fs.to_json();
+        assert!(json.starts_with(r#"{"name":"/","type":"dir","size":48381165,"children":[{"name":"a","type":"dir","size":94853,"#));
+        assert_eq!(json.matches('{').count(), 14);
+    }
+
+    #[test]
+    fn test_human_size_rounds_up() {
+        assert_eq!(human_size(0), "0");
+        assert_eq!(human_size(1023), "1023");
+        assert_eq!(human_size(1025), "1.1K");
+        assert_eq!(human_size(1024 * 1024), "1.0M");
+        assert_eq!(human_size(48381165), "47M");
+    }
+
+    #[test]
+    fn test_human_size_unit_boundary() {
+        // Just under a unit rounds up into the next one, as du does.
+        assert_eq!(human_size(1024 * 1024 - 1), "1.0M");
+        assert_eq!(human_size(1023 * 1024 + 1), "1.0M");
+        assert_eq!(human_size(1023 * 1024), "1023K");
+        assert_eq!(human_size(1024 * 1024 * 1024 - 1), "1.0G");
+        assert_eq!(human_size(10 * 1024 * 1024 - 1), "10M");
     }
 }
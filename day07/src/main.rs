@@ -1,11 +1,29 @@
 use day07::load_no_blanks;
 use day07::puzzle_a;
 use day07::puzzle_b;
+use day07::FileSystem;
+use std::env;
 
 fn main() {
     let filename = "input";
     let template = load_no_blanks(filename);
 
+    // --tree, --du or --json print the rebuilt filesystem instead of the answers.
+    let args: Vec<String> = env::args().collect();
+    let views = ["--tree", "--du", "--json"];
+    if let Some(view) = args.iter().find(|a| views.contains(&a.as_str())) {
+        let fs = match FileSystem::from_transcript(&template) {
+            Ok(fs) => fs,
+            Err(e) => panic!("Can't follow the terminal: {}", e),
+        };
+        match view.as_str() {
+            "--tree" => print!("{}", fs.render_tree()),
+            "--du" => print!("{}", fs.du_report()),
+            _ => println!("{}", fs.to_json()),
+        }
+        return;
+    }
+
     let value = puzzle_a(&template);
     println!("Answer to 1st question: {}", value);
 
@@ -4,92 +4,204 @@ pub use filelib::load_no_blanks;
 use gridlib::{Direction, GridCoordinateInf};
 use std::collections::HashSet;
 
-#[derive(Debug)]
-struct State {
-    head: GridCoordinateInf,
-    tail: GridCoordinateInf,
-    tail_locations: HashSet<GridCoordinateInf>,
+/// Where a knot moves to, given the knot in front of it and where it is now.
+pub type FollowRule = fn(GridCoordinateInf, GridCoordinateInf) -> GridCoordinateInf;
+
+/// The puzzle's rule: stay put while touching (diagonals included), otherwise take
+/// one step towards the leader on each axis where they differ.
+/// ```
+/// use gridlib::GridCoordinateInf as C;
+/// assert_eq!(day09::follow(C::new(2, 0), C::new(0, 0)), C::new(1, 0));
+/// assert_eq!(day09::follow(C::new(2, 1), C::new(0, 0)), C::new(1, 1));
+/// assert_eq!(day09::follow(C::new(1, 1), C::new(0, 0)), C::new(0, 0));
+/// ```
+pub fn follow(leader: GridCoordinateInf, follower: GridCoordinateInf) -> GridCoordinateInf {
+    let dx = leader.x - follower.x;
+    let dy = leader.y - follower.y;
+    if dx.abs() <= 1 && dy.abs() <= 1 {
+        return follower;
+    }
+    return follower + GridCoordinateInf::new(dx.signum(), dy.signum());
 }
 
-impl State {
-    pub fn new(location: GridCoordinateInf) -> State {
-        let mut set: HashSet<GridCoordinateInf> = HashSet::new();
-        set.insert(location);
-        //println!("Inserting {}", location);
-        return State {
-            head: location,
-            tail: location,
-            tail_locations: set,
+/// A rope of knots, the head first, where every other knot follows the one in front.
+#[derive(Debug, Clone)]
+pub struct Rope {
+    knots: Vec<GridCoordinateInf>,
+    start: GridCoordinateInf,
+    rule: FollowRule,
+    // Knot index, and every cell it has been in since tracking started.
+    visited: Vec<(usize, HashSet<GridCoordinateInf>)>,
+}
+
+impl Rope {
+    /// Every knot starts at the origin and follows by `follow`.
+    ///
+    /// Panics if there are no knots.
+    pub fn new(knots: usize) -> Rope {
+        return Rope::with_rule(knots, GridCoordinateInf::new(0, 0), follow);
+    }
+
+    pub fn with_rule(knots: usize, start: GridCoordinateInf, rule: FollowRule) -> Rope {
+        if knots == 0 {
+            panic!("Can't make a rope with no knots");
+        }
+        return Rope {
+            knots: vec![start; knots],
+            start: start,
+            rule: rule,
+            visited: vec![],
         };
     }
 
-    fn move_step(&mut self, direction: Direction, times: usize) {
-        for _ in 0..times {
-            self.head = move_head(self.head, direction);
-            self.tail = move_tail(self.head, self.tail);
-            //println!("Inserting {}, Head: {}", self.tail, self.head);
-            self.tail_locations.insert(self.tail);
+    pub fn knots(&self) -> &[GridCoordinateInf] {
+        return &self.knots;
+    }
+
+    pub fn head(&self) -> GridCoordinateInf {
+        return self.knots[0];
+    }
+
+    pub fn tail(&self) -> GridCoordinateInf {
+        return self.knots[self.knots.len() - 1];
+    }
+
+    /// Start remembering where a knot goes, from where it is now.
+    ///
+    /// Panics if there's no such knot.
+    pub fn track(&mut self, knot: usize) {
+        if knot >= self.knots.len() {
+            panic!(
+                "Can't track knot {} of a rope with {}",
+                knot,
+                self.knots.len()
+            );
+        }
+        if self.visited.iter().all(|(k, _)| *k != knot) {
+            self.visited.push((knot, HashSet::from([self.knots[knot]])));
         }
     }
 
-    fn get_num_tail(&self) -> usize {
-        return self.tail_locations.len();
+    /// Every cell a tracked knot has been in, or None if it isn't tracked.
+    pub fn visited(&self, knot: usize) -> Option<&HashSet<GridCoordinateInf>> {
+        return self
+            .visited
+            .iter()
+            .find(|(k, _)| *k == knot)
+            .map(|(_, v)| v);
     }
-}
 
-fn move_tail(head: GridCoordinateInf, tail: GridCoordinateInf) -> GridCoordinateInf {
-    // cardinal directions, if two up, the tail move up one.
-    let two_up = GridCoordinateInf::new(0, -2);
-    let two_down = GridCoordinateInf::new(0, 2);
-    let two_left = GridCoordinateInf::new(-2, 0);
-    let two_right = GridCoordinateInf::new(2, 0);
-
-    if (tail + two_up) == head {
-        return tail + GridCoordinateInf::new(0, -1);
-    } else if (tail + two_down) == head {
-        return tail + GridCoordinateInf::new(0, 1);
-    } else if (tail + two_left) == head {
-        return tail + GridCoordinateInf::new(-1, 0);
-    } else if (tail + two_right) == head {
-        return tail + GridCoordinateInf::new(1, 0);
-    }
-
-    // check if adjacent on diagonals
-    let northeast = GridCoordinateInf::new(1, -1);
-    let northwest = GridCoordinateInf::new(-1, -1);
-    let southeast = GridCoordinateInf::new(1, 1);
-    let southwest = GridCoordinateInf::new(-1, 1);
-    if (tail + northeast) == head
-        || (tail + northwest) == head
-        || (tail + southeast) == head
-        || (tail + southwest) == head
-    {
-        return tail;
-    }
-
-    if tail.x == head.x || tail.y == head.y {
-        return tail;
-    }
-
-    // Determine which way to move
-    if tail.x < head.x {
-        if tail.y < head.y {
-            return tail + southeast;
+    /// Move the head one step, and let the rest follow.
+    pub fn step(&mut self, direction: Direction) {
+        self.knots[0] = self.knots[0].move_dir(direction);
+        for i in 1..self.knots.len() {
+            self.knots[i] = (self.rule)(self.knots[i - 1], self.knots[i]);
         }
-        return tail + northeast;
-    } else {
-        if tail.y < head.y {
-            return tail + southwest;
+        for (knot, cells) in self.visited.iter_mut() {
+            cells.insert(self.knots[*knot]);
         }
-        return tail + northwest;
+    }
+
+    /// Move the head several steps in one direction.
+    pub fn apply(&mut self, motion: Motion) {
+        let (direction, times) = motion;
+        for _ in 0..times {
+            self.step(direction);
+        }
+    }
+
+    // H for the head, then 1, 2, 3 and so on, or T for the tail of a two knot rope.
+    fn label(&self, knot: usize) -> char {
+        if knot == 0 {
+            return 'H';
+        }
+        if self.knots.len() == 2 {
+            return 'T';
+        }
+        return std::char::from_digit((knot % 36) as u32, 36).unwrap();
+    }
+
+    // Top left and bottom right corners fitting the knots and the start.
+    fn bounds(&self) -> (GridCoordinateInf, GridCoordinateInf) {
+        let cells = self.knots.iter().chain(std::iter::once(&self.start));
+        let min_x = cells.clone().map(|c| c.x).min().unwrap();
+        let min_y = cells.clone().map(|c| c.y).min().unwrap();
+        let max_x = cells.clone().map(|c| c.x).max().unwrap();
+        let max_y = cells.map(|c| c.y).max().unwrap();
+        return (
+            GridCoordinateInf::new(min_x, min_y),
+            GridCoordinateInf::new(max_x, max_y),
+        );
+    }
+
+    /// Draw the rope just big enough to fit, in the puzzle's style.
+    pub fn render(&self) -> String {
+        let (min, max) = self.bounds();
+        return self.render_area(min, max);
+    }
+
+    /// Draw the cells from `min` to `max` inclusive, with `s` for the start and knots
+    /// nearer the head drawn over the ones behind.
+    /// ```
+    /// use gridlib::{Direction, GridCoordinateInf as C};
+    /// let mut rope = day09::Rope::new(10);
+    /// rope.apply((Direction::EAST, 4));
+    /// assert_eq!(rope.render_area(C::new(0, -2), C::new(5, 0)), "......\n......\n4321H.\n");
+    /// ```
+    pub fn render_area(&self, min: GridCoordinateInf, max: GridCoordinateInf) -> String {
+        return render_cells(min, max, |c| {
+            if let Some(knot) = self.knots.iter().position(|k| *k == c) {
+                return self.label(knot);
+            }
+            if c == self.start {
+                return 's';
+            }
+            return '.';
+        });
+    }
+
+    /// Draw where a tracked knot has been with `#`, and the start with `s`.
+    pub fn render_visited(&self, knot: usize) -> Option<String> {
+        let cells = self.visited(knot)?;
+        let all = cells.iter().chain(std::iter::once(&self.start));
+        let min = GridCoordinateInf::new(
+            all.clone().map(|c| c.x).min().unwrap(),
+            all.clone().map(|c| c.y).min().unwrap(),
+        );
+        let max = GridCoordinateInf::new(
+            all.clone().map(|c| c.x).max().unwrap(),
+            all.map(|c| c.y).max().unwrap(),
+        );
+        return Some(render_cells(min, max, |c| {
+            if c == self.start {
+                return 's';
+            }
+            if cells.contains(&c) {
+                return '#';
+            }
+            return '.';
+        }));
     }
 }
 
-fn move_head(head: GridCoordinateInf, direction: Direction) -> GridCoordinateInf {
-    return head.move_dir(direction);
+fn render_cells<F>(min: GridCoordinateInf, max: GridCoordinateInf, cell: F) -> String
+where
+    F: Fn(GridCoordinateInf) -> char,
+{
+    let mut s = String::new();
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            s.push(cell(GridCoordinateInf::new(x, y)));
+        }
+        s.push('\n');
+    }
+    return s;
 }
 
-fn parse_input_to_directions(input: &Vec<String>) -> Vec<(Direction, usize)> {
+/// Which way the head moves, and how many steps.
+pub type Motion = (Direction, usize);
+
+pub fn parse_input_to_directions(input: &Vec<String>) -> Vec<Motion> {
     return input
         .iter()
         .map(|line| {
@@ -110,6 +222,16 @@ fn parse_input_to_directions(input: &Vec<String>) -> Vec<(Direction, usize)> {
         .collect();
 }
 
+/// How many cells the tail of a rope with this many knots visits.
+pub fn count_tail_visits(input: &Vec<String>, knots: usize) -> usize {
+    let mut rope = Rope::new(knots);
+    rope.track(knots - 1);
+    for motion in parse_input_to_directions(input) {
+        rope.apply(motion);
+    }
+    return rope.visited(knots - 1).unwrap().len();
+}
+
 /// Solution to puzzle_a entry point
 /// ```
 /// let vec1: Vec<String> = vec!["R 4", "U 4", "L 3", "D 1",
@@ -117,69 +239,103 @@ fn parse_input_to_directions(input: &Vec<String>) -> Vec<(Direction, usize)> {
 /// assert_eq!(day09::puzzle_a(&vec1), 13);
 /// ```
 pub fn puzzle_a(input: &Vec<String>) -> usize {
-    let steps = parse_input_to_directions(input);
-    let mut state = State::new(GridCoordinateInf::new(0, 0));
-    for (dir, num) in steps {
-        state.move_step(dir, num);
-    }
-    return state.get_num_tail();
+    return count_tail_visits(input, 2);
 }
 
-#[derive(Debug)]
-struct StateSegmented {
-    head: GridCoordinateInf,
-    segments: Vec<GridCoordinateInf>,
-    tail_locations: HashSet<GridCoordinateInf>,
+/// Solution to puzzle_b entry point
+/// ```
+/// let vec1: Vec<String> = vec!["R 5", "U 8", "L 8", "D 3", "R 17", "D 10", "L 25", "U 20"].iter().map(|s| s.to_string()).collect();
+/// assert_eq!(day09::puzzle_b(&vec1), 36);
+/// ```
+pub fn puzzle_b(input: &Vec<String>) -> usize {
+    return count_tail_visits(input, 10);
 }
 
-impl StateSegmented {
-    pub fn new(location: GridCoordinateInf, num_segments: usize) -> StateSegmented {
-        let mut set: HashSet<GridCoordinateInf> = HashSet::new();
-        set.insert(location);
-        let mut segments = vec![];
-        for _ in 0..num_segments {
-            segments.push(location);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_example() -> Vec<String> {
+        return ["R 4", "U 4", "L 3", "D 1", "R 4", "D 1", "L 5", "R 2"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+    }
+
+    #[test]
+    fn test_follow_never_leaves_a_gap() {
+        for lx in -2..=2 {
+            for ly in -2..=2 {
+                let moved = follow(GridCoordinateInf::new(lx, ly), GridCoordinateInf::new(0, 0));
+                assert!((lx - moved.x).abs() <= 1 && (ly - moved.y).abs() <= 1);
+                assert!(moved.x.abs() <= 1 && moved.y.abs() <= 1);
+            }
         }
-        //println!("Inserting {}", location);
-        return StateSegmented {
-            head: location,
-            segments: segments,
-            tail_locations: set,
-        };
     }
 
-    fn move_step(&mut self, direction: Direction, times: usize) {
-        for _ in 0..times {
-            self.head = move_head(self.head, direction);
-            for i in 0..self.segments.len() {
-                if i == 0 {
-                    self.segments[i] = move_tail(self.head, self.segments[i]);
-                } else {
-                    self.segments[i] = move_tail(self.segments[i - 1], self.segments[i]);
-                }
-                //println!("Moved segment {} to {}", i, self.segments[i]);
+    #[test]
+    fn test_track_any_knot() {
+        let mut rope = Rope::new(10);
+        rope.track(0);
+        rope.track(1);
+        rope.track(9);
+        for motion in parse_input_to_directions(&small_example()) {
+            rope.apply(motion);
+        }
+        // Knot 1 of a long rope moves just like the tail of a short one.
+        assert_eq!(rope.visited(1).unwrap().len(), 13);
+        assert_eq!(rope.visited(9).unwrap().len(), 1);
+        let mut head = GridCoordinateInf::new(0, 0);
+        let mut head_cells = HashSet::from([head]);
+        for (direction, times) in parse_input_to_directions(&small_example()) {
+            for _ in 0..times {
+                head = head.move_dir(direction);
+                head_cells.insert(head);
             }
-            //println!("Inserting {}, Head: {}", self.segments[self.segments.len() - 1], self.head);
-            self.tail_locations
-                .insert(self.segments[self.segments.len() - 1]);
         }
+        assert_eq!(rope.visited(0), Some(&head_cells));
+        assert_eq!(rope.visited(5), None);
     }
 
-    fn get_num_tail(&self) -> usize {
-        return self.tail_locations.len();
+    #[test]
+    fn test_render_matches_puzzle() {
+        let mut rope = Rope::new(2);
+        rope.track(1);
+        for motion in parse_input_to_directions(&small_example()) {
+            rope.apply(motion);
+        }
+        let area = rope.render_area(GridCoordinateInf::new(0, -4), GridCoordinateInf::new(5, 0));
+        assert_eq!(area, "......\n......\n.TH...\n......\ns.....\n");
+        assert_eq!(
+            rope.render_visited(1).unwrap(),
+            "..##.\n...##\n.####\n....#\ns###.\n"
+        );
     }
-}
 
-/// Solution to puzzle_b entry point
-/// ```
-/// let vec1: Vec<String> = vec!["R 5", "U 8", "L 8", "D 3", "R 17", "D 10", "L 25", "U 20"].iter().map(|s| s.to_string()).collect();
-/// assert_eq!(day09::puzzle_b(&vec1), 36);
-/// ```
-pub fn puzzle_b(input: &Vec<String>) -> usize {
-    let steps = parse_input_to_directions(input);
-    let mut state = StateSegmented::new(GridCoordinateInf::new(0, 0), 9);
-    for (dir, num) in steps {
-        state.move_step(dir, num);
+    #[test]
+    fn test_custom_rule() {
+        // Knots that only ever close the gap along the longer axis.
+        fn lazy(leader: GridCoordinateInf, follower: GridCoordinateInf) -> GridCoordinateInf {
+            let (dx, dy) = (leader.x - follower.x, leader.y - follower.y);
+            if dx.abs() <= 1 && dy.abs() <= 1 {
+                return follower;
+            }
+            if dx.abs() >= dy.abs() {
+                return follower + GridCoordinateInf::new(dx.signum(), 0);
+            }
+            return follower + GridCoordinateInf::new(0, dy.signum());
+        }
+        let mut rope = Rope::with_rule(3, GridCoordinateInf::new(0, 0), lazy);
+        rope.apply((Direction::EAST, 2));
+        rope.apply((Direction::NORTH, 2));
+        assert_eq!(
+            rope.knots(),
+            [
+                GridCoordinateInf::new(2, -2),
+                GridCoordinateInf::new(1, -1),
+                GridCoordinateInf::new(0, 0)
+            ]
+        );
+        assert_eq!(rope.render(), "..H\n.1.\n2..\n");
     }
-    return state.get_num_tail();
 }
@@ -1,11 +1,30 @@
 use day09::load_no_blanks;
+use day09::parse_input_to_directions;
 use day09::puzzle_a;
 use day09::puzzle_b;
+use day09::Rope;
+use std::env;
 
 fn main() {
     let filename = "input";
     let template = load_no_blanks(filename);
 
+    // --trace [knots] draws the rope after every motion, then where the tail went.
+    let args: Vec<String> = env::args().collect();
+    if let Some(i) = args.iter().position(|a| a == "--trace") {
+        let knots = args.get(i + 1).map_or(10, |n| {
+            n.parse::<usize>().expect("Knots should be a number")
+        });
+        let mut rope = Rope::new(knots);
+        rope.track(knots - 1);
+        for (line, motion) in template.iter().zip(parse_input_to_directions(&template)) {
+            rope.apply(motion);
+            println!("== {} ==\n{}", line, rope.render());
+        }
+        print!("{}", rope.render_visited(knots - 1).unwrap());
+        return;
+    }
+
     let value = puzzle_a(&template);
     println!("Answer to 1st question: {}", value);
 
@@ -2,6 +2,7 @@ extern crate filelib;
 
 pub use filelib::load_no_blanks;
 use gridlib::{GridCoordinate, GridTraversable};
+pub use ocrlib::OcrError;
 pub use std::fmt::{Display, Formatter};
 
 pub fn get_example_input() -> Vec<String> {
@@ -28,144 +29,298 @@ pub fn get_example_input() -> Vec<String> {
     .collect();
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum Operation {
-    Addx,
-    Noop,
+/// Index of the X register, the only one the puzzle's CPU has.
+pub const X: usize = 0;
+
+/// What an instruction does to the registers once its last cycle is over.
+pub type Effect = fn(&mut [i64], &[i64]);
+
+/// One instruction the CPU understands.
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub name: String,
+    pub operands: usize,
+    /// How many cycles it takes. Zero means it happens between cycles.
+    pub cycles: usize,
+    pub effect: Effect,
 }
 
-impl Operation {
-    pub fn new(s: &str) -> Operation {
-        return match s {
-            "addx" => Operation::Addx,
-            "noop" => Operation::Noop,
-            _ => panic!("Unknown"),
+/// Every instruction a program can use, looked up by name.
+#[derive(Debug, Clone, Default)]
+pub struct InstructionSet {
+    instructions: Vec<Instruction>,
+}
+
+impl InstructionSet {
+    pub fn new() -> InstructionSet {
+        return InstructionSet {
+            instructions: vec![],
         };
     }
 
-    pub fn get_clock_cycles(&self) -> usize {
-        return match *self {
-            Operation::Addx => 2,
-            Operation::Noop => 1,
+    /// `addx V` and `noop`, as in the puzzle.
+    pub fn standard() -> InstructionSet {
+        let mut set = InstructionSet::new();
+        set.register("noop", 0, 1, |_, _| ());
+        set.register("addx", 1, 2, |regs, args| regs[X] += args[0]);
+        return set;
+    }
+
+    /// Add an instruction, replacing any with the same name.
+    /// ```
+    /// let mut set = day10::InstructionSet::standard();
+    /// set.register("mulx", 1, 3, |regs, args| regs[day10::X] *= args[0]);
+    /// let program = day10::assemble(&vec!["mulx 5".to_string()], &set).unwrap();
+    /// let cpu = day10::Cpu::new(&program, vec![2]);
+    /// assert_eq!(cpu.run(), vec![10]);
+    /// ```
+    pub fn register(&mut self, name: &str, operands: usize, cycles: usize, effect: Effect) {
+        let instruction = Instruction {
+            name: name.to_string(),
+            operands: operands,
+            cycles: cycles,
+            effect: effect,
         };
+        match self.instructions.iter_mut().find(|i| i.name == name) {
+            Some(existing) => *existing = instruction,
+            None => self.instructions.push(instruction),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Instruction> {
+        return self.instructions.iter().find(|i| i.name == name);
     }
 }
 
-impl Display for Operation {
+/// Why a program couldn't be assembled, with the line it went wrong on, counting from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssembleError {
+    UnknownInstruction {
+        line: usize,
+        name: String,
+    },
+    WrongOperandCount {
+        line: usize,
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    BadOperand {
+        line: usize,
+        text: String,
+    },
+}
+
+impl Display for AssembleError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let s = match self {
-            Operation::Addx => "addx",
-            Operation::Noop => "noop",
+        return match self {
+            AssembleError::UnknownInstruction { line, name } => {
+                write!(f, "line {}: no instruction called '{}'", line, name)
+            }
+            AssembleError::WrongOperandCount {
+                line,
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: {} takes {} operands, not {}",
+                line, name, expected, found
+            ),
+            AssembleError::BadOperand { line, text } => {
+                write!(f, "line {}: '{}' isn't a number", line, text)
+            }
         };
-        return write!(f, "{}", s);
     }
 }
 
-#[derive(Debug)]
-struct State {
-    x: i32,
-    clock: usize,
-    screen_state: gridlib::Grid<bool>,
-    // 1 = width 3 (left, middle, right).
-    // 2 = width 5 (left1, left2, middle, right1, right2)
-    sprite_pixels_adjacent: usize,
+/// An instruction with its operands, ready to run.
+#[derive(Debug, Clone)]
+pub struct Op {
+    pub instruction: Instruction,
+    pub args: Vec<i64>,
 }
 
-impl State {
-    pub fn new() -> State {
-        let all_off = vec![false; 40 * 6];
-        let grid = gridlib::Grid::new(40, 6, all_off);
-        return State {
-            x: 1,
-            clock: 1,
-            screen_state: grid,
-            sprite_pixels_adjacent: 1,
+/// Turn lines like `addx -3` into instructions from the set.
+pub fn assemble(lines: &Vec<String>, set: &InstructionSet) -> Result<Vec<Op>, AssembleError> {
+    let mut program = vec![];
+    for (i, text) in lines.iter().enumerate() {
+        let line = i + 1;
+        let mut words = text.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name,
+            None => continue,
         };
+        let instruction = set
+            .get(name)
+            .ok_or_else(|| AssembleError::UnknownInstruction {
+                line: line,
+                name: name.to_string(),
+            })?;
+        let args = words
+            .map(|w| {
+                w.parse::<i64>().map_err(|_| AssembleError::BadOperand {
+                    line: line,
+                    text: w.to_string(),
+                })
+            })
+            .collect::<Result<Vec<i64>, AssembleError>>()?;
+        if args.len() != instruction.operands {
+            return Err(AssembleError::WrongOperandCount {
+                line: line,
+                name: name.to_string(),
+                expected: instruction.operands,
+                found: args.len(),
+            });
+        }
+        program.push(Op {
+            instruction: instruction.clone(),
+            args: args,
+        });
     }
+    return Ok(program);
+}
 
-    pub fn do_op(&mut self, op: Operation, v: i32) {
-        let sprite_radius: i32 = self.sprite_pixels_adjacent.try_into().unwrap();
-        let width: i32 = self.screen_state.get_width().try_into().unwrap();
-
-        let mut cycles = op.get_clock_cycles();
-        while cycles > 0 {
-            let clocki32: i32 = self.clock.try_into().unwrap();
-            let col: i32 = (clocki32 - 1) % width;
-            let min = self.x - sprite_radius;
-            let max = self.x + sprite_radius;
-            //println!("c: {}, x: {}", self.clock, self.x);
-            //println!("min: {}, max: {}", min, max);
-            let coordinate_x = (self.clock - 1) % self.screen_state.get_width();
-            let coordinate_y = (self.clock - 1) / self.screen_state.get_width();
-            let coordinate = GridCoordinate::new(coordinate_x, coordinate_y);
-            let value = col >= min && col <= max;
-            //println!("Storing {} to {}", value, coordinate);
-            self.screen_state.set_value(coordinate, value);
-
-            cycles -= 1;
-            self.clock += 1;
-        }
-        match op {
-            Operation::Addx => self.x += v,
-            Operation::Noop => (),
+/// Runs a program, one cycle at a time.
+///
+/// As an iterator it gives each cycle number, counting from 1, with the registers
+/// as they are during that cycle.
+/// ```
+/// let program = day10::assemble(&day10::get_example_input(), &day10::InstructionSet::standard()).unwrap();
+/// let during_20 = day10::Cpu::new(&program, vec![1]).find(|(cycle, _)| *cycle == 20).unwrap();
+/// assert_eq!(during_20.1[day10::X], 21);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cpu<'a> {
+    program: &'a [Op],
+    pc: usize,
+    // Cycles left for the instruction at pc, or None if it hasn't started yet.
+    cycles_left: Option<usize>,
+    cycle: usize,
+    registers: Vec<i64>,
+}
+
+impl<'a> Cpu<'a> {
+    pub fn new(program: &'a [Op], registers: Vec<i64>) -> Cpu<'a> {
+        return Cpu {
+            program: program,
+            pc: 0,
+            cycles_left: None,
+            cycle: 1,
+            registers: registers,
         };
-        //println!("c: {}, x: {}", self.clock, self.x);
     }
 
-    pub fn get_signal_strength(&self, offset: usize) -> i32 {
-        let clock_on_cycle: i32 = (self.clock + offset).try_into().unwrap();
-        let s = clock_on_cycle * self.x;
-        //println!("c: {}, x: {} = {}", clock_on_cycle, self.x, s);
-        return s;
+    /// Run to the end and return the final registers.
+    pub fn run(mut self) -> Vec<i64> {
+        while self.next().is_some() {}
+        self.finish_instructions();
+        return self.registers;
     }
 
-    pub fn will_pass_cycle_mod(&self, op: Operation, cycle: usize) -> bool {
-        let c = (self.clock + op.get_clock_cycles()) % cycle;
-        return c != 0 && self.clock % cycle > c;
+    // Apply any instructions that take no time, since they'd otherwise wait for another cycle.
+    fn finish_instructions(&mut self) {
+        while self.pc < self.program.len() {
+            let op = &self.program[self.pc];
+            let left = *self.cycles_left.get_or_insert(op.instruction.cycles);
+            if left > 0 {
+                return;
+            }
+            (op.instruction.effect)(&mut self.registers, &op.args);
+            self.pc += 1;
+            self.cycles_left = None;
+        }
     }
+}
 
-    pub fn should_get_signal_strengh(&self, cycle: usize) -> bool {
-        return self.clock % cycle == 0;
-    }
+impl Iterator for Cpu<'_> {
+    type Item = (usize, Vec<i64>);
 
-    pub fn printscreen(&self) -> String {
-        let mut s = "\n".to_string();
-        let width = self.screen_state.get_width();
-        let height = self.screen_state.get_height();
-        for y in 0..height {
-            for x in 0..width {
-                if self
-                    .screen_state
-                    .get_value(GridCoordinate::new(x, y))
-                    .unwrap()
-                {
-                    s.push('#');
-                } else {
-                    s.push('.');
-                }
-            }
-            s.push('\n');
+    fn next(&mut self) -> Option<Self::Item> {
+        self.finish_instructions();
+        let left = self.cycles_left.as_mut()?;
+        *left -= 1;
+        let item = (self.cycle, self.registers.clone());
+        if *left == 0 {
+            let op = &self.program[self.pc];
+            (op.instruction.effect)(&mut self.registers, &op.args);
+            self.pc += 1;
+            self.cycles_left = None;
         }
-        return s;
+        self.cycle += 1;
+        return Some(item);
     }
 }
 
-fn parse_input(input: &Vec<String>) -> Vec<(Operation, i32)> {
-    return input
-        .iter()
-        .map(|line| {
-            let mut v = 0;
-            let op: Operation;
-            if line.starts_with("n") {
-                op = Operation::new(line);
+/// Size of the CRT, and how far either side of X the sprite reaches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenConfig {
+    pub width: usize,
+    pub height: usize,
+    pub sprite_radius: usize,
+}
+
+impl Default for ScreenConfig {
+    fn default() -> Self {
+        return ScreenConfig {
+            width: 40,
+            height: 6,
+            sprite_radius: 1,
+        };
+    }
+}
+
+/// Draw one pixel per cycle, left to right and top to bottom, lit where the sprite
+/// around X covers it. Stops when the program or the screen runs out.
+/// ```
+/// let program = day10::assemble(&vec!["noop".to_string(), "addx 3".to_string()], &day10::InstructionSet::standard()).unwrap();
+/// let config = day10::ScreenConfig { width: 3, height: 1, sprite_radius: 0 };
+/// let screen = day10::draw_screen(day10::Cpu::new(&program, vec![1]), &config);
+/// assert_eq!(day10::render_screen(&screen), "\n.#.\n");
+/// ```
+pub fn draw_screen<I>(cycles: I, config: &ScreenConfig) -> gridlib::Grid<bool>
+where
+    I: Iterator<Item = (usize, Vec<i64>)>,
+{
+    let mut grid = gridlib::Grid::new(
+        config.width,
+        config.height,
+        vec![false; config.width * config.height],
+    );
+    if config.width == 0 {
+        return grid;
+    }
+    for (cycle, registers) in cycles.take(config.width * config.height) {
+        let coordinate =
+            GridCoordinate::new((cycle - 1) % config.width, (cycle - 1) / config.width);
+        let col = coordinate.x as i64;
+        let lit = (col - registers[X]).unsigned_abs() <= config.sprite_radius as u64;
+        grid.set_value(coordinate, lit);
+    }
+    return grid;
+}
+
+/// `#` for lit pixels and `.` for dark ones, starting with a newline so it lines up when printed.
+pub fn render_screen(screen: &gridlib::Grid<bool>) -> String {
+    let mut s = "\n".to_string();
+    for y in 0..screen.get_height() {
+        for x in 0..screen.get_width() {
+            if screen.get_value(GridCoordinate::new(x, y)).unwrap() {
+                s.push('#');
             } else {
-                let (a, b) = line.split_once(" ").unwrap();
-                op = Operation::new(a);
-                v = b.parse::<i32>().unwrap();
+                s.push('.');
             }
-            return (op, v);
-        })
-        .collect();
+        }
+        s.push('\n');
+    }
+    return s;
+}
+
+fn parse_input(input: &Vec<String>) -> Vec<Op> {
+    return match assemble(input, &InstructionSet::standard()) {
+        Ok(program) => program,
+        Err(e) => panic!("Can't assemble the program: {}", e),
+    };
 }
 
 /// Solution to puzzle_a entry point
@@ -173,29 +328,13 @@ fn parse_input(input: &Vec<String>) -> Vec<(Operation, i32)> {
 /// let vec1: Vec<String> = day10::get_example_input();
 /// assert_eq!(day10::puzzle_a(&vec1), 13140);
 /// ```
-pub fn puzzle_a(input: &Vec<String>) -> i32 {
-    let mut s = State::new();
-    let parsed = parse_input(input);
-    let cycle_offset = 40;
-    let cycle_v = 20;
-    let mut cycle_i = 0;
-    let mut signal_strength = 0;
-    for (op, value) in parsed {
-        //println!("{} {}", op, value);
-        if s.will_pass_cycle_mod(op, cycle_v + cycle_i * cycle_offset) {
-            signal_strength += s.get_signal_strength(1);
-            cycle_i += 1;
-        }
-        s.do_op(op, value);
-        if s.should_get_signal_strengh(cycle_v + cycle_i * cycle_offset) {
-            signal_strength += s.get_signal_strength(0);
-            cycle_i += 1;
-        }
-        if s.clock >= 220 {
-            break;
-        }
-    }
-    return signal_strength;
+pub fn puzzle_a(input: &Vec<String>) -> i64 {
+    let program = parse_input(input);
+    return Cpu::new(&program, vec![1])
+        .take_while(|(cycle, _)| *cycle <= 220)
+        .filter(|(cycle, _)| *cycle % 40 == 20)
+        .map(|(cycle, registers)| cycle as i64 * registers[X])
+        .sum();
 }
 
 /// Solution to puzzle_b entry point
@@ -205,10 +344,105 @@ pub fn puzzle_a(input: &Vec<String>) -> i32 {
 /// assert_eq!(day10::puzzle_b(&vec1), expected);
 /// ```
 pub fn puzzle_b(input: &Vec<String>) -> String {
-    let mut s = State::new();
-    let parsed = parse_input(input);
-    for (op, value) in parsed {
-        s.do_op(op, value);
+    let program = parse_input(input);
+    let screen = draw_screen(Cpu::new(&program, vec![1]), &ScreenConfig::default());
+    return render_screen(&screen);
+}
+
+/// Puzzle b's screen read as letters, for when the answer needs to be typed in.
+/// ```
+/// let vec1: Vec<String> = day10::get_example_input();
+/// // The example draws a test pattern rather than letters.
+/// assert!(matches!(day10::read_screen(&vec1), Err(day10::OcrError::UnknownGlyphs { .. })));
+/// ```
+pub fn read_screen(input: &Vec<String>) -> Result<String, OcrError> {
+    let program = parse_input(input);
+    let screen = draw_screen(Cpu::new(&program, vec![1]), &ScreenConfig::default());
+    return ocrlib::read_text(&screen, &ocrlib::Font::small());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        return text.iter().map(|s| s.to_string()).collect();
+    }
+
+    #[test]
+    fn test_small_program_trace() {
+        let program = assemble(
+            &lines(&["noop", "addx 3", "addx -5"]),
+            &InstructionSet::standard(),
+        )
+        .unwrap();
+        let xs: Vec<(usize, i64)> = Cpu::new(&program, vec![1])
+            .map(|(c, r)| (c, r[X]))
+            .collect();
+        assert_eq!(xs, vec![(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)]);
+        assert_eq!(Cpu::new(&program, vec![1]).run(), vec![-1]);
+    }
+
+    #[test]
+    fn test_custom_instructions() {
+        let mut set = InstructionSet::standard();
+        set.register("sety", 1, 0, |regs, args| regs[1] = args[0]);
+        set.register("swap", 0, 1, |regs, _| regs.swap(X, 1));
+        set.register("noop", 0, 3, |_, _| ());
+        let program = assemble(&lines(&["sety 7", "swap", "noop", "sety 2"]), &set).unwrap();
+        let trace: Vec<(usize, Vec<i64>)> = Cpu::new(&program, vec![1, 0]).collect();
+        assert_eq!(trace.len(), 4);
+        assert_eq!(trace[0], (1, vec![1, 7]));
+        assert_eq!(trace[3], (4, vec![7, 1]));
+        assert_eq!(Cpu::new(&program, vec![1, 0]).run(), vec![7, 2]);
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        let set = InstructionSet::standard();
+        assert_eq!(
+            assemble(&lines(&["noop", "jmp 2"]), &set).unwrap_err(),
+            AssembleError::UnknownInstruction {
+                line: 2,
+                name: "jmp".to_string()
+            }
+        );
+        assert_eq!(
+            assemble(&lines(&["addx"]), &set).unwrap_err().to_string(),
+            "line 1: addx takes 1 operands, not 0"
+        );
+        assert_eq!(
+            assemble(&lines(&["addx x"]), &set).unwrap_err(),
+            AssembleError::BadOperand {
+                line: 1,
+                text: "x".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_screen_sizes() {
+        let program = assemble(&get_example_input(), &InstructionSet::standard()).unwrap();
+        let xs: Vec<i64> = Cpu::new(&program, vec![1]).map(|(_, r)| r[X]).collect();
+        for (width, height, sprite_radius) in [(20, 2, 2), (7, 30, 0), (40, 10, 1), (1, 1, 5)] {
+            let config = ScreenConfig {
+                width: width,
+                height: height,
+                sprite_radius: sprite_radius,
+            };
+            let screen = draw_screen(Cpu::new(&program, vec![1]), &config);
+            for coordinate in screen.coord_iter() {
+                let i = coordinate.y * width + coordinate.x;
+                let lit =
+                    i < xs.len() && (coordinate.x as i64 - xs[i]).abs() <= sprite_radius as i64;
+                assert_eq!(
+                    screen.get_value(coordinate),
+                    Some(lit),
+                    "{} in {:?}",
+                    coordinate,
+                    config
+                );
+            }
+        }
     }
-    return s.printscreen();
 }
@@ -1,6 +1,7 @@
 use day10::load_no_blanks;
 use day10::puzzle_a;
 use day10::puzzle_b;
+use day10::read_screen;
 
 fn main() {
     let filename = "input";
@@ -9,6 +10,12 @@ fn main() {
     let value = puzzle_a(&template);
     println!("Answer to 1st question: {}", value);
 
-    let value_b = puzzle_b(&template);
-    println!("Answer to 2nd question: {}", value_b);
+    match read_screen(&template) {
+        Ok(letters) => println!("Answer to 2nd question: {}", letters),
+        Err(e) => {
+            let value_b = puzzle_b(&template);
+            println!("Answer to 2nd question: {}", value_b);
+            println!("Couldn't read the screen: {}", e);
+        }
+    }
 }
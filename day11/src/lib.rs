@@ -1,102 +1,722 @@
 extern crate filelib;
+extern crate mathlib;
 
 pub use filelib::load_no_blanks;
-use std::cell::RefCell;
-use std::rc::Rc;
+use num_bigint::BigUint;
+use num_traits::{CheckedSub, ToPrimitive, Zero};
+use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Clone)]
-enum OperationOperand {
-    Constant(u64),
-    Old,
+/// Why the monkey notes couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonkeyError {
+    /// A line that isn't part of any monkey's notes, counting from 1.
+    BadLine {
+        line: usize,
+        text: String,
+    },
+    /// Monkeys have to be listed as 0, 1, 2 and so on.
+    OutOfOrder {
+        expected: usize,
+        found: usize,
+    },
+    MissingField {
+        monkey: usize,
+        field: &'static str,
+    },
+    /// An operation that can't be understood.
+    BadExpression {
+        monkey: usize,
+        reason: String,
+    },
+    /// Throwing to a monkey that isn't there.
+    UnknownTarget {
+        monkey: usize,
+        target: usize,
+    },
+    /// A test that divides by zero.
+    ZeroDivisor {
+        monkey: usize,
+    },
 }
 
-#[derive(Debug, Clone)]
-enum OperationOp {
-    Multiply,
+impl Display for MonkeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            MonkeyError::BadLine { line, text } => {
+                write!(f, "line {}: can't understand '{}'", line, text)
+            }
+            MonkeyError::OutOfOrder { expected, found } => {
+                write!(f, "expected monkey {}, found monkey {}", expected, found)
+            }
+            MonkeyError::MissingField { monkey, field } => {
+                write!(f, "monkey {} has no {}", monkey, field)
+            }
+            MonkeyError::BadExpression { monkey, reason } => {
+                write!(f, "monkey {}: {}", monkey, reason)
+            }
+            MonkeyError::UnknownTarget { monkey, target } => {
+                write!(
+                    f,
+                    "monkey {} throws to monkey {}, who isn't there",
+                    monkey, target
+                )
+            }
+            MonkeyError::ZeroDivisor { monkey } => {
+                write!(f, "monkey {} tests for divisibility by 0", monkey)
+            }
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
     Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+impl BinaryOp {
+    // How tightly it binds, and whether it groups to the right.
+    fn precedence(&self) -> (u8, bool) {
+        return match self {
+            BinaryOp::Add | BinaryOp::Subtract => (1, false),
+            BinaryOp::Multiply | BinaryOp::Divide => (2, false),
+            BinaryOp::Power => (3, true),
+        };
+    }
+
+    fn from_char(c: char) -> Option<BinaryOp> {
+        return match c {
+            '+' => Some(BinaryOp::Add),
+            '-' => Some(BinaryOp::Subtract),
+            '*' => Some(BinaryOp::Multiply),
+            '/' => Some(BinaryOp::Divide),
+            '^' => Some(BinaryOp::Power),
+            _ => None,
+        };
+    }
+
+    /// None if the result doesn't fit, goes below zero, or divides by zero.
+    pub fn apply(&self, left: u64, right: u64) -> Option<u64> {
+        return match self {
+            BinaryOp::Add => left.checked_add(right),
+            BinaryOp::Subtract => left.checked_sub(right),
+            BinaryOp::Multiply => left.checked_mul(right),
+            BinaryOp::Divide => left.checked_div(right),
+            BinaryOp::Power => left.checked_pow(u32::try_from(right).ok()?),
+        };
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Power => "^",
+        };
+        return write!(f, "{}", symbol);
+    }
+}
+
+/// How a monkey changes the worry level of an item it inspects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    Old,
+    Constant(u64),
+    Binary(BinaryOp, Box<Expression>, Box<Expression>),
+}
+
+impl Expression {
+    /// Parse the right hand side of `new = ...`, with the usual precedence and parentheses.
+    /// ```
+    /// let e = day11::Expression::parse("(old - 1) * 2 + old ^ 2").unwrap();
+    /// assert_eq!(e.evaluate(5), Some(33));
+    /// assert_eq!(e.to_string(), "(((old - 1) * 2) + (old ^ 2))");
+    /// ```
+    pub fn parse(text: &str) -> Result<Expression, String> {
+        let mut parser = ExpressionParser {
+            chars: text.chars().collect(),
+            position: 0,
+        };
+        let expression = parser.expression(0)?;
+        parser.skip_spaces();
+        if let Some(c) = parser.peek() {
+            return Err(format!("unexpected '{}' at {}", c, parser.position));
+        }
+        return Ok(expression);
+    }
+
+    /// The new worry level, or None if it doesn't fit in a u64, goes below zero or divides by zero.
+    pub fn evaluate(&self, old: u64) -> Option<u64> {
+        return self.evaluate_with(&old, &|v| v, &|op: BinaryOp, l, r| op.apply(l, r));
+    }
+
+    /// Whether the value depends on `old` at all.
+    pub fn uses_old(&self) -> bool {
+        return match self {
+            Expression::Old => true,
+            Expression::Constant(_) => false,
+            Expression::Binary(_, left, right) => left.uses_old() || right.uses_old(),
+        };
+    }
+
+    /// Work the expression out in some other kind of number, given how to make a
+    /// constant and how to apply each operator.
+    pub fn evaluate_with<W, C, A>(&self, old: &W, constant: &C, apply: &A) -> Option<W>
+    where
+        W: Clone,
+        C: Fn(u64) -> W,
+        A: Fn(BinaryOp, W, W) -> Option<W>,
+    {
+        return match self {
+            Expression::Old => Some(old.clone()),
+            Expression::Constant(v) => Some(constant(*v)),
+            Expression::Binary(op, left, right) => {
+                let l = left.evaluate_with(old, constant, apply)?;
+                let r = right.evaluate_with(old, constant, apply)?;
+                apply(*op, l, r)
+            }
+        };
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            Expression::Old => write!(f, "old"),
+            Expression::Constant(v) => write!(f, "{}", v),
+            Expression::Binary(op, left, right) => write!(f, "({} {} {})", left, op, right),
+        };
+    }
+}
+
+// Precedence climbing over the characters of an expression.
+struct ExpressionParser {
+    chars: Vec<char>,
+    position: usize,
 }
 
-struct Monkey {
-    item_worries: Vec<u64>,
-    operation_left: OperationOperand,
-    operation_right: OperationOperand,
-    operation_op: OperationOp,
-    test_divisible_by: u64,
-    true_target: Option<Rc<RefCell<Monkey>>>,
-    false_target: Option<Rc<RefCell<Monkey>>>,
-    num_inspected: usize,
-    max_product: u64,
-}
-
-impl Monkey {
-    fn new(
-        item_worries: Vec<u64>,
-        operation_left: OperationOperand,
-        operation_right: OperationOperand,
-        test_divisible_by: u64,
-        operation_op: OperationOp,
-    ) -> Monkey {
-        return Monkey {
-            item_worries: item_worries,
-            operation_left: operation_left,
-            operation_right: operation_right,
-            operation_op: operation_op,
-            test_divisible_by: test_divisible_by,
-            true_target: None,
-            false_target: None,
-            num_inspected: 0,
-            max_product: 0,
-        };
-    }
-
-    fn compute_worries(&mut self, divide_by_three: bool) {
-        for index in 0..self.item_worries.len() {
-            let old = self.item_worries[index];
-            let left = match self.operation_left {
-                OperationOperand::Constant(v) => v,
-                OperationOperand::Old => old,
+impl ExpressionParser {
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(' ') {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        return self.chars.get(self.position).copied();
+    }
+
+    fn expression(&mut self, min_precedence: u8) -> Result<Expression, String> {
+        let mut left = self.atom()?;
+        loop {
+            self.skip_spaces();
+            let op = match self.peek().and_then(BinaryOp::from_char) {
+                Some(op) => op,
+                None => return Ok(left),
             };
-            let right = match self.operation_right {
-                OperationOperand::Constant(v) => v,
-                OperationOperand::Old => old,
+            let (precedence, right_assoc) = op.precedence();
+            if precedence < min_precedence {
+                return Ok(left);
+            }
+            self.position += 1;
+            let next_min = if right_assoc {
+                precedence
+            } else {
+                precedence + 1
             };
-            let inspect_value = match self.operation_op {
-                OperationOp::Multiply => left * right,
-                OperationOp::Add => left + right,
+            let right = self.expression(next_min)?;
+            left = Expression::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expression, String> {
+        self.skip_spaces();
+        let start = self.position;
+        return match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let inner = self.expression(0)?;
+                self.skip_spaces();
+                if self.peek() != Some(')') {
+                    return Err(format!("expected ')' at {}", self.position));
+                }
+                self.position += 1;
+                Ok(inner)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.position += 1;
+                }
+                let digits: String = self.chars[start..self.position].iter().collect();
+                match digits.parse::<u64>() {
+                    Ok(v) => Ok(Expression::Constant(v)),
+                    Err(_) => Err(format!("{} is too big", digits)),
+                }
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+                    self.position += 1;
+                }
+                let word: String = self.chars[start..self.position].iter().collect();
+                if word != "old" {
+                    return Err(format!("unknown name '{}'", word));
+                }
+                Ok(Expression::Old)
+            }
+            Some(c) => Err(format!("unexpected '{}' at {}", c, start)),
+            None => Err("expression ends too soon".to_string()),
+        };
+    }
+}
+
+/// How worry levels are stored, and kept from growing out of hand between inspections.
+pub trait WorryPolicy {
+    type Worry: Clone + Display;
+
+    /// The worry level of a starting item.
+    fn start(&self, worry: u64) -> Self::Worry;
+
+    /// The worry level after a monkey inspects an item, relief included.
+    /// None if it can't be worked out.
+    fn inspect(&self, operation: &Expression, old: &Self::Worry) -> Option<Self::Worry>;
+
+    fn is_divisible(&self, worry: &Self::Worry, divisor: u64) -> bool;
+}
+
+/// Relief divides the worry level by a fixed amount, rounding down, like in part a.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DivideBy(pub u64);
+
+impl WorryPolicy for DivideBy {
+    type Worry = u64;
+
+    fn start(&self, worry: u64) -> u64 {
+        return worry;
+    }
+
+    fn inspect(&self, operation: &Expression, old: &u64) -> Option<u64> {
+        return operation.evaluate(*old)?.checked_div(self.0);
+    }
+
+    fn is_divisible(&self, worry: &u64, divisor: u64) -> bool {
+        return worry.is_multiple_of(divisor);
+    }
+}
+
+/// No relief, but worry levels are kept modulo a number every test divides into.
+///
+/// This gives the same throws as exact arithmetic, as long as operations don't divide
+/// or raise to a power worked out from `old`. Exponents have to be known exactly, as
+/// `a ^ (e mod m)` isn't `a ^ e mod m`.
+///
+/// Subtraction always wraps round the modulus, as the true worry level isn't known.
+/// Where exact arithmetic would go below zero and give up, this carries on with the
+/// worry level modulo the modulus instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModuloLcm {
+    modulus: u64,
+}
+
+impl ModuloLcm {
+    /// Panics if the modulus is 0.
+    pub fn new(modulus: u64) -> ModuloLcm {
+        if modulus == 0 {
+            panic!("Can't keep worry levels modulo 0");
+        }
+        return ModuloLcm { modulus: modulus };
+    }
+
+    /// Modulo the lowest common multiple of the troop's tests.
+    pub fn for_troop<W: Clone + Display>(troop: &Troop<W>) -> ModuloLcm {
+        return ModuloLcm::new(troop.test_lcm());
+    }
+
+    pub fn modulus(&self) -> u64 {
+        return self.modulus;
+    }
+
+    fn reduce(&self, expression: &Expression, old: u64) -> Option<u64> {
+        let m = self.modulus as u128;
+        let (op, left, right) = match expression {
+            Expression::Old => return Some(old),
+            Expression::Constant(v) => return Some(v % self.modulus),
+            Expression::Binary(op, left, right) => (op, left, right),
+        };
+        let l = self.reduce(left, old)? as u128;
+        let result = match op {
+            // The exponent isn't reduced, so it can't come from old.
+            BinaryOp::Power if right.uses_old() => return None,
+            BinaryOp::Power => mathlib::mod_pow(l, right.evaluate(0)? as u128, m),
+            // Division doesn't carry over to remainders.
+            BinaryOp::Divide => return None,
+            _ => {
+                let r = self.reduce(right, old)? as u128;
+                match op {
+                    BinaryOp::Add => (l + r) % m,
+                    BinaryOp::Subtract => (l + m - r) % m,
+                    _ => (l * r) % m,
+                }
+            }
+        };
+        return Some(result as u64);
+    }
+}
+
+impl WorryPolicy for ModuloLcm {
+    type Worry = u64;
+
+    fn start(&self, worry: u64) -> u64 {
+        return worry % self.modulus;
+    }
+
+    fn inspect(&self, operation: &Expression, old: &u64) -> Option<u64> {
+        return self.reduce(operation, *old);
+    }
+
+    fn is_divisible(&self, worry: &u64, divisor: u64) -> bool {
+        return worry.is_multiple_of(divisor);
+    }
+}
+
+/// No relief, and worry levels kept exactly however big they get. Slow, but always right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exact;
+
+impl WorryPolicy for Exact {
+    type Worry = BigUint;
+
+    fn start(&self, worry: u64) -> BigUint {
+        return BigUint::from(worry);
+    }
+
+    fn inspect(&self, operation: &Expression, old: &BigUint) -> Option<BigUint> {
+        let apply = |op: BinaryOp, l: BigUint, r: BigUint| -> Option<BigUint> {
+            return match op {
+                BinaryOp::Add => Some(l + r),
+                BinaryOp::Subtract => l.checked_sub(&r),
+                BinaryOp::Multiply => Some(l * r),
+                BinaryOp::Divide if r.is_zero() => None,
+                BinaryOp::Divide => Some(l / r),
+                BinaryOp::Power => Some(l.pow(r.to_u32()?)),
             };
-            let new;
-            if divide_by_three {
-                new = inspect_value / 3;
-            } else {
-                new = inspect_value % self.max_product;
+        };
+        return operation.evaluate_with(old, &BigUint::from, &apply);
+    }
+
+    fn is_divisible(&self, worry: &BigUint, divisor: u64) -> bool {
+        return (worry % divisor).is_zero();
+    }
+}
+
+/// A monkey's notes, holding items with worry levels of type `W`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Monkey<W = u64> {
+    pub items: Vec<W>,
+    pub operation: Expression,
+    pub test_divisible_by: u64,
+    pub if_true: usize,
+    pub if_false: usize,
+}
+
+/// One item thrown from one monkey to another, with its worry level once it lands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transfer<W = u64> {
+    pub from: usize,
+    pub to: usize,
+    pub worry: W,
+}
+
+/// All the monkeys, indexed by their number, with how many items each has inspected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Troop<W = u64> {
+    monkeys: Vec<Monkey<W>>,
+    inspected: Vec<usize>,
+}
+
+/// How many items each monkey had inspected in total after each round.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InspectionReport {
+    after_round: Vec<Vec<usize>>,
+}
+
+impl InspectionReport {
+    pub fn rounds(&self) -> usize {
+        return self.after_round.len();
+    }
+
+    /// Totals after the given round, counting from 1.
+    pub fn after_round(&self, round: usize) -> &[usize] {
+        return &self.after_round[round - 1];
+    }
+
+    /// How many items each monkey inspected during just the given round, counting from 1.
+    pub fn in_round(&self, round: usize) -> Vec<usize> {
+        let after = self.after_round(round);
+        if round == 1 {
+            return after.to_vec();
+        }
+        let before = self.after_round(round - 1);
+        return after.iter().zip(before).map(|(a, b)| a - b).collect();
+    }
+}
+
+/// Every round in the same layout as the puzzle's walkthrough.
+impl Display for InspectionReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, counts) in self.after_round.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "== After round {} ==", i + 1)?;
+            for (monkey, count) in counts.iter().enumerate() {
+                writeln!(f, "Monkey {} inspected items {} times.", monkey, count)?;
             }
-            self.item_worries[index] = new;
-            self.num_inspected += 1;
         }
+        return Ok(());
     }
+}
+
+// The notes for one monkey, filled in as the lines are read.
+#[derive(Default)]
+struct MonkeyNotes {
+    items: Option<Vec<u64>>,
+    operation: Option<Expression>,
+    test_divisible_by: Option<u64>,
+    if_true: Option<usize>,
+    if_false: Option<usize>,
+}
 
-    fn give_item(&mut self, worry: u64) {
-        self.item_worries.push(worry);
+impl MonkeyNotes {
+    fn finish(self, monkey: usize) -> Result<Monkey, MonkeyError> {
+        let missing = |field| MonkeyError::MissingField {
+            monkey: monkey,
+            field: field,
+        };
+        return Ok(Monkey {
+            items: self.items.ok_or(missing("starting items"))?,
+            operation: self.operation.ok_or(missing("operation"))?,
+            test_divisible_by: self.test_divisible_by.ok_or(missing("test"))?,
+            if_true: self.if_true.ok_or(missing("true target"))?,
+            if_false: self.if_false.ok_or(missing("false target"))?,
+        });
+    }
+}
+
+impl Troop<u64> {
+    /// Read the notes on every monkey.
+    /// ```
+    /// let troop = day11::Troop::parse(&day11::produce_sample_input()).unwrap();
+    /// assert_eq!(troop.monkeys().len(), 4);
+    /// assert_eq!(troop.monkeys()[2].operation.to_string(), "(old * old)");
+    /// ```
+    pub fn parse(input: &Vec<String>) -> Result<Troop, MonkeyError> {
+        let mut monkeys = vec![];
+        let mut notes: Option<MonkeyNotes> = None;
+        for (i, raw) in input.iter().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let bad_line = || MonkeyError::BadLine {
+                line: i + 1,
+                text: raw.to_string(),
+            };
+            if let Some(id) = line.strip_prefix("Monkey ") {
+                let found = id
+                    .trim_end_matches(':')
+                    .parse::<usize>()
+                    .map_err(|_| bad_line())?;
+                if let Some(done) = notes.take() {
+                    monkeys.push(done.finish(monkeys.len())?);
+                }
+                if found != monkeys.len() {
+                    return Err(MonkeyError::OutOfOrder {
+                        expected: monkeys.len(),
+                        found: found,
+                    });
+                }
+                notes = Some(MonkeyNotes::default());
+                continue;
+            }
+            let current = notes.as_mut().ok_or_else(bad_line)?;
+            let (field, value) = line.split_once(':').ok_or_else(bad_line)?;
+            let value = value.trim();
+            let number = |text: &str| text.parse::<u64>().map_err(|_| bad_line());
+            let last_number = || number(value.rsplit(' ').next().unwrap_or(""));
+            match field {
+                "Starting items" => {
+                    let items = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(number)
+                        .collect::<Result<Vec<u64>, MonkeyError>>()?;
+                    current.items = Some(items);
+                }
+                "Operation" => {
+                    let rhs = value.strip_prefix("new =").ok_or_else(bad_line)?;
+                    let expression =
+                        Expression::parse(rhs).map_err(|reason| MonkeyError::BadExpression {
+                            monkey: monkeys.len(),
+                            reason: reason,
+                        })?;
+                    current.operation = Some(expression);
+                }
+                "Test" => current.test_divisible_by = Some(last_number()?),
+                "If true" => current.if_true = Some(last_number()? as usize),
+                "If false" => current.if_false = Some(last_number()? as usize),
+                _ => return Err(bad_line()),
+            }
+        }
+        if let Some(done) = notes.take() {
+            monkeys.push(done.finish(monkeys.len())?);
+        }
+        for (i, monkey) in monkeys.iter().enumerate() {
+            if monkey.test_divisible_by == 0 {
+                return Err(MonkeyError::ZeroDivisor { monkey: i });
+            }
+            for target in [monkey.if_true, monkey.if_false] {
+                if target >= monkeys.len() {
+                    return Err(MonkeyError::UnknownTarget {
+                        monkey: i,
+                        target: target,
+                    });
+                }
+            }
+        }
+        let inspected = vec![0; monkeys.len()];
+        return Ok(Troop {
+            monkeys: monkeys,
+            inspected: inspected,
+        });
     }
 
-    fn test_items(&mut self) {
-        let true_target = match &self.true_target {
-            Some(v) => v.clone(),
-            None => panic!("Failed to set items"),
+    /// Switch to keeping worry levels the way `policy` does, from the starting items.
+    pub fn with_policy<P: WorryPolicy>(&self, policy: &P) -> Troop<P::Worry> {
+        let monkeys = self
+            .monkeys
+            .iter()
+            .map(|m| Monkey {
+                items: m.items.iter().map(|w| policy.start(*w)).collect(),
+                operation: m.operation.clone(),
+                test_divisible_by: m.test_divisible_by,
+                if_true: m.if_true,
+                if_false: m.if_false,
+            })
+            .collect();
+        return Troop {
+            monkeys: monkeys,
+            inspected: self.inspected.clone(),
         };
-        let false_target = match &self.false_target {
-            Some(v) => v.clone(),
-            None => panic!("Failed to set items"),
+    }
+}
+
+impl<W: Clone + Display> Troop<W> {
+    pub fn monkeys(&self) -> &[Monkey<W>] {
+        return &self.monkeys;
+    }
+
+    /// How many items each monkey has inspected so far.
+    pub fn inspected(&self) -> &[usize] {
+        return &self.inspected;
+    }
+
+    /// Lowest common multiple of every test divisor.
+    ///
+    /// Taking worry levels modulo this keeps every test giving the same answer.
+    /// Panics if it doesn't fit in a u64, rather than wrapping to a wrong modulus.
+    pub fn test_lcm(&self) -> u64 {
+        let divisors = self.monkeys.iter().map(|m| m.test_divisible_by);
+        return match mathlib::checked_lcm_all(divisors) {
+            Some(lcm) => lcm,
+            None => panic!("Can't fit the lowest common multiple of the tests in a u64"),
         };
+    }
 
-        for item in &self.item_worries {
-            if item % self.test_divisible_by == 0 {
-                true_target.borrow_mut().give_item(*item);
+    /// One monkey inspects and throws everything it is holding.
+    ///
+    /// Panics if the policy can't work out an operation.
+    pub fn turn<P: WorryPolicy<Worry = W>>(
+        &mut self,
+        monkey: usize,
+        policy: &P,
+    ) -> Vec<Transfer<W>> {
+        let items = std::mem::take(&mut self.monkeys[monkey].items);
+        self.inspected[monkey] += items.len();
+        let mut transfers = vec![];
+        for old in items {
+            let thrower = &self.monkeys[monkey];
+            let worry = match policy.inspect(&thrower.operation, &old) {
+                Some(v) => v,
+                None => panic!(
+                    "Can't work out {} for monkey {} with old = {}",
+                    thrower.operation, monkey, old
+                ),
+            };
+            let to = if policy.is_divisible(&worry, thrower.test_divisible_by) {
+                thrower.if_true
             } else {
-                false_target.borrow_mut().give_item(*item);
-            }
+                thrower.if_false
+            };
+            self.monkeys[to].items.push(worry.clone());
+            transfers.push(Transfer {
+                from: monkey,
+                to: to,
+                worry: worry,
+            });
         }
-        self.item_worries = vec![];
+        return transfers;
+    }
+
+    /// Every monkey takes a turn, in order. Returns every throw, in the order they happened.
+    /// ```
+    /// let mut troop = day11::Troop::parse(&day11::produce_sample_input()).unwrap();
+    /// let transfers = troop.round(&day11::DivideBy(3));
+    /// assert_eq!(transfers[0], day11::Transfer { from: 0, to: 3, worry: 500 });
+    /// assert_eq!(troop.monkeys()[0].items, vec![20, 23, 27, 26]);
+    /// ```
+    pub fn round<P: WorryPolicy<Worry = W>>(&mut self, policy: &P) -> Vec<Transfer<W>> {
+        let mut transfers = vec![];
+        for monkey in 0..self.monkeys.len() {
+            transfers.extend(self.turn(monkey, policy));
+        }
+        return transfers;
+    }
+
+    /// Play some rounds, noting the inspection counts after each one.
+    /// ```
+    /// let troop = day11::Troop::parse(&day11::produce_sample_input()).unwrap();
+    /// let policy = day11::ModuloLcm::for_troop(&troop);
+    /// let report = troop.with_policy(&policy).run(&policy, 20);
+    /// assert_eq!(report.after_round(20), [99, 97, 8, 103]);
+    /// assert_eq!(report.in_round(1), [2, 4, 3, 6]);
+    /// ```
+    pub fn run<P: WorryPolicy<Worry = W>>(
+        &mut self,
+        policy: &P,
+        rounds: usize,
+    ) -> InspectionReport {
+        let mut after_round = vec![];
+        for _ in 0..rounds {
+            self.round(policy);
+            after_round.push(self.inspected.clone());
+        }
+        return InspectionReport {
+            after_round: after_round,
+        };
+    }
+
+    /// The two biggest inspection counts multiplied together.
+    pub fn monkey_business(&self) -> usize {
+        let mut inspected = self.inspected.clone();
+        inspected.sort_unstable_by(|a, b| b.cmp(a));
+        return inspected.iter().take(2).product();
     }
 }
 
@@ -132,85 +752,11 @@ pub fn produce_sample_input() -> Vec<String> {
     .collect();
 }
 
-fn parse_input(input: &Vec<String>) -> Vec<Rc<RefCell<Monkey>>> {
-    let mut monkeys: Vec<Rc<RefCell<Monkey>>> = vec![];
-    let mut monkey_trues: Vec<usize> = vec![];
-    let mut monkey_falses: Vec<usize> = vec![];
-    let mut monkey_tests_product: u64 = 1;
-
-    // Create all the monkeys first, then handle the true and falses
-    let mut temp_worries = vec![];
-    let mut temp_op_left = OperationOperand::Old;
-    let mut temp_op_right = OperationOperand::Old;
-    let mut temp_op = OperationOp::Multiply;
-    let mut temp_test = 1;
-
-    for line in input {
-        if line.starts_with("Monkey") && !line.starts_with("Monkey 0") {
-            // This monkey is done
-            monkeys.push(Rc::new(RefCell::new(Monkey::new(
-                temp_worries.clone(),
-                temp_op_left.clone(),
-                temp_op_right.clone(),
-                temp_test.clone(),
-                temp_op.clone(),
-            ))));
-            monkey_tests_product *= temp_test;
-        } else if line.trim().starts_with("Starting") {
-            let (_, all_worries) = line.split_once(": ").unwrap();
-            temp_worries = all_worries
-                .split(", ")
-                .map(|s| s.parse::<u64>().unwrap())
-                .collect();
-        } else if line.trim().starts_with("Operation") {
-            let (_, equation) = line.split_once("new = ").unwrap();
-            let equation_parts: Vec<&str> = equation.split(" ").collect();
-            if equation_parts[0] == "old" {
-                temp_op_left = OperationOperand::Old;
-            } else {
-                temp_op_left =
-                    OperationOperand::Constant(equation_parts[0].parse::<u64>().unwrap());
-            }
-            if equation_parts[1] == "*" {
-                temp_op = OperationOp::Multiply;
-            } else {
-                temp_op = OperationOp::Add;
-            }
-            if equation_parts[2] == "old" {
-                temp_op_right = OperationOperand::Old;
-            } else {
-                temp_op_right =
-                    OperationOperand::Constant(equation_parts[2].parse::<u64>().unwrap());
-            }
-        } else if line.trim().starts_with("Test") {
-            let (_, v) = line.split_once("by ").unwrap();
-            temp_test = v.parse::<u64>().unwrap();
-        } else if line.trim().starts_with("If true") {
-            let (_, v) = line.split_once("monkey ").unwrap();
-            monkey_trues.push(v.parse::<usize>().unwrap());
-        } else if line.trim().starts_with("If false") {
-            let (_, v) = line.split_once("monkey ").unwrap();
-            monkey_falses.push(v.parse::<usize>().unwrap());
-        }
-    }
-    // Should have one monkey unfinished
-    monkeys.push(Rc::new(RefCell::new(Monkey::new(
-        temp_worries.clone(),
-        temp_op_left.clone(),
-        temp_op_right.clone(),
-        temp_test.clone(),
-        temp_op.clone(),
-    ))));
-    monkey_tests_product *= temp_test;
-
-    // Now assign targets
-    for i in 0..monkeys.len() {
-        monkeys[i].borrow_mut().true_target = Some(monkeys[monkey_trues[i]].clone());
-        monkeys[i].borrow_mut().false_target = Some(monkeys[monkey_falses[i]].clone());
-        monkeys[i].borrow_mut().max_product = monkey_tests_product;
-    }
-
-    return monkeys;
+fn parse_input(input: &Vec<String>) -> Troop {
+    return match Troop::parse(input) {
+        Ok(troop) => troop,
+        Err(e) => panic!("Can't read the monkey notes: {}", e),
+    };
 }
 
 /// Solution to puzzle_a entry point
@@ -220,17 +766,10 @@ fn parse_input(input: &Vec<String>) -> Vec<Rc<RefCell<Monkey>>> {
 /// ```
 pub fn puzzle_a(input: &Vec<String>) -> usize {
     let rounds = 20;
-    let monkeys: Vec<Rc<RefCell<Monkey>>> = parse_input(input);
-    for _ in 0..rounds {
-        for index in 0..monkeys.len() {
-            let monkey = monkeys[index].clone();
-            monkey.borrow_mut().compute_worries(true);
-            monkey.borrow_mut().test_items();
-        }
-    }
-    let mut inspected: Vec<usize> = monkeys.iter().map(|m| m.borrow().num_inspected).collect();
-    inspected.sort();
-    return inspected.pop().unwrap() * inspected.pop().unwrap();
+    let policy = DivideBy(3);
+    let mut troop = parse_input(input).with_policy(&policy);
+    troop.run(&policy, rounds);
+    return troop.monkey_business();
 }
 
 /// Solution to puzzle_b entry point
@@ -240,15 +779,194 @@ pub fn puzzle_a(input: &Vec<String>) -> usize {
 /// ```
 pub fn puzzle_b(input: &Vec<String>) -> usize {
     let rounds = 10000;
-    let monkeys: Vec<Rc<RefCell<Monkey>>> = parse_input(input);
-    for _ in 0..rounds {
-        for index in 0..monkeys.len() {
-            let monkey = monkeys[index].clone();
-            monkey.borrow_mut().compute_worries(false);
-            monkey.borrow_mut().test_items();
+    let troop = parse_input(input);
+    let policy = ModuloLcm::for_troop(&troop);
+    let mut troop = troop.with_policy(&policy);
+    troop.run(&policy, rounds);
+    return troop.monkey_business();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_lines(lines: &[&str]) -> Vec<String> {
+        return lines.iter().map(|s| s.to_string()).collect();
+    }
+
+    #[test]
+    fn test_expressions() {
+        let cases = [
+            ("old * 19", 2, Some(38)),
+            ("old*old", 7, Some(49)),
+            ("old ^ 2", 7, Some(49)),
+            ("2 ^ 3 ^ 2", 0, Some(512)),
+            ("old - 3 - 2", 10, Some(5)),
+            ("old / 2 * 3", 9, Some(12)),
+            ("1 + 2 * 3", 0, Some(7)),
+            ("(1 + 2) * 3", 0, Some(9)),
+            ("old - 5", 4, None),
+            ("old / (old - 4)", 4, None),
+            ("old * old", u64::MAX, None),
+        ];
+        for (text, old, expected) in cases {
+            assert_eq!(
+                Expression::parse(text).unwrap().evaluate(old),
+                expected,
+                "{}",
+                text
+            );
+        }
+        for bad in [
+            "",
+            "old +",
+            "(old",
+            "old old",
+            "new * 2",
+            "99999999999999999999",
+            "old % 2",
+        ] {
+            assert!(Expression::parse(bad).is_err(), "{}", bad);
         }
     }
-    let mut inspected: Vec<usize> = monkeys.iter().map(|m| m.borrow().num_inspected).collect();
-    inspected.sort();
-    return inspected.pop().unwrap() * inspected.pop().unwrap();
+
+    #[test]
+    fn test_round_transfers_match_puzzle() {
+        let mut troop = Troop::parse(&produce_sample_input()).unwrap();
+        let transfers = troop.round(&DivideBy(3));
+        let from_monkey_2: Vec<(usize, u64)> = transfers
+            .iter()
+            .filter(|t| t.from == 2)
+            .map(|t| (t.to, t.worry))
+            .collect();
+        assert_eq!(from_monkey_2, vec![(1, 2080), (3, 1200), (3, 3136)]);
+        assert_eq!(transfers.len(), 2 + 4 + 3 + 5);
+        assert_eq!(
+            troop.monkeys()[1].items,
+            vec![2080, 25, 167, 207, 401, 1046]
+        );
+        assert!(troop.monkeys()[2].items.is_empty());
+        assert_eq!(troop.inspected(), [2, 4, 3, 5]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let sample = produce_sample_input();
+        let mut out_of_order = sample.clone();
+        out_of_order[6] = "Monkey 2:".to_string();
+        assert_eq!(
+            Troop::parse(&out_of_order).unwrap_err(),
+            MonkeyError::OutOfOrder {
+                expected: 1,
+                found: 2
+            }
+        );
+        let mut bad_target = sample.clone();
+        bad_target[4] = "    If true: throw to monkey 7".to_string();
+        assert_eq!(
+            Troop::parse(&bad_target).unwrap_err(),
+            MonkeyError::UnknownTarget {
+                monkey: 0,
+                target: 7
+            }
+        );
+        let missing = to_lines(&["Monkey 0:", "  Starting items: 1", "  Operation: new = old"]);
+        assert_eq!(
+            Troop::parse(&missing).unwrap_err().to_string(),
+            "monkey 0 has no test"
+        );
+        let mut bad_operation = sample.clone();
+        bad_operation[2] = "  Operation: new = old * (2".to_string();
+        assert!(matches!(
+            Troop::parse(&bad_operation).unwrap_err(),
+            MonkeyError::BadExpression { monkey: 0, .. }
+        ));
+        assert_eq!(
+            Troop::parse(&to_lines(&["  Test: divisible by 3"])).unwrap_err(),
+            MonkeyError::BadLine {
+                line: 1,
+                text: "  Test: divisible by 3".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_modulo_power_of_old() {
+        let modulo = ModuloLcm::new(96577);
+        let two_to_old = Expression::parse("2 ^ old").unwrap();
+        // 2 ^ 96578 is not 2 ^ 1 modulo 96577, so this can't be reduced.
+        assert_eq!(modulo.inspect(&two_to_old, &1), None);
+        assert!(Exact
+            .inspect(&two_to_old, &BigUint::from(96578u64))
+            .is_some());
+        // A constant exponent bigger than the modulus is kept whole.
+        let big_power = Expression::parse("old ^ (96577 + 2)").unwrap();
+        let exact = Exact.inspect(&big_power, &BigUint::from(5u64)).unwrap();
+        assert_eq!(
+            BigUint::from(modulo.inspect(&big_power, &5).unwrap()),
+            exact % 96577u64
+        );
+    }
+
+    #[test]
+    fn test_modulo_matches_exact() {
+        let mut input = produce_sample_input();
+        // Subtraction that stays above zero and constant powers, even past the modulus,
+        // are fine to take modulo too.
+        input[8] = "  Operation: new = (old + 6) * 2 - old ^ 1 + 3 ^ 96577".to_string();
+        let troop = Troop::parse(&input).unwrap();
+        let modulo = ModuloLcm::for_troop(&troop);
+        let mut small = troop.with_policy(&modulo);
+        let mut exact = troop.with_policy(&Exact);
+        for _ in 0..12 {
+            let small_transfers = small.round(&modulo);
+            let exact_transfers = exact.round(&Exact);
+            assert_eq!(small_transfers.len(), exact_transfers.len());
+            for (s, e) in small_transfers.iter().zip(&exact_transfers) {
+                assert_eq!((s.from, s.to), (e.from, e.to));
+                assert_eq!(BigUint::from(s.worry), &e.worry % modulo.modulus());
+            }
+        }
+        assert_eq!(small.inspected(), exact.inspected());
+    }
+
+    #[test]
+    fn test_report() {
+        let troop = Troop::parse(&produce_sample_input()).unwrap();
+        let policy = ModuloLcm::new(troop.test_lcm() * 7);
+        let report = troop.with_policy(&policy).run(&policy, 1000);
+        assert_eq!(report.rounds(), 1000);
+        assert_eq!(report.after_round(1000), [5204, 4792, 199, 5192]);
+        let text = report.to_string();
+        assert!(text.starts_with("== After round 1 ==\nMonkey 0 inspected items 2 times.\n"));
+        assert!(text.contains("\n\n== After round 2 ==\n"));
+        let per_round: usize = (1..=1000).map(|r| report.in_round(r)[3]).sum();
+        assert_eq!(per_round, 5192);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't work out (old - 80)")]
+    fn test_modulo_wraps_below_zero() {
+        let mut input = produce_sample_input();
+        // Monkey 0 starts with 79 and 98.
+        input[2] = "  Operation: new = old - 80".to_string();
+        let troop = Troop::parse(&input).unwrap();
+        let modulo = ModuloLcm::for_troop(&troop);
+        let mut small = troop.with_policy(&modulo);
+        let transfers = small.turn(0, &modulo);
+        assert_eq!(transfers[0].worry, modulo.modulus() - 1);
+        assert_eq!(transfers[1].worry, 18);
+        // Exact arithmetic can't go below zero.
+        troop.with_policy(&Exact).turn(0, &Exact);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't work out (old / 2)")]
+    fn test_modulo_refuses_division() {
+        let mut input = produce_sample_input();
+        input[2] = "  Operation: new = old / 2".to_string();
+        let troop = Troop::parse(&input).unwrap();
+        let policy = ModuloLcm::for_troop(&troop);
+        troop.with_policy(&policy).round(&policy);
+    }
 }
@@ -1,11 +1,38 @@
 use day11::load_no_blanks;
 use day11::puzzle_a;
 use day11::puzzle_b;
+use day11::{DivideBy, Exact, ModuloLcm, Troop, WorryPolicy};
+use std::env;
+
+// Play the rounds with the given policy and print how many items each monkey inspected.
+fn report<P: WorryPolicy>(troop: &Troop, policy: &P, rounds: usize) {
+    let mut troop = troop.with_policy(policy);
+    print!("{}", troop.run(policy, rounds));
+    println!("Monkey business: {}", troop.monkey_business());
+}
 
 fn main() {
     let filename = "input";
     let template = load_no_blanks(filename);
 
+    // --report <divide|lcm|exact> [rounds] prints the inspection counts after every round.
+    let args: Vec<String> = env::args().collect();
+    if let Some(i) = args.iter().position(|a| a == "--report") {
+        let troop = match Troop::parse(&template) {
+            Ok(troop) => troop,
+            Err(e) => panic!("Can't read the monkey notes: {}", e),
+        };
+        let rounds = args.get(i + 2).map_or(20, |n| {
+            n.parse::<usize>().expect("Rounds should be a number")
+        });
+        match args.get(i + 1).map(|s| s.as_str()) {
+            Some("lcm") => report(&troop, &ModuloLcm::for_troop(&troop), rounds),
+            Some("exact") => report(&troop, &Exact, rounds),
+            _ => report(&troop, &DivideBy(3), rounds),
+        }
+        return;
+    }
+
     let value = puzzle_a(&template);
     println!("Answer to 1st question: {}", value);
 
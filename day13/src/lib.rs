@@ -2,10 +2,9 @@ extern crate filelib;
 
 pub use filelib::load;
 pub use filelib::split_lines_by_blanks;
-use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
-use std::rc::Rc;
+use std::str::FromStr;
 
 pub fn example_input() -> Vec<Vec<String>> {
     return vec![
@@ -41,172 +40,405 @@ pub fn example_input() -> Vec<Vec<String>> {
     ];
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-enum Tokens {
-    Value(i32),
-    List(Vec<Rc<RefCell<Tokens>>>),
+/// A packet, either an integer or a list of packets.
+///
+/// Packets are ordered the way the distress signal wants, where an integer compared
+/// to a list acts like a list of just that integer. Equality follows the same rule,
+/// so `3` and `[[3]]` are equal.
+#[derive(Clone, Debug)]
+pub enum Packet {
+    Integer(u32),
+    List(Vec<Packet>),
 }
 
-impl Ord for Tokens {
+impl Packet {
+    // The packet as a list, without allocating.
+    fn as_slice(&self) -> &[Packet] {
+        return match self {
+            Packet::Integer(_) => std::slice::from_ref(self),
+            Packet::List(items) => items,
+        };
+    }
+}
+
+impl Ord for Packet {
     fn cmp(&self, other: &Self) -> Ordering {
-        match compare_pair(
-            Rc::new(RefCell::new(self.clone())),
-            Rc::new(RefCell::new(other.clone())),
-        ) {
-            CompareBool::True => Ordering::Less,
-            CompareBool::False => Ordering::Greater,
-            _ => Ordering::Equal,
-        }
+        return match (self, other) {
+            (Packet::Integer(a), Packet::Integer(b)) => a.cmp(b),
+            _ => self.as_slice().cmp(other.as_slice()),
+        };
     }
 }
 
-impl PartialOrd for Tokens {
+impl PartialOrd for Packet {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         return Some(self.cmp(other));
     }
 }
 
-fn parse_packets(packets: &Vec<Vec<String>>) -> Vec<(Tokens, Tokens)> {
-    let mut result = vec![];
-    for packet_pair in packets {
-        let mut p1: Tokens = Tokens::Value(99999);
-        let mut p2: Tokens = Tokens::Value(99999);
-        let mut first = true;
-        for packet_line in packet_pair {
-            let mut token_stack = vec![];
-            let mut cur_string = "".to_string();
-            let mut cur_token: Tokens = Tokens::List(vec![]);
-            let mut first_char = true;
-            for c in packet_line.chars() {
-                if first_char {
-                    first_char = false;
-                    continue;
-                }
-                if c == '[' {
-                    token_stack.push(cur_token);
-                    cur_token = Tokens::List(vec![]);
-                } else if c == ']' {
-                    if cur_string.len() > 0 {
-                        //println!("Parsing 1 '{}'", cur_string);
-                        let v = cur_string.parse::<i32>().unwrap();
-                        cur_string = "".to_string();
-                        match cur_token {
-                            Tokens::Value(_) => panic!("Shouldn't happen"),
-                            Tokens::List(ref mut l) => {
-                                l.push(Rc::new(RefCell::new(Tokens::Value(v))))
-                            }
-                        };
-                    }
-                    if token_stack.len() > 0 {
-                        let mut v = token_stack.pop().unwrap();
-                        match v {
-                            Tokens::Value(_) => panic!("Shouldn't happen"),
-                            Tokens::List(ref mut l) => l.push(Rc::new(RefCell::new(cur_token))),
-                        };
-                        cur_token = v;
-                    }
-                } else if c == ',' {
-                    if cur_string.len() > 0 {
-                        //println!("Parsing 2 '{}'", cur_string);
-                        let v = cur_string.parse::<i32>().unwrap();
-                        cur_string = "".to_string();
-                        match cur_token {
-                            Tokens::Value(_) => panic!("Shouldn't happen"),
-                            Tokens::List(ref mut l) => {
-                                l.push(Rc::new(RefCell::new(Tokens::Value(v))))
-                            }
-                        };
+impl PartialEq for Packet {
+    fn eq(&self, other: &Self) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl Eq for Packet {}
+
+impl Display for Packet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            Packet::Integer(v) => write!(f, "{}", v),
+            Packet::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
                     }
-                } else {
-                    cur_string.push(c);
+                    write!(f, "{}", item)?;
                 }
+                write!(f, "]")
             }
-            if first {
-                p1 = cur_token;
-                first = false;
-            } else {
-                p2 = cur_token;
-            }
-        }
-        result.push((p1, p2));
+        };
     }
-    return result;
 }
 
-#[derive(Debug)]
-enum CompareBool {
-    True,
-    False,
-    KeepGoing,
+/// Why a packet couldn't be read, with the character position it went wrong at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PacketError {
+    /// The line ended in the middle of a packet.
+    UnexpectedEnd,
+    /// A character that can't go here.
+    UnexpectedChar {
+        position: usize,
+        found: char,
+        expected: &'static str,
+    },
+    /// More characters after a whole packet.
+    TrailingInput { position: usize },
+    /// An integer too big to store.
+    IntegerTooLarge { position: usize },
 }
 
-impl Display for CompareBool {
+impl Display for PacketError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let s: &str = match self {
-            CompareBool::True => "True",
-            CompareBool::False => "False",
-            CompareBool::KeepGoing => "KeepGoing",
+        return match self {
+            PacketError::UnexpectedEnd => write!(f, "packet ended early"),
+            PacketError::UnexpectedChar {
+                position,
+                found,
+                expected,
+            } => write!(
+                f,
+                "unexpected '{}' at position {}, expected {}",
+                found, position, expected
+            ),
+            PacketError::TrailingInput { position } => {
+                write!(f, "extra input after the packet at position {}", position)
+            }
+            PacketError::IntegerTooLarge { position } => {
+                write!(f, "integer at position {} is too large", position)
+            }
         };
-        return write!(f, "{}", s);
     }
 }
 
-fn compare_pair(packet1: Rc<RefCell<Tokens>>, packet2: Rc<RefCell<Tokens>>) -> CompareBool {
-    return match &*packet1.borrow() {
-        Tokens::Value(i1) => match &*packet2.borrow() {
-            Tokens::Value(i2) => compare_int(*i1, *i2),
-            Tokens::List(v2) => {
-                let v1 = vec![Rc::new(RefCell::new(Tokens::Value(*i1)))];
-                return compare_lists(v1, v2.clone());
+// Recursive descent over the characters of a single packet.
+struct PacketParser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> PacketParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        return self.text.as_bytes().get(self.position).copied();
+    }
+
+    fn unexpected(&self, expected: &'static str) -> PacketError {
+        // Only ASCII is ever stepped over, so position is always at the start of a character.
+        return match self.text[self.position..].chars().next() {
+            None => PacketError::UnexpectedEnd,
+            Some(c) => PacketError::UnexpectedChar {
+                position: self.position,
+                found: c,
+                expected: expected,
+            },
+        };
+    }
+
+    // packet := integer | '[' (packet (',' packet)*)? ']'
+    fn packet(&mut self) -> Result<Packet, PacketError> {
+        return match self.peek() {
+            Some(b'[') => self.list(),
+            Some(c) if c.is_ascii_digit() => self.integer(),
+            _ => Err(self.unexpected("an integer or '['")),
+        };
+    }
+
+    fn list(&mut self) -> Result<Packet, PacketError> {
+        self.position += 1;
+        let mut items = vec![];
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Packet::List(items));
+        }
+        loop {
+            items.push(self.packet()?);
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(Packet::List(items));
+                }
+                _ => return Err(self.unexpected("',' or ']'")),
             }
-        },
-        Tokens::List(v1) => {
-            return match &*packet2.borrow() {
-                Tokens::Value(i2) => {
-                    let v2 = vec![Rc::new(RefCell::new(Tokens::Value(*i2)))];
-                    return compare_lists(v1.clone(), v2);
+        }
+    }
+
+    fn integer(&mut self) -> Result<Packet, PacketError> {
+        let start = self.position;
+        let mut value: u32 = 0;
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_digit()) {
+            value = value
+                .checked_mul(10)
+                .and_then(|v| v.checked_add((c - b'0') as u32))
+                .ok_or(PacketError::IntegerTooLarge { position: start })?;
+            self.position += 1;
+        }
+        return Ok(Packet::Integer(value));
+    }
+}
+
+impl FromStr for Packet {
+    type Err = PacketError;
+
+    /// ```
+    /// use day13::Packet;
+    /// let packet: Packet = "[1,[2,[]],3]".parse().unwrap();
+    /// assert_eq!(packet.to_string(), "[1,[2,[]],3]");
+    /// let err = "[1,2;3]".parse::<Packet>().unwrap_err();
+    /// assert_eq!(err.to_string(), "unexpected ';' at position 4, expected ',' or ']'");
+    /// let err = "[1,é]".parse::<Packet>().unwrap_err();
+    /// assert_eq!(err.to_string(), "unexpected 'é' at position 3, expected an integer or '['");
+    /// ```
+    fn from_str(s: &str) -> Result<Packet, PacketError> {
+        let mut parser = PacketParser {
+            text: s,
+            position: 0,
+        };
+        let packet = parser.packet()?;
+        if parser.position < s.len() {
+            return Err(PacketError::TrailingInput {
+                position: parser.position,
+            });
+        }
+        return Ok(packet);
+    }
+}
+
+/// Something that happened while comparing two packets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// Comparing these two packets, left then right.
+    Compare(String, String),
+    /// The left integer was turned into a list holding it, to compare with a list.
+    PromoteLeft(String),
+    /// The right integer was turned into a list holding it, to compare with a list.
+    PromoteRight(String),
+    LeftSmaller,
+    RightSmaller,
+    LeftRanOut,
+    RightRanOut,
+}
+
+/// One line of a comparison walkthrough.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    /// How deep in the comparison this is, for indenting.
+    pub depth: usize,
+    /// Index into each list on the way down to the packets being compared.
+    pub path: Vec<usize>,
+    pub event: TraceEvent,
+}
+
+/// How two packets compare, and every step taken to decide it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    /// `Less` if the packets are in the right order.
+    pub order: Ordering,
+    pub steps: Vec<TraceStep>,
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for step in &self.steps {
+            let text = match &step.event {
+                TraceEvent::Compare(left, right) => format!("Compare {} vs {}", left, right),
+                TraceEvent::PromoteLeft(left) => format!(
+                    "Mixed types; convert left to [{}] and retry comparison",
+                    left
+                ),
+                TraceEvent::PromoteRight(right) => format!(
+                    "Mixed types; convert right to [{}] and retry comparison",
+                    right
+                ),
+                TraceEvent::LeftSmaller => {
+                    "Left side is smaller, so inputs are in the right order".to_string()
+                }
+                TraceEvent::RightSmaller => {
+                    "Right side is smaller, so inputs are not in the right order".to_string()
+                }
+                TraceEvent::LeftRanOut => {
+                    "Left side ran out of items, so inputs are in the right order".to_string()
+                }
+                TraceEvent::RightRanOut => {
+                    "Right side ran out of items, so inputs are not in the right order".to_string()
                 }
-                Tokens::List(v2) => compare_lists(v1.clone(), v2.clone()),
             };
+            writeln!(f, "{}- {}", "  ".repeat(step.depth), text)?;
         }
+        return Ok(());
+    }
+}
+
+/// Compare two packets step by step, like the puzzle's walkthrough.
+/// ```
+/// let left: day13::Packet = "[[1],[2,3,4]]".parse().unwrap();
+/// let right: day13::Packet = "[[1],4]".parse().unwrap();
+/// let comparison = day13::explain(&left, &right);
+/// assert_eq!(comparison.order, std::cmp::Ordering::Less);
+/// assert_eq!(comparison.to_string(), "\
+/// - Compare [[1],[2,3,4]] vs [[1],4]
+///   - Compare [1] vs [1]
+///     - Compare 1 vs 1
+///   - Compare [2,3,4] vs 4
+///     - Mixed types; convert right to [4] and retry comparison
+///     - Compare [2,3,4] vs [4]
+///       - Compare 2 vs 4
+///         - Left side is smaller, so inputs are in the right order
+/// ");
+/// ```
+pub fn explain(left: &Packet, right: &Packet) -> Comparison {
+    let mut steps = vec![];
+    let order = explain_into(left, right, 0, &mut vec![], &mut steps);
+    return Comparison {
+        order: order,
+        steps: steps,
     };
 }
 
-fn compare_int(i1: i32, i2: i32) -> CompareBool {
-    //println!("Comparing {}, {}", i1, i2);
-    if i1 < i2 {
-        return CompareBool::True;
-    }
-    if i1 > i2 {
-        return CompareBool::False;
-    }
-    return CompareBool::KeepGoing;
+fn push_step(steps: &mut Vec<TraceStep>, depth: usize, path: &[usize], event: TraceEvent) {
+    steps.push(TraceStep {
+        depth: depth,
+        path: path.to_vec(),
+        event: event,
+    });
 }
 
-fn compare_lists(list1: Vec<Rc<RefCell<Tokens>>>, list2: Vec<Rc<RefCell<Tokens>>>) -> CompareBool {
-    let length_lists: Vec<usize> = vec![list1.len(), list2.len()];
-    let higher_length = *length_lists.iter().max().unwrap();
-    //println!("Comparing Vec of len {}, and len {}", list1.len(), list2.len());
-    for i in 0..higher_length {
-        if i < list2.len() && i < list1.len() {
-            let decision = compare_pair(list1[i].clone(), list2[i].clone());
-            let should_return = match decision {
-                CompareBool::True => true,
-                CompareBool::False => true,
-                CompareBool::KeepGoing => false,
-            };
-            if should_return {
-                return decision;
+fn explain_into(
+    left: &Packet,
+    right: &Packet,
+    depth: usize,
+    path: &mut Vec<usize>,
+    steps: &mut Vec<TraceStep>,
+) -> Ordering {
+    push_step(
+        steps,
+        depth,
+        path,
+        TraceEvent::Compare(left.to_string(), right.to_string()),
+    );
+    let decided = |order: Ordering| {
+        return match order {
+            Ordering::Less => Some(TraceEvent::LeftSmaller),
+            Ordering::Greater => Some(TraceEvent::RightSmaller),
+            Ordering::Equal => None,
+        };
+    };
+    return match (left, right) {
+        (Packet::Integer(a), Packet::Integer(b)) => {
+            let order = a.cmp(b);
+            if let Some(event) = decided(order) {
+                push_step(steps, depth + 1, path, event);
             }
+            order
         }
-        if i >= list2.len() {
-            return CompareBool::False;
+        (Packet::Integer(_), Packet::List(_)) => {
+            push_step(
+                steps,
+                depth + 1,
+                path,
+                TraceEvent::PromoteLeft(left.to_string()),
+            );
+            let promoted = Packet::List(vec![left.clone()]);
+            explain_into(&promoted, right, depth + 1, path, steps)
         }
-        if i >= list1.len() {
-            return CompareBool::True;
+        (Packet::List(_), Packet::Integer(_)) => {
+            push_step(
+                steps,
+                depth + 1,
+                path,
+                TraceEvent::PromoteRight(right.to_string()),
+            );
+            let promoted = Packet::List(vec![right.clone()]);
+            explain_into(left, &promoted, depth + 1, path, steps)
+        }
+        (Packet::List(a), Packet::List(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let event = if i >= a.len() {
+                    TraceEvent::LeftRanOut
+                } else if i >= b.len() {
+                    TraceEvent::RightRanOut
+                } else {
+                    path.push(i);
+                    let order = explain_into(&a[i], &b[i], depth + 1, path, steps);
+                    path.pop();
+                    if order == Ordering::Equal {
+                        continue;
+                    }
+                    return order;
+                };
+                push_step(steps, depth + 1, path, event);
+                return a.len().cmp(&b.len());
+            }
+            Ordering::Equal
         }
+    };
+}
+
+fn parse_packets(packets: &Vec<Vec<String>>) -> Vec<(Packet, Packet)> {
+    let parse = |line: &String| match line.parse::<Packet>() {
+        Ok(p) => p,
+        Err(e) => panic!("Can't read packet {}: {}", line, e),
+    };
+    return packets
+        .iter()
+        .map(|pair| (parse(&pair[0]), parse(&pair[1])))
+        .collect();
+}
+
+/// Walkthroughs for every pair in the input, or just pair `only` (counting from 1).
+/// ```
+/// let vec1 = day13::example_input();
+/// let text = day13::explain_pairs(&vec1, Some(6));
+/// assert_eq!(text, "\
+/// == Pair 6 ==
+/// - Compare [] vs [3]
+///   - Left side ran out of items, so inputs are in the right order
+/// ");
+/// assert_eq!(day13::explain_pairs(&vec1, None).matches("== Pair").count(), 8);
+/// ```
+pub fn explain_pairs(input: &Vec<Vec<String>>, only: Option<usize>) -> String {
+    let mut text = String::new();
+    for (i, (p1, p2)) in parse_packets(input).iter().enumerate() {
+        if only.is_some_and(|n| n != i + 1) {
+            continue;
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text += &format!("== Pair {} ==\n{}", i + 1, explain(p1, p2));
     }
-    return CompareBool::KeepGoing;
+    return text;
 }
 
 /// Solution to puzzle_a entry point
@@ -217,83 +449,34 @@ fn compare_lists(list1: Vec<Rc<RefCell<Tokens>>>, list2: Vec<Rc<RefCell<Tokens>>
 pub fn puzzle_a(input: &Vec<Vec<String>>) -> usize {
     let packet_pairs = parse_packets(input);
     let mut sum = 0;
-    for i in 0..packet_pairs.len() {
-        let (p1, p2) = &packet_pairs[i];
-        let result = compare_pair(
-            Rc::new(RefCell::new(p1.clone())),
-            Rc::new(RefCell::new(p2.clone())),
-        );
-        //println!("i={}, r={}", i, result);
-        match result {
-            CompareBool::True => sum += i + 1,
-            _ => (),
+    for (i, (p1, p2)) in packet_pairs.iter().enumerate() {
+        if p1 < p2 {
+            sum += i + 1;
         }
     }
     return sum;
 }
 
-#[derive(Debug, Eq, PartialEq, PartialOrd)]
-struct OrderWrapper {
-    token: Tokens,
-}
-
-impl Ord for OrderWrapper {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match compare_pair(
-            Rc::new(RefCell::new(self.token.clone())),
-            Rc::new(RefCell::new(other.token.clone())),
-        ) {
-            CompareBool::True => Ordering::Less,
-            CompareBool::False => Ordering::Greater,
-            _ => Ordering::Equal,
-        }
-    }
-}
-
 /// Solution to puzzle_b entry point
 /// ```
 /// let vec1 = day13::example_input();
 /// assert_eq!(day13::puzzle_b(&vec1), 140);
 /// ```
 pub fn puzzle_b(input: &Vec<Vec<String>>) -> usize {
-    let mut packet_pairs = parse_packets(input);
-    let divider1 = Tokens::List(vec![Rc::new(RefCell::new(Tokens::List(vec![Rc::new(
-        RefCell::new(Tokens::Value(2)),
-    )])))]);
-    let divider2 = Tokens::List(vec![Rc::new(RefCell::new(Tokens::List(vec![Rc::new(
-        RefCell::new(Tokens::Value(6)),
-    )])))]);
-    packet_pairs.push((divider1.clone(), divider2.clone()));
-    let mut just_vec: Vec<OrderWrapper> = packet_pairs
+    let divider1: Packet = "[[2]]".parse().unwrap();
+    let divider2: Packet = "[[6]]".parse().unwrap();
+    let mut packets: Vec<Packet> = parse_packets(input)
         .into_iter()
-        .map(|(p1, p2)| vec![OrderWrapper { token: p1 }, OrderWrapper { token: p2 }])
-        .flatten()
+        .flat_map(|(p1, p2)| [p1, p2])
         .collect();
-    just_vec.sort();
-
-    let mut divider_indexes = 1;
+    packets.push(divider1.clone());
+    packets.push(divider2.clone());
+    packets.sort();
 
-    divider_indexes *= just_vec
-        .iter()
-        .position(|o| {
-            *o.clone()
-                == OrderWrapper {
-                    token: divider1.clone(),
-                }
-        })
-        .unwrap()
-        + 1;
-    divider_indexes *= just_vec
-        .iter()
-        .position(|o| {
-            *o.clone()
-                == OrderWrapper {
-                    token: divider2.clone(),
-                }
-        })
-        .unwrap()
-        + 1;
-    return divider_indexes;
+    // Packets equal to a divider could land either side of it, so count from the first.
+    let divider1_index = packets.partition_point(|p| *p < divider1) + 1;
+    let divider2_index = packets.partition_point(|p| *p < divider2) + 1;
+    return divider1_index * divider2_index;
 }
 
 #[cfg(test)]
@@ -306,21 +489,111 @@ mod tests {
             .iter()
             .map(|s| s.to_string())
             .collect()];
-        let inner_one = Tokens::Value(1);
-        let inner_two = Tokens::Value(2);
-        let inner_three = Tokens::Value(3);
-        let inner_four = Tokens::Value(4);
-        let one_rc = Rc::new(RefCell::new(inner_one));
-        let two_rc = Rc::new(RefCell::new(inner_two));
-        let three_rc = Rc::new(RefCell::new(inner_three));
-        let four_rc = Rc::new(RefCell::new(inner_four));
-
-        let top_one = Tokens::List(vec![one_rc.clone()]);
-        let top_one_rc = Rc::new(RefCell::new(top_one));
-        let top_two = Tokens::List(vec![two_rc.clone(), three_rc.clone(), four_rc.clone()]);
-        let top = Tokens::List(vec![top_one_rc.clone(), Rc::new(RefCell::new(top_two))]);
-        let bottom = Tokens::List(vec![top_one_rc.clone(), four_rc.clone()]);
-        let expected = vec![(top, bottom)];
-        assert_eq!(parse_packets(&input), expected);
+        let one = Packet::List(vec![Packet::Integer(1)]);
+        let top = Packet::List(vec![
+            one.clone(),
+            Packet::List(vec![
+                Packet::Integer(2),
+                Packet::Integer(3),
+                Packet::Integer(4),
+            ]),
+        ]);
+        let bottom = Packet::List(vec![one, Packet::Integer(4)]);
+        let parsed = parse_packets(&input);
+        // Packet equality promotes integers, so compare the exact structure instead.
+        assert_eq!(
+            format!("{:?}", parsed),
+            format!("{:?}", vec![(top, bottom)])
+        );
+        assert_eq!(parsed[0].0.to_string(), input[0][0]);
+        assert_eq!(parsed[0].1.to_string(), input[0][1]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for pair in example_input() {
+            for line in pair {
+                assert_eq!(line.parse::<Packet>().unwrap().to_string(), line);
+            }
+        }
+        assert_eq!("1234".parse::<Packet>().unwrap().to_string(), "1234");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |s: &str| s.parse::<Packet>().unwrap_err();
+        assert_eq!(err(""), PacketError::UnexpectedEnd);
+        assert_eq!(err("[1,2"), PacketError::UnexpectedEnd);
+        assert_eq!(
+            err("[1,]"),
+            PacketError::UnexpectedChar {
+                position: 3,
+                found: ']',
+                expected: "an integer or '['"
+            }
+        );
+        assert_eq!(err("[1]]"), PacketError::TrailingInput { position: 3 });
+        assert_eq!(
+            err("[1,99999999999]"),
+            PacketError::IntegerTooLarge { position: 3 }
+        );
+        assert_eq!(
+            err("[-1]").to_string(),
+            "unexpected '-' at position 1, expected an integer or '['"
+        );
+    }
+
+    #[test]
+    fn test_ordering() {
+        let p = |s: &str| s.parse::<Packet>().unwrap();
+        assert!(p("[1,1,3,1,1]") < p("[1,1,5,1,1]"));
+        assert!(p("[[1],[2,3,4]]") < p("[[1],4]"));
+        assert!(p("[9]") > p("[[8,7,6]]"));
+        assert!(p("[]") < p("[3]"));
+        assert!(p("[[[]]]") > p("[[]]"));
+        // An integer acts like a list of just itself, however deep.
+        assert_eq!(p("3"), p("[[[3]]]"));
+        assert!(p("3") < p("[3,0]"));
+    }
+
+    #[test]
+    fn test_explain_matches_ordering() {
+        let packets: Vec<Packet> = example_input()
+            .iter()
+            .flatten()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        for left in &packets {
+            for right in &packets {
+                assert_eq!(explain(left, right).order, left.cmp(right));
+            }
+        }
+    }
+
+    #[test]
+    fn test_explain_ran_out() {
+        let p = |s: &str| s.parse::<Packet>().unwrap();
+        let comparison = explain(&p("[7,[7,7,7]]"), &p("[7,[7,7]]"));
+        assert_eq!(comparison.order, Ordering::Greater);
+        let last = comparison.steps.last().unwrap();
+        assert_eq!(last.event, TraceEvent::RightRanOut);
+        assert_eq!(last.depth, 2);
+        assert_eq!(last.path, vec![1]);
+
+        let comparison = explain(&p("3"), &p("[[3]]"));
+        assert_eq!(comparison.order, Ordering::Equal);
+        assert_eq!(
+            comparison.to_string(),
+            "\
+- Compare 3 vs [[3]]
+  - Mixed types; convert left to [3] and retry comparison
+  - Compare [3] vs [[3]]
+    - Compare 3 vs [3]
+      - Mixed types; convert left to [3] and retry comparison
+      - Compare [3] vs [3]
+        - Compare 3 vs 3
+"
+        );
+        assert_eq!(comparison.steps[6].path, vec![0, 0]);
     }
 }
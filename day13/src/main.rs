@@ -1,13 +1,23 @@
+use day13::explain_pairs;
 use day13::load;
 use day13::puzzle_a;
 use day13::puzzle_b;
 use day13::split_lines_by_blanks;
+use std::env;
 
 fn main() {
     let filename = "input";
     let template = load(filename);
     let groups = split_lines_by_blanks(&template);
 
+    // --explain [pair] prints how each pair (or just the one) was compared.
+    let args: Vec<String> = env::args().collect();
+    if let Some(i) = args.iter().position(|a| a == "--explain") {
+        let only = args.get(i + 1).and_then(|n| n.parse::<usize>().ok());
+        print!("{}", explain_pairs(&groups, only));
+        return;
+    }
+
     let value = puzzle_a(&groups);
     println!("Answer to 1st question: {}", value);
 
@@ -1,6 +1,7 @@
 extern crate filelib;
 
 pub use filelib::load_no_blanks;
+use rangelib::{Interval, RangeSet};
 
 fn parse_input(input: &Vec<String>) -> Vec<(i32, i32, i32, i32)> {
     return input.iter().map(|line| parse_line(line)).collect();
@@ -50,18 +51,19 @@ fn get_manhatten_distance(px: i32, py: i32, sx: i32, sy: i32) -> usize {
     return v.try_into().unwrap();
 }
 
-fn higher(a: i32, b: i32) -> i32 {
-    if a > b {
-        return a;
-    }
-    return b;
-}
-
-fn lower(a: i32, b: i32) -> i32 {
-    if a > b {
-        return b;
-    }
-    return a;
+/// Every x on the row within range of at least one sensor.
+fn row_coverage(sensors_beacons: &[(i32, i32, i32, i32)], row: i32) -> RangeSet<i32> {
+    return sensors_beacons
+        .iter()
+        .filter_map(|(sx, sy, bx, by)| {
+            // How far along the row this sensor reaches
+            let reach = (bx - sx).abs() + (by - sy).abs() - (row - sy).abs();
+            if reach < 0 {
+                return None;
+            }
+            return Some(Interval::new(sx - reach, sx + reach));
+        })
+        .collect();
 }
 
 /// Solution to puzzle_a entry point
@@ -71,53 +73,16 @@ fn lower(a: i32, b: i32) -> i32 {
 /// ```
 pub fn puzzle_a(input: &Vec<String>, answer_row: i32) -> usize {
     let sensors_beacons = parse_input(input);
-    // first get all the distances
-    let distances: Vec<usize> = sensors_beacons
+    let coverage = row_coverage(&sensors_beacons, answer_row);
+    // Anything already on the row doesn't count.
+    let occupied: RangeSet<i32> = sensors_beacons
         .iter()
-        .map(|(sx, sy, bx, by)| get_manhatten_distance(*bx, *by, *sx, *sy))
+        .flat_map(|(sx, sy, bx, by)| [(*sx, *sy), (*bx, *by)])
+        .filter(|(_, y)| *y == answer_row)
+        .map(|(x, _)| Interval::new(x, x))
         .collect();
-    let max_distance_u: usize = *distances.iter().max().unwrap();
-    let max_distance: i32 = max_distance_u.try_into().unwrap();
-    // get the furthest left x, and furthest right x, to figure out the "width" of the map
-    // then adjust by the max distances, in case some are near an edge
-    let map_neg_x = sensors_beacons
-        .iter()
-        .map(|(sx, _, bx, _)| lower(*sx, *bx))
-        .min()
-        .unwrap()
-        - max_distance;
-    let map_pos_x = sensors_beacons
-        .iter()
-        .map(|(sx, _, bx, _)| higher(*sx, *bx))
-        .max()
-        .unwrap()
-        + max_distance;
-    let mut impossible_xs = vec![];
-    for i in map_neg_x..=map_pos_x {
-        //println!("{}", i);
-        let matching: Vec<bool> = sensors_beacons
-            .iter()
-            .map(|(sx, sy, bx, by)| {
-                (*sy == answer_row && *sx == i) || (*by == answer_row && *bx == i)
-            })
-            .filter(|b| *b)
-            .collect();
-        if matching.len() >= 1 {
-            continue;
-        }
-        // get the distance to this point from every sensor
-        let cur_distances: Vec<usize> = sensors_beacons
-            .iter()
-            .map(|(sx, sy, _, _)| get_manhatten_distance(i, answer_row, *sx, *sy))
-            .collect();
-        for j in 0..cur_distances.len() {
-            if cur_distances[j] <= distances[j] {
-                impossible_xs.push(i);
-                break;
-            }
-        }
-    }
-    return impossible_xs.len();
+    let impossible_xs = coverage.subtract(&occupied);
+    return impossible_xs.len().try_into().unwrap();
 }
 
 /// Solution to puzzle_b entry point
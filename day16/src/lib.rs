@@ -1,7 +1,9 @@
 extern crate filelib;
 
 pub use filelib::load_no_blanks;
+use graphlib::WeightedGraph;
 use rustc_hash::FxHashMap;
+use std::fmt::{Display, Formatter};
 
 pub fn sample_input() -> Vec<String> {
     return vec![
@@ -38,58 +40,213 @@ fn parse_input(lines: &Vec<String>) -> Vec<(&str, usize, Vec<&str>)> {
     return lines.iter().map(|s| parse_valve(s)).collect();
 }
 
-fn solve(valves: &mut Vec<(&str, usize, Vec<&str>)>, max_time: usize, start: &str) -> usize {
-    valves.sort_by(|a, b| b.1.cmp(&a.1));
-    let label_indexes = valves
-        .iter()
-        .enumerate()
-        .map(|(i, v)| (v.0, i))
-        .collect::<FxHashMap<_, _>>();
-    let num_positive_flow_rate = valves.iter().filter(|v| v.1 > 0).count();
-    let num_valves = valves.len();
-
-    // adjacency map
-    let mut adj = vec![vec![0usize; 0]; num_valves];
-    let mut flow = vec![0usize; num_valves];
-    for valve in valves.iter() {
-        let i = label_indexes[valve.0];
-        flow[i] = valve.1;
-        for w in valve.2.iter() {
-            adj[i].push(label_indexes[w]);
+/// How to run the valves: who is opening them, for how long, and where they start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValveConfig {
+    /// Number of agents opening valves at the same time.
+    pub agents: usize,
+    /// Time until the volcano erupts.
+    pub minutes: usize,
+    /// Time spent before anyone can start moving, such as teaching an elephant.
+    pub training_minutes: usize,
+    pub start: String,
+}
+
+/// A valve being opened as part of a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValveOpening {
+    pub valve: String,
+    /// Minutes since the start, including training, when the valve has just been opened.
+    pub minute: usize,
+    /// Pressure this valve releases from then until the eruption.
+    pub pressure: usize,
+}
+
+/// The best way to open the valves, with the order each agent opens them in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValvePlan {
+    pub pressure: usize,
+    pub schedules: Vec<Vec<ValveOpening>>,
+}
+
+impl Display for ValvePlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (agent, schedule) in self.schedules.iter().enumerate() {
+            let openings: Vec<String> = schedule
+                .iter()
+                .map(|o| format!("{} at minute {} (+{})", o.valve, o.minute, o.pressure))
+                .collect();
+            writeln!(f, "Agent {}: {}", agent + 1, openings.join(", "))?;
         }
+        return write!(f, "Total pressure released: {}", self.pressure);
     }
-    let start_index = label_indexes[start];
-    let positive_bitset = 1 << num_positive_flow_rate;
-
-    // dynamic programming, via 3 dimensional array [time left, current node, bitset of available valves]
-    let mut opt = vec![vec![vec![0; max_time]; num_valves]; positive_bitset];
-    // For every time
-    for time in 1..max_time {
-        // For every possible valve
-        for valve_index in 0..num_valves {
-            let cur_bitmask = 1 << valve_index;
-            // In every possible state
-            for possible_bitmask in 0..positive_bitset {
-                // 0 if we haven't been here before.
-                let mut o = opt[possible_bitmask][valve_index][time];
-                // if the current state for this bitmask is off and we have the time, try turning this on.
-                if cur_bitmask & possible_bitmask != 0 && time >= 1 {
-                    o = o.max(
-                        opt[possible_bitmask - cur_bitmask][valve_index][time - 1]
-                            + flow[valve_index] * time as usize,
-                    );
-                }
-                // Try also instead going to any other valve via the edges.
-                for &j in adj[valve_index].iter() {
-                    o = o.max(opt[possible_bitmask][j][time - 1]);
-                }
-                // The highest value is stored for dynamic programming.
-                opt[possible_bitmask][valve_index][time] = o;
+}
+
+// (agents still to go after this one, position, time left, valves opened)
+type State = (usize, usize, usize, u64);
+
+// The valves worth opening, plus the start, with the shortest walk between each pair.
+struct ValveNetwork {
+    names: Vec<String>,
+    flows: Vec<usize>,
+    // usize::MAX if there is no way between them.
+    distances: Vec<Vec<usize>>,
+    start: usize,
+}
+
+impl ValveNetwork {
+    fn new(valves: &[(&str, usize, Vec<&str>)], start: &str) -> ValveNetwork {
+        let mut graph = WeightedGraph::new();
+        let mut flow_rates: FxHashMap<&str, usize> = FxHashMap::default();
+        for (name, flow, _) in valves {
+            graph.add_node(*name);
+            flow_rates.insert(name, *flow);
+        }
+        assert!(
+            flow_rates.contains_key(start),
+            "No valve called {} to start at",
+            start
+        );
+        for (name, _, tunnels) in valves {
+            for tunnel in tunnels {
+                graph.add_edge(*name, *tunnel, 1);
             }
         }
+        let compressed = graph.compress(|name| *name == start || flow_rates[name] > 0);
+        assert!(
+            compressed.len() <= 64,
+            "Can only keep track of 64 valves worth opening"
+        );
+        let distances = compressed
+            .distance_table()
+            .iter()
+            .map(|row| row.iter().map(|d| d.unwrap_or(usize::MAX)).collect())
+            .collect();
+        return ValveNetwork {
+            names: compressed.nodes().iter().map(|n| n.to_string()).collect(),
+            flows: compressed.nodes().iter().map(|n| flow_rates[n]).collect(),
+            distances: distances,
+            start: compressed.index_of(&start).unwrap(),
+        };
     }
 
-    return opt[positive_bitset - 1][start_index][max_time - 1];
+    // Most pressure released by `agents` agents, each with `minutes` minutes, one after another.
+    // Also gives the valves each agent opens, with the time left when they do.
+    fn plan(&self, agents: usize, minutes: usize) -> (usize, Vec<Vec<(usize, usize)>>) {
+        if agents == 0 {
+            return (0, vec![]);
+        }
+        let mut memo = FxHashMap::default();
+        let mut state = (agents - 1, self.start, minutes, 0);
+        let best = self.explore(state, minutes, &mut memo);
+
+        // Follow the moves that keep the best result reachable.
+        let mut schedules = vec![vec![]];
+        let mut left = best;
+        while left > 0 {
+            let (released, next, opened) = self
+                .moves(state, minutes)
+                .find(|(released, next, _)| {
+                    *released + self.explore(*next, minutes, &mut memo) == left
+                })
+                .unwrap();
+            match opened {
+                Some(valve) => schedules.last_mut().unwrap().push((valve, next.2)),
+                None => schedules.push(vec![]),
+            }
+            left -= released;
+            state = next;
+        }
+        schedules.resize(agents, vec![]);
+        return (best, schedules);
+    }
+
+    // Every move from a state, as (pressure released, next state, valve opened).
+    // The current agent can open a valve, or stop and let the next agent start,
+    // with stopping last so plans keep the earlier agents busy.
+    fn moves(
+        &self,
+        state: State,
+        minutes: usize,
+    ) -> impl Iterator<Item = (usize, State, Option<usize>)> + '_ {
+        let (agents_after, pos, time_left, opened) = state;
+        let hand_over = if agents_after > 0 {
+            Some((0, (agents_after - 1, self.start, minutes, opened), None))
+        } else {
+            None
+        };
+        let open = (0..self.flows.len()).filter_map(move |next| {
+            let bit = 1 << next;
+            if self.flows[next] == 0 || opened & bit != 0 {
+                return None;
+            }
+            // Walk there and take a minute to open it.
+            let cost = self.distances[pos][next].saturating_add(1);
+            if cost >= time_left {
+                return None;
+            }
+            let remaining = time_left - cost;
+            let released = self.flows[next] * remaining;
+            return Some((
+                released,
+                (agents_after, next, remaining, opened | bit),
+                Some(next),
+            ));
+        });
+        return open.chain(hand_over);
+    }
+
+    // Best pressure that can still be released from this state.
+    fn explore(&self, state: State, minutes: usize, memo: &mut FxHashMap<State, usize>) -> usize {
+        if let Some(known) = memo.get(&state) {
+            return *known;
+        }
+        let mut best = 0;
+        for (released, next, _) in self.moves(state, minutes) {
+            best = best.max(released + self.explore(next, minutes, memo));
+        }
+        memo.insert(state, best);
+        return best;
+    }
+}
+
+/// Find the most pressure that can be released, and who opens which valve when.
+/// ```
+/// let config = day16::ValveConfig {
+///     agents: 1,
+///     minutes: 30,
+///     training_minutes: 0,
+///     start: "AA".to_string(),
+/// };
+/// let plan = day16::solve_valves(&day16::sample_input(), &config);
+/// assert_eq!(plan.pressure, 1651);
+/// let order: Vec<&str> = plan.schedules[0].iter().map(|o| o.valve.as_str()).collect();
+/// assert_eq!(order, vec!["DD", "BB", "JJ", "HH", "EE", "CC"]);
+/// assert_eq!(plan.schedules[0][0].minute, 2);
+/// assert_eq!(plan.schedules[0][0].pressure, 20 * 28);
+/// ```
+pub fn solve_valves(input: &Vec<String>, config: &ValveConfig) -> ValvePlan {
+    let valves = parse_input(input);
+    let network = ValveNetwork::new(&valves, &config.start);
+    let minutes = config.minutes.saturating_sub(config.training_minutes);
+    let (pressure, schedules) = network.plan(config.agents, minutes);
+    let schedules = schedules
+        .iter()
+        .map(|schedule| {
+            schedule
+                .iter()
+                .map(|(valve, time_left)| ValveOpening {
+                    valve: network.names[*valve].clone(),
+                    minute: config.minutes - time_left,
+                    pressure: network.flows[*valve] * time_left,
+                })
+                .collect()
+        })
+        .collect();
+    return ValvePlan {
+        pressure: pressure,
+        schedules: schedules,
+    };
 }
 
 /// Solution to puzzle_a entry point
@@ -98,92 +255,160 @@ fn solve(valves: &mut Vec<(&str, usize, Vec<&str>)>, max_time: usize, start: &st
 /// assert_eq!(day16::puzzle_a(&vec1), 1651);
 /// ```
 pub fn puzzle_a(input: &Vec<String>) -> usize {
-    let max_time = 30;
-    let start = "AA";
-    let mut valves = parse_input(input);
-    return solve(&mut valves, max_time, start);
+    let config = ValveConfig {
+        agents: 1,
+        minutes: 30,
+        training_minutes: 0,
+        start: "AA".to_string(),
+    };
+    return solve_valves(input, &config).pressure;
 }
 
-fn solve_elephant(
-    valves: &mut Vec<(&str, usize, Vec<&str>)>,
-    max_time: usize,
-    start: &str,
-) -> usize {
-    // refer to solve for most of the logic
-    let elephant_time = 4;
-    // The max time with the elephant
-    let effective_max_time = max_time - elephant_time;
-
-    valves.sort_by(|a, b| b.1.cmp(&a.1));
-    let label_indexes = valves
-        .iter()
-        .enumerate()
-        .map(|(i, v)| (v.0, i))
-        .collect::<FxHashMap<_, _>>();
-    let num_positive_flow_rate = valves.iter().filter(|v| v.1 > 0).count();
-    let num_valves = valves.len();
-
-    // adjacency map
-    let mut adj = vec![vec![0usize; 0]; num_valves];
-    let mut flow = vec![0usize; num_valves];
-    for valve in valves.iter() {
-        let i = label_indexes[valve.0];
-        flow[i] = valve.1;
-        for w in valve.2.iter() {
-            adj[i].push(label_indexes[w]);
+/// Solution to puzzle_b entry point
+/// ```
+/// let vec1: Vec<String> = day16::sample_input();
+/// assert_eq!(day16::puzzle_b(&vec1), 1707);
+/// ```
+pub fn puzzle_b(input: &Vec<String>) -> usize {
+    // You and the elephant, once you've taught it how
+    let config = ValveConfig {
+        agents: 2,
+        minutes: 30,
+        training_minutes: 4,
+        start: "AA".to_string(),
+    };
+    return solve_valves(input, &config).pressure;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compressed_to_useful_valves() {
+        let input = sample_input();
+        let valves = parse_input(&input);
+        let network = ValveNetwork::new(&valves, "AA");
+        // AA, BB, CC, DD, EE, HH, JJ
+        assert_eq!(network.flows, vec![0, 13, 2, 20, 3, 22, 21]);
+        assert_eq!(network.start, 0);
+        // AA -> II -> JJ
+        assert_eq!(network.distances[0][6], 2);
+        // HH -> GG -> FF -> EE -> DD -> AA -> II -> JJ
+        assert_eq!(network.distances[5][6], 7);
+    }
+
+    #[test]
+    fn test_more_agents() {
+        let input = sample_input();
+        let valves = parse_input(&input);
+        let network = ValveNetwork::new(&valves, "AA");
+        assert_eq!(network.plan(0, 26), (0, vec![]));
+        assert_eq!(network.plan(1, 0), (0, vec![vec![]]));
+        // Extra agents never hurt, they can always do nothing.
+        let mut last = 0;
+        for agents in 1..5 {
+            let pressure = network.plan(agents, 10).0;
+            assert!(pressure >= last);
+            last = pressure;
         }
+        // With enough agents every valve is opened as soon as it can be.
+        let best_case: usize = (1..network.flows.len())
+            .map(|v| network.flows[v] * (10 - network.distances[0][v] - 1))
+            .sum();
+        assert_eq!(network.plan(6, 10).0, best_case);
     }
-    let start_index = label_indexes[start];
-    let positive_bitset = 1 << num_positive_flow_rate;
-
-    // dynamic programming, via 3 dimensional array [time left, current node, bitset of available valves]
-    let mut opt = vec![vec![vec![0; effective_max_time]; num_valves]; positive_bitset];
-    for time in 1..effective_max_time {
-        for valve_index in 0..num_valves {
-            let cur_bitmask = 1 << valve_index;
-            for x in 0..positive_bitset {
-                let mut o = opt[x][valve_index][time];
-                if cur_bitmask & x != 0 && time >= 1 {
-                    o = o.max(
-                        opt[x - cur_bitmask][valve_index][time - 1]
-                            + flow[valve_index] * time as usize,
-                    );
-                }
-                for &j in adj[valve_index].iter() {
-                    o = o.max(opt[x][j][time - 1]);
+
+    #[test]
+    fn test_unreachable_valve() {
+        let input: Vec<String> = [
+            "Valve AA has flow rate=0; tunnel leads to valve BB",
+            "Valve BB has flow rate=5; tunnel leads to valve AA",
+            "Valve CC has flow rate=100; tunnel leads to valve DD",
+            "Valve DD has flow rate=0; tunnel leads to valve CC",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(puzzle_a(&input), 5 * 28);
+    }
+
+    #[test]
+    fn test_schedules_are_achievable() {
+        let input = sample_input();
+        let valves = parse_input(&input);
+        let network = ValveNetwork::new(&valves, "AA");
+        for agents in 1..4 {
+            let config = ValveConfig {
+                agents: agents,
+                minutes: 30,
+                training_minutes: 4,
+                start: "AA".to_string(),
+            };
+            let plan = solve_valves(&input, &config);
+            assert_eq!(plan.schedules.len(), agents);
+            let mut total = 0;
+            let mut seen: Vec<String> = vec![];
+            for schedule in &plan.schedules {
+                let mut pos = network.start;
+                let mut minute = config.training_minutes;
+                for opening in schedule {
+                    let valve = network
+                        .names
+                        .iter()
+                        .position(|n| *n == opening.valve)
+                        .unwrap();
+                    assert!(!seen.contains(&opening.valve));
+                    seen.push(opening.valve.clone());
+                    minute += network.distances[pos][valve] + 1;
+                    assert_eq!(opening.minute, minute);
+                    assert_eq!(opening.pressure, network.flows[valve] * (30 - minute));
+                    total += opening.pressure;
+                    pos = valve;
                 }
-                opt[x][valve_index][time] = o;
             }
+            assert_eq!(total, plan.pressure);
         }
     }
 
-    // Now how do we handle the elephant?
-    let mut best = 0;
-    // Try going through all possible states
-    for x in 0..positive_bitset {
-        // And having the elephant represent the inverse possible state
-        // eg the elephant doesn't do anything you can do
-        for y in 0..x {
-            if (x & y) == 0 {
-                // split in half, y is elephant, x is you.
-                best = best.max(
-                    opt[x][start_index][effective_max_time - 1]
-                        + opt[y][start_index][effective_max_time - 1],
-                );
-            }
-        }
+    #[test]
+    fn test_plan_display() {
+        let config = ValveConfig {
+            agents: 2,
+            minutes: 30,
+            training_minutes: 4,
+            start: "AA".to_string(),
+        };
+        let plan = solve_valves(&sample_input(), &config);
+        assert_eq!(plan.pressure, 1707);
+        let text = plan.to_string();
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.starts_with("Agent 1: "));
+        assert!(text.ends_with("Total pressure released: 1707"));
     }
-    return best;
-}
 
-/// Solution to puzzle_b entry point
-/// ```
-/// let vec1: Vec<String> = day16::sample_input();
-/// assert_eq!(day16::puzzle_b(&vec1), 1707);
-/// ```
-pub fn puzzle_b(input: &Vec<String>) -> usize {
-    let max_time = 30;
-    let start = "AA";
-    let mut valves = parse_input(input);
-    return solve_elephant(&mut valves, max_time, start);
+    #[test]
+    fn test_no_time() {
+        let config = ValveConfig {
+            agents: 2,
+            minutes: 3,
+            training_minutes: 5,
+            start: "BB".to_string(),
+        };
+        let plan = solve_valves(&sample_input(), &config);
+        assert_eq!(plan.pressure, 0);
+        assert_eq!(plan.schedules, vec![vec![], vec![]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "No valve called ZZ")]
+    fn test_unknown_start() {
+        let config = ValveConfig {
+            agents: 1,
+            minutes: 30,
+            training_minutes: 0,
+            start: "ZZ".to_string(),
+        };
+        solve_valves(&sample_input(), &config);
+    }
 }
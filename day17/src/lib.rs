@@ -1,5 +1,6 @@
 extern crate filelib;
 
+use cyclelib::{find_cycle_in_iter, Cycle};
 pub use filelib::load_no_blanks;
 use gridlib::GridCoordinateInf64;
 use rustc_hash::FxHashMap;
@@ -141,126 +142,128 @@ fn parse_jetstream(line: &str) -> JetStreamPattern {
     return JetStreamPattern::new(pattern);
 }
 
-fn drop_blocks(p: &mut JetStreamPattern, max: usize) -> usize {
-    let mut rocks = RockPattern::new();
-    // Figure out the starting floor
-    // tallest rock is 4 vertical, so floor can never be more than 4*max
-    // Then we spawn 3 above that
-
-    let tallest = rocks.get_max_height();
-    let floor_y = max * tallest + 3 + 1;
-    let mut cur_spawn;
-    let width = 7;
-    let start_x = 2;
+struct Chamber {
+    rocks: RockPattern,
     // Symbols for state
-    let rock = 1;
-    let floor = 2;
     // for Part b, state needs to be a sparse map instead.
     // 2022 * 7 * 4 + 4 is still well within memory requirements.
-    let mut state: FxHashMap<(usize, usize), usize> = FxHashMap::default();
-    for i in 0..width {
-        state.insert((i, floor_y), floor);
+    state: FxHashMap<(usize, usize), usize>,
+    floor_y: usize,
+    last_floor: usize,
+    width: usize,
+}
+
+impl Chamber {
+    fn new(max: usize) -> Chamber {
+        let rocks = RockPattern::new();
+        // Figure out the starting floor
+        // tallest rock is 4 vertical, so floor can never be more than 4*max
+        // Then we spawn 3 above that
+        let tallest = rocks.get_max_height();
+        let floor_y = max * tallest + 3 + 1;
+        let width = 7;
+        let floor = 2;
+        let mut state = FxHashMap::default();
+        for i in 0..width {
+            state.insert((i, floor_y), floor);
+        }
+        return Chamber {
+            rocks: rocks,
+            state: state,
+            floor_y: floor_y,
+            last_floor: floor_y,
+            width: width,
+        };
     }
-    let mut rock_pos: Vec<ShapeCoord>;
-    let mut jet;
-    let mut potential_spot;
-    let mut start_trans;
-    let fall_trans = ShapeCoord::new(0, 1);
-    let mut last_floor = floor_y;
-
-    // number to actually do before memoization kicks in.
-    let skip_first = 3000;
-    let mut memo: FxHashMap<(Vec<usize>, usize, usize), usize> = FxHashMap::default();
-    let mut height_at_step = vec![];
 
-    for i in 0..max {
-        let cur_rock = rocks.next().unwrap();
+    fn height(&self) -> usize {
+        return self.floor_y - self.last_floor;
+    }
+
+    // Enough to tell two chambers apart, once the bottom has filled in.
+    fn key(&self, p: &JetStreamPattern) -> (Vec<usize>, usize, usize) {
+        let peaks: Vec<usize> = (0..self.width)
+            .map(|x| *self.state.get(&(x, self.last_floor)).unwrap_or(&0))
+            .collect();
+        return (peaks, p.curr, self.rocks.curr);
+    }
+
+    fn blocked(&self, spot: &[ShapeCoord]) -> bool {
+        return spot.iter().any(|v| {
+            if v.x < 0 {
+                return true;
+            }
+            let y_usize: usize = v.y.try_into().unwrap();
+            let x_usize: usize = v.x.try_into().unwrap();
+            return y_usize >= self.floor_y
+                || x_usize >= self.width
+                || self.state.contains_key(&(x_usize, y_usize));
+        });
+    }
+
+    fn drop_rock(&mut self, p: &mut JetStreamPattern) {
+        let start_x = 2;
+        let rock = 1;
+        let fall_trans = ShapeCoord::new(0, 1);
+
+        let cur_rock = self.rocks.next().unwrap();
         // bottom is 3 above lowest rock / floor.
-        //println!("current_height is {}", rocks.get_current_height());
-        cur_spawn = last_floor - (3 + rocks.get_current_height());
-        start_trans = ShapeCoord::new(start_x, cur_spawn.try_into().unwrap());
-        rock_pos = cur_rock.iter().map(|c| c.clone() + start_trans).collect();
-        //println!("Starting at: {:?}", rock_pos.clone());
+        let cur_spawn = self.last_floor - (3 + self.rocks.get_current_height());
+        let start_trans = ShapeCoord::new(start_x, cur_spawn.try_into().unwrap());
+        let mut rock_pos: Vec<ShapeCoord> = cur_rock.iter().map(|c| *c + start_trans).collect();
         loop {
-            jet = p.next().unwrap();
-            potential_spot = jet.move_pattern(rock_pos.clone());
-            // to check spot:
-            if potential_spot.iter().any(|v| {
-                if v.x < 0 {
-                    return true;
-                }
-                let y_usize: usize = v.y.try_into().unwrap();
-                let x_usize: usize = v.x.try_into().unwrap();
-                return x_usize >= width.try_into().unwrap()
-                    || state.contains_key(&(x_usize, y_usize));
-            }) {
-                // Bad spot, don't move
-            } else {
+            let jet = p.next().unwrap();
+            let mut potential_spot = jet.move_pattern(rock_pos.clone());
+            if !self.blocked(&potential_spot) {
                 // Good spot
                 rock_pos = potential_spot;
             }
             // Next, try falling!
-
-            potential_spot = rock_pos
-                .iter()
-                .map(|coord| coord.clone() + fall_trans)
-                .collect();
-            if potential_spot.iter().any(|v| {
-                if v.x < 0 {
-                    return true;
-                }
-                let y_usize: usize = v.y.try_into().unwrap();
-                let x_usize: usize = v.x.try_into().unwrap();
-                return y_usize >= floor_y
-                    || x_usize >= width.try_into().unwrap()
-                    || state.contains_key(&(x_usize, y_usize));
-            }) {
+            potential_spot = rock_pos.iter().map(|coord| *coord + fall_trans).collect();
+            if self.blocked(&potential_spot) {
                 // Bad spot, we are done!
                 for pos in rock_pos.iter() {
                     let y_usize: usize = pos.y.try_into().unwrap();
                     let x_usize: usize = pos.x.try_into().unwrap();
-                    //println!("Freezing ({},{})", x_usize, y_usize);
-                    state.insert((x_usize, y_usize), rock);
+                    self.state.insert((x_usize, y_usize), rock);
                 }
                 break;
-            } else {
-                // Good spot
-                rock_pos = potential_spot;
             }
+            // Good spot
+            rock_pos = potential_spot;
             // Loop, next jet and next fall!
         }
         // determine new spawn point:
         for pos in rock_pos {
-            last_floor = last_floor.min(pos.y.try_into().unwrap());
-        }
-        height_at_step.push(floor_y - last_floor);
-
-        if i >= skip_first {
-            let peaks: Vec<usize> = (0..width)
-                .map(|x| *state.get(&(x, last_floor)).unwrap_or(&0))
-                .collect();
-            let pattern_n = p.curr;
-            let rock_n = rocks.curr;
-            let key = (peaks, pattern_n, rock_n);
-            if memo.contains_key(&key) {
-                let prev_seen = memo[&key];
-                let highest_then = height_at_step[prev_seen];
-                let height_now = floor_y - last_floor;
-                let height_change = height_now - highest_then;
-                let cycle_size = i - prev_seen;
-                let goal = max - prev_seen;
-                let num_cycles = goal / cycle_size;
-                let left_over = goal % cycle_size;
-                let leftover_height = height_at_step[prev_seen + left_over] - highest_then;
-                // I have an off by one error, that I'm not sure why its there.
-                return highest_then + leftover_height + (num_cycles * height_change) - 1;
-            }
-            memo.insert(key, i);
+            self.last_floor = self.last_floor.min(pos.y.try_into().unwrap());
         }
     }
-    // Simulation done, cur_spawn should be height.
-    //println!("floor: {}, last_floor: {}", floor_y, last_floor);
-    return floor_y - last_floor;
+}
+
+fn drop_blocks(p: &mut JetStreamPattern, max: usize) -> usize {
+    let mut chamber = Chamber::new(max);
+    // height_at_step[i] is the height after i + 1 rocks.
+    let mut height_at_step = vec![];
+
+    // number to actually do before looking for a cycle,
+    // the key only looks at the top row, so it needs the bottom to fill in first.
+    let skip_first = 3000.min(max);
+    for _ in 0..skip_first {
+        chamber.drop_rock(p);
+        height_at_step.push(chamber.height());
+    }
+    let keys = (skip_first..max).map(|_| {
+        chamber.drop_rock(p);
+        height_at_step.push(chamber.height());
+        return chamber.key(p);
+    });
+    if let Some(found) = find_cycle_in_iter(keys) {
+        let cycle = Cycle::new(found.start + skip_first, found.length);
+        let height = cycle.extrapolate(max - 1, |i| height_at_step[i] as i64);
+        return height.try_into().unwrap();
+    }
+    // Simulation done without repeating.
+    return chamber.height();
 }
 
 /// Solution to puzzle_a entry point
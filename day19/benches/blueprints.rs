@@ -0,0 +1,23 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+fn sample_input() -> Vec<String> {
+    return vec![
+        "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.",
+        "Blueprint 2: Each ore robot costs 2 ore. Each clay robot costs 3 ore. Each obsidian robot costs 3 ore and 8 clay. Each geode robot costs 3 ore and 12 obsidian.",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+}
+
+fn bench_blueprints(c: &mut Criterion) {
+    let input = sample_input();
+    let mut group = c.benchmark_group("blueprints");
+    group.sample_size(10);
+    group.bench_function("puzzle_a", |b| b.iter(|| day19::puzzle_a(&input)));
+    group.bench_function("puzzle_b", |b| b.iter(|| day19::puzzle_b(&input)));
+    group.finish();
+}
+
+criterion_group!(benches, bench_blueprints);
+criterion_main!(benches);
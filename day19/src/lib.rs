@@ -1,187 +1,388 @@
 extern crate filelib;
 
 pub use filelib::load_no_blanks;
-use rustc_hash::FxHashMap;
-use rustc_hash::FxHashSet;
-use std::cmp::max;
-use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::thread;
-use std::thread::JoinHandle;
-
-// id, ore_robot_cost, clay_robot_cost, obsidian_ore_cost, obsidian_clay_cost, geode_ore_cost, geode_obsidian_cost
-type BlueprintTuple = (usize, usize, usize, usize, usize, usize, usize);
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
-struct Blueprint {
-    id: usize,
-    ore_robot_cost: usize,
-    clay_robot_cost: usize,
-    obsidian_ore_cost: usize,
-    obsidian_clay_cost: usize,
-    geode_ore_cost: usize,
-    geode_obsidian_cost: usize,
+
+/// Why a blueprint couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlueprintError {
+    /// The line doesn't start with "Blueprint <id>:".
+    BadHeader(String),
+    /// A sentence that isn't "Each <resource> robot costs <n> <resource> and ...".
+    BadClause(String),
+    /// Two sentences for the same robot.
+    DuplicateRobot(String),
+    /// A cost in a resource that no robot collects.
+    UnknownResource(String),
 }
 
-impl Blueprint {
-    fn new(t: BlueprintTuple) -> Blueprint {
-        return Blueprint {
-            id: t.0,
-            ore_robot_cost: t.1,
-            clay_robot_cost: t.2,
-            obsidian_ore_cost: t.3,
-            obsidian_clay_cost: t.4,
-            geode_ore_cost: t.5,
-            geode_obsidian_cost: t.6,
+impl Display for BlueprintError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            BlueprintError::BadHeader(header) => write!(f, "bad blueprint header: {}", header),
+            BlueprintError::BadClause(clause) => write!(f, "bad robot recipe: {}", clause),
+            BlueprintError::DuplicateRobot(name) => write!(f, "{} robot listed twice", name),
+            BlueprintError::UnknownResource(name) => write!(f, "no robot collects {}", name),
         };
     }
 }
 
-//std::mem::size_of is 64
-#[derive(Default, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+/// Robot recipes, with one robot for each resource, which collects that resource.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Blueprint {
+    pub id: usize,
+    /// Names of the resources, in the order their robots were listed.
+    pub resources: Vec<String>,
+    /// `costs[robot][resource]` is how much of a resource it takes to build a robot.
+    pub costs: Vec<Vec<usize>>,
+}
+
+impl Blueprint {
+    /// Read a blueprint, with any number of "Each X robot costs ..." sentences.
+    /// ```
+    /// let line = "Blueprint 7: Each sand robot costs 2 sand. Each glass robot costs 3 sand and 1 heat. Each heat robot costs 4 sand.";
+    /// let blueprint = day19::Blueprint::parse(line).unwrap();
+    /// assert_eq!(blueprint.id, 7);
+    /// assert_eq!(blueprint.resources, vec!["sand", "glass", "heat"]);
+    /// assert_eq!(blueprint.costs[1], vec![3, 0, 1]);
+    /// ```
+    pub fn parse(line: &str) -> Result<Blueprint, BlueprintError> {
+        let (header, recipes) = line
+            .split_once(':')
+            .ok_or_else(|| BlueprintError::BadHeader(line.to_string()))?;
+        let id = header
+            .trim()
+            .strip_prefix("Blueprint ")
+            .and_then(|id| id.parse::<usize>().ok())
+            .ok_or_else(|| BlueprintError::BadHeader(header.to_string()))?;
+
+        let mut resources: Vec<String> = vec![];
+        let mut unparsed_costs: Vec<Vec<(usize, &str)>> = vec![];
+        for clause in recipes
+            .split('.')
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+        {
+            let bad_clause = || BlueprintError::BadClause(clause.to_string());
+            let (robot, costs) = clause
+                .strip_prefix("Each ")
+                .and_then(|c| c.split_once(" robot costs "))
+                .ok_or_else(bad_clause)?;
+            if resources.iter().any(|r| r == robot) {
+                return Err(BlueprintError::DuplicateRobot(robot.to_string()));
+            }
+            let mut robot_costs = vec![];
+            for cost in costs.split(" and ") {
+                let (amount, resource) = cost.split_once(' ').ok_or_else(bad_clause)?;
+                let amount = amount.parse::<usize>().map_err(|_| bad_clause())?;
+                robot_costs.push((amount, resource));
+            }
+            resources.push(robot.to_string());
+            unparsed_costs.push(robot_costs);
+        }
+
+        let mut costs = vec![vec![0; resources.len()]; resources.len()];
+        for (robot, robot_costs) in unparsed_costs.iter().enumerate() {
+            for (amount, resource) in robot_costs {
+                let index = resources
+                    .iter()
+                    .position(|r| r == resource)
+                    .ok_or_else(|| BlueprintError::UnknownResource(resource.to_string()))?;
+                costs[robot][index] += amount;
+            }
+        }
+        return Ok(Blueprint {
+            id: id,
+            resources: resources,
+            costs: costs,
+        });
+    }
+
+    pub fn resource_index(&self, name: &str) -> Option<usize> {
+        return self.resources.iter().position(|r| r == name);
+    }
+}
+
+#[derive(Default, Hash, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
 struct Inventory {
-    ore: usize,
-    clay: usize,
-    obsidian: usize,
-    geode: usize,
-    ore_robot: usize,
-    clay_robot: usize,
-    obsidian_robot: usize,
-    geode_robot: usize,
+    amounts: Vec<usize>,
+    robots: Vec<usize>,
 }
 
 impl Inventory {
-    fn new(num_ore_robots: usize) -> Inventory {
+    // Nothing collected yet, with some robots of the first resource.
+    fn new(num_resources: usize, num_first_robots: usize) -> Inventory {
+        let mut robots = vec![0; num_resources];
+        if num_resources > 0 {
+            robots[0] = num_first_robots;
+        }
         return Inventory {
-            ore: 0,
-            clay: 0,
-            obsidian: 0,
-            geode: 0,
-            ore_robot: num_ore_robots,
-            clay_robot: 0,
-            obsidian_robot: 0,
-            geode_robot: 0,
+            amounts: vec![0; num_resources],
+            robots: robots,
         };
     }
 
-    fn collect(&self) -> Inventory {
+    // Let the robots collect for a number of minutes.
+    fn advance(&self, minutes: usize) -> Inventory {
         return Inventory {
-            ore: self.ore + self.ore_robot,
-            clay: self.clay + self.clay_robot,
-            obsidian: self.obsidian + self.obsidian_robot,
-            geode: self.geode + self.geode_robot,
-            ore_robot: self.ore_robot,
-            clay_robot: self.clay_robot,
-            obsidian_robot: self.obsidian_robot,
-            geode_robot: self.geode_robot,
+            amounts: self
+                .amounts
+                .iter()
+                .zip(self.robots.iter())
+                .map(|(amount, robots)| amount + robots * minutes)
+                .collect(),
+            robots: self.robots.clone(),
         };
     }
 }
 
-// I cache per blueprint, so don't need blueprint in key.
-type Cache = FxHashMap<usize, usize>;
-type SeenState = FxHashSet<Inventory>;
+/// One robot built as part of a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Build {
+    /// The minute the robot is built during, counting from 1.
+    pub minute: usize,
+    pub robot: String,
+}
+
+/// The most of a resource that can be collected, and the robots to build to do it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildPlan {
+    pub collected: usize,
+    pub builds: Vec<Build>,
+}
 
 fn parse_blueprints(lines: &Vec<String>) -> Vec<Blueprint> {
-    return lines.iter().map(|line| Blueprint::new(sscanf::sscanf!(line, "Blueprint {}: Each ore robot costs {} ore. Each clay robot costs {} ore. Each obsidian robot costs {} ore and {} clay. Each geode robot costs {} ore and {} obsidian.", usize, usize, usize, usize, usize, usize, usize).unwrap())).collect();
+    return lines
+        .iter()
+        .map(|line| match Blueprint::parse(line) {
+            Ok(b) => b,
+            Err(e) => panic!("Can't read blueprint: {}", e),
+        })
+        .collect();
 }
 
 fn calculate_quality(b: &Blueprint, num_geodes: usize) -> usize {
     return b.id * num_geodes;
 }
 
-fn solve_single_blueprint(
-    blueprint: &Blueprint,
-    start_ore_robots: usize,
-    num_turns: usize,
-) -> usize {
-    let mut cache = Cache::default();
+// Minutes until there is enough of a resource, None if there never will be.
+fn wait_for(cost: usize, have: usize, robots: usize) -> Option<usize> {
+    if have >= cost {
+        return Some(0);
+    }
+    if robots == 0 {
+        return None;
+    }
+    return Some((cost - have).div_ceil(robots));
+}
 
-    if num_turns == 0 {
-        return 0;
+// Depth first search over which robot to build next, keeping the best found so far.
+struct BlueprintSearch<'a> {
+    blueprint: &'a Blueprint,
+    goal: usize,
+    // Only one robot is built a minute, so there is never a point in making more of
+    // a resource a minute than the most any robot costs, with the exception of the goal.
+    max_required: Vec<usize>,
+    // Robots to try first, the goal then the ones that cost the most.
+    robot_order: Vec<usize>,
+    // (minutes left when started, robot) for the path being searched.
+    path: Vec<(usize, usize)>,
+    best: usize,
+    best_path: Vec<(usize, usize)>,
+    // Reused by upper_bound, to save allocating every time.
+    scratch: Vec<usize>,
+}
+
+impl<'a> BlueprintSearch<'a> {
+    fn new(blueprint: &'a Blueprint, goal: usize) -> BlueprintSearch<'a> {
+        let num_resources = blueprint.resources.len();
+        let max_required = (0..num_resources)
+            .map(|resource| {
+                if resource == goal {
+                    return usize::MAX;
+                }
+                return blueprint
+                    .costs
+                    .iter()
+                    .map(|c| c[resource])
+                    .max()
+                    .unwrap_or(0);
+            })
+            .collect();
+        let mut robot_order: Vec<usize> = (0..num_resources).collect();
+        robot_order.sort_by_key(|robot| {
+            let total_cost: usize = blueprint.costs[*robot].iter().sum();
+            return (*robot != goal, usize::MAX - total_cost);
+        });
+        return BlueprintSearch {
+            blueprint: blueprint,
+            goal: goal,
+            max_required: max_required,
+            robot_order: robot_order,
+            path: vec![],
+            best: 0,
+            best_path: vec![],
+            scratch: vec![],
+        };
     }
 
-    // never a point in generating more of a resource then we need, with the exception of geodes.
-    let max_ore_required: usize = max(
-        max(
-            max(blueprint.ore_robot_cost, blueprint.clay_robot_cost),
-            blueprint.obsidian_ore_cost,
-        ),
-        blueprint.geode_ore_cost,
-    );
-    let max_clay_required = blueprint.obsidian_clay_cost;
-    let max_obsidian_required = blueprint.geode_obsidian_cost;
-
-    // If we ever have less geodes then another route with this, consider the route dead.
-    let max_fall_off = 2;
-
-    let start_state = Inventory::new(start_ore_robots);
-    let mut queue: VecDeque<(Inventory, usize)> = VecDeque::new();
-    queue.push_back((start_state, num_turns));
-    let mut seen = SeenState::default();
-
-    while let Some((state, cur_turns)) = queue.pop_front() {
-        let &prior_best = cache.get(&cur_turns).unwrap_or(&0);
-        if state.geode + max_fall_off < prior_best {
-            continue;
+    // Most of the goal possible if every other robot was free, and one of each was built
+    // every minute alongside a goal robot whenever the other resources stretch to one.
+    // The goal robot's own cost in the goal is never charged, as spending it can only
+    // make the real answer lower than the bound, never higher.
+    fn upper_bound(&mut self, state: &Inventory, time_left: usize) -> usize {
+        let goal = self.goal;
+        let goal_cost = &self.blueprint.costs[goal];
+        let amounts = &mut self.scratch;
+        amounts.clear();
+        amounts.extend_from_slice(&state.amounts);
+        let mut goal_robots = state.robots[goal];
+        for minute in 0..time_left {
+            let can_build_goal = amounts
+                .iter()
+                .zip(goal_cost)
+                .enumerate()
+                .all(|(resource, (have, cost))| resource == goal || have >= cost);
+            for (resource, amount) in amounts.iter_mut().enumerate() {
+                if resource == goal {
+                    *amount += goal_robots;
+                } else {
+                    *amount += state.robots[resource] + minute;
+                }
+            }
+            if can_build_goal {
+                for (resource, (amount, cost)) in amounts.iter_mut().zip(goal_cost).enumerate() {
+                    if resource != goal {
+                        *amount -= cost;
+                    }
+                }
+                goal_robots += 1;
+            }
         }
-        cache.insert(cur_turns, prior_best.max(state.geode));
+        return amounts[goal];
+    }
 
-        if cur_turns == 0 {
-            continue;
+    // The state after waiting to afford a robot then building it, None if it can't be done in time.
+    fn build(
+        &self,
+        robot: usize,
+        state: &Inventory,
+        time_left: usize,
+    ) -> Option<(Inventory, usize)> {
+        let costs = &self.blueprint.costs[robot];
+        let mut wait = 0;
+        for (resource, cost) in costs.iter().enumerate() {
+            let resource_wait = wait_for(*cost, state.amounts[resource], state.robots[resource])?;
+            wait = wait.max(resource_wait);
         }
-
-        // We are a BFS, so if we see the same state later, that means its just a later turn version of the same thing as another move
-        // Which will always be worse then being in that state earlier.
-        if seen.contains(&state) {
-            continue;
+        // A robot needs a minute to be built, then at least one more to be any use.
+        if wait + 1 >= time_left {
+            return None;
         }
-        seen.insert(state);
-
-        // You can think of a turn as made of three phases
-        // Construction start (pay costs)
-        // collection (Gain resources)
-        // construction finish (gain robots)
-
-        let new_state_base = state.collect();
-        let &next_best = cache.get(&(cur_turns - 1)).unwrap_or(&0);
-        if new_state_base.geode + max_fall_off < next_best {
-            // trim off all of these possibilities immediately
-            continue;
+        let mut next = state.advance(wait + 1);
+        for (amount, cost) in next.amounts.iter_mut().zip(costs) {
+            *amount -= cost;
         }
+        next.robots[robot] += 1;
+        return Some((next, time_left - wait - 1));
+    }
 
-        if state.ore_robot < max_ore_required && blueprint.ore_robot_cost <= state.ore {
-            let mut new_state = new_state_base.clone();
-            new_state.ore -= blueprint.ore_robot_cost;
-            new_state.ore_robot += 1;
-            queue.push_back((new_state, cur_turns - 1));
+    fn search(&mut self, state: Inventory, time_left: usize) {
+        // Building nothing else still collects some.
+        let idle = state.amounts[self.goal] + state.robots[self.goal] * time_left;
+        if idle > self.best {
+            self.best = idle;
+            self.best_path = self.path.clone();
         }
-        if state.clay_robot < max_clay_required && blueprint.clay_robot_cost <= state.ore {
-            let mut new_state = new_state_base.clone();
-            new_state.ore -= blueprint.clay_robot_cost;
-            new_state.clay_robot += 1;
-            queue.push_back((new_state, cur_turns - 1));
+        if self.upper_bound(&state, time_left) <= self.best {
+            return;
         }
-        if blueprint.obsidian_clay_cost <= state.clay
-            && blueprint.obsidian_ore_cost <= state.ore
-            && state.obsidian_robot < max_obsidian_required
-        {
-            let mut new_state = new_state_base.clone();
-            new_state.ore -= blueprint.obsidian_ore_cost;
-            new_state.clay -= blueprint.obsidian_clay_cost;
-            new_state.obsidian_robot += 1;
-            queue.push_back((new_state, cur_turns - 1));
-        }
-        if blueprint.geode_obsidian_cost <= state.obsidian && blueprint.geode_ore_cost <= state.ore
-        {
-            let mut new_state = new_state_base.clone();
-            new_state.ore -= blueprint.geode_ore_cost;
-            new_state.obsidian -= blueprint.geode_obsidian_cost;
-            new_state.geode_robot += 1;
-            queue.push_back((new_state, cur_turns - 1));
-        } else {
-            queue.push_back((new_state_base, cur_turns - 1));
+        for i in 0..self.robot_order.len() {
+            let robot = self.robot_order[i];
+            if state.robots[robot] >= self.max_required[robot] {
+                continue;
+            }
+            if let Some((next, next_time_left)) = self.build(robot, &state, time_left) {
+                self.path.push((next_time_left + 1, robot));
+                self.search(next, next_time_left);
+                self.path.pop();
+            }
         }
     }
-    return *cache.get(&0).unwrap();
+}
+
+/// Find the most of `goal` that can be collected in the time, and the robots to build for it.
+///
+/// Starts with a single robot collecting the first resource in the blueprint.
+/// ```
+/// let line = "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.";
+/// let blueprint = day19::Blueprint::parse(line).unwrap();
+/// let plan = day19::best_build_order(&blueprint, "geode", 24);
+/// assert_eq!(plan.collected, 9);
+/// assert_eq!(plan.builds[0], day19::Build { minute: 3, robot: "clay".to_string() });
+/// assert_eq!(plan.builds.last().unwrap().robot, "geode");
+/// ```
+pub fn best_build_order(blueprint: &Blueprint, goal: &str, minutes: usize) -> BuildPlan {
+    let goal = blueprint
+        .resource_index(goal)
+        .unwrap_or_else(|| panic!("No robot collects {}", goal));
+    let mut search = BlueprintSearch::new(blueprint, goal);
+    search.search(Inventory::new(blueprint.resources.len(), 1), minutes);
+    let builds = search
+        .best_path
+        .iter()
+        .map(|(time_left, robot)| Build {
+            minute: minutes - time_left + 1,
+            robot: blueprint.resources[*robot].clone(),
+        })
+        .collect();
+    return BuildPlan {
+        collected: search.best,
+        builds: builds,
+    };
+}
+
+fn solve_single_blueprint(blueprint: &Blueprint, num_turns: usize) -> usize {
+    return best_build_order(blueprint, "geode", num_turns).collected;
+}
+
+// Run `work` on every item, using no more threads than there are cores.
+fn map_in_pool<T, R, F>(items: &[T], work: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(items.len());
+    let next_item = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new((0..items.len()).map(|_| None).collect());
+    thread::scope(|scope| {
+        for worker in 0..workers {
+            thread::Builder::new()
+                .name(format!("blueprint-worker-{}", worker))
+                .spawn_scoped(scope, || loop {
+                    let i = next_item.fetch_add(1, Ordering::Relaxed);
+                    if i >= items.len() {
+                        break;
+                    }
+                    let result = work(&items[i]);
+                    results.lock().unwrap()[i] = Some(result);
+                })
+                .unwrap();
+        }
+    });
+    return results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.unwrap())
+        .collect();
 }
 
 /// Solution to puzzle_a entry point
@@ -197,18 +398,9 @@ pub fn puzzle_a(input: &Vec<String>) -> usize {
 
 // seperated out for easy testing
 fn solve_puzzle_a_state(blueprints: Vec<Blueprint>, num_rounds: usize) -> usize {
-    let handles: Vec<JoinHandle<usize>> = blueprints
-        .into_iter()
-        .map(|b| {
-            return thread::Builder::new()
-                .name(format!("blueprint-thread-{}", b.id).to_string())
-                .spawn(move || {
-                    return calculate_quality(&b, solve_single_blueprint(&b, 1, num_rounds));
-                })
-                .unwrap();
-        })
-        .collect();
-    let qualities: Vec<usize> = handles.into_iter().map(|t| t.join().unwrap()).collect();
+    let qualities = map_in_pool(&blueprints, |b| {
+        return calculate_quality(b, solve_single_blueprint(b, num_rounds));
+    });
     return qualities.iter().sum();
 }
 
@@ -227,24 +419,33 @@ pub fn puzzle_b(input: &Vec<String>) -> usize {
 }
 
 fn solve_puzzle_b_state(blueprints: Vec<Blueprint>, num_rounds: usize) -> usize {
-    let handles: Vec<JoinHandle<usize>> = blueprints
-        .into_iter()
-        .map(|b| {
-            return thread::Builder::new()
-                .name(format!("blueprint-thread-{}", b.id).to_string())
-                .spawn(move || {
-                    return solve_single_blueprint(&b, 1, num_rounds);
-                })
-                .unwrap();
-        })
-        .collect();
-    let joins: Vec<usize> = handles.into_iter().map(|t| t.join().unwrap()).collect();
-    return joins.iter().product();
+    let geodes = map_in_pool(&blueprints, |b| {
+        return solve_single_blueprint(b, num_rounds);
+    });
+    return geodes.iter().product();
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+
+    // id, ore_robot_cost, clay_robot_cost, obsidian_ore_cost, obsidian_clay_cost, geode_ore_cost, geode_obsidian_cost
+    fn standard_blueprint(t: (usize, usize, usize, usize, usize, usize, usize)) -> Blueprint {
+        return Blueprint {
+            id: t.0,
+            resources: ["ore", "clay", "obsidian", "geode"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            costs: vec![
+                vec![t.1, 0, 0, 0],
+                vec![t.2, 0, 0, 0],
+                vec![t.3, t.4, 0, 0],
+                vec![t.5, 0, t.6, 0],
+            ],
+        };
+    }
 
     fn get_blueprint_1() -> Vec<String> {
         return vec!["Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.".to_string()];
@@ -256,14 +457,14 @@ mod tests {
 
     #[test]
     fn test_parse_blueprint() {
-        let expected: Vec<Blueprint> = vec![Blueprint::new((2, 2, 3, 3, 8, 3, 12))];
+        let expected: Vec<Blueprint> = vec![standard_blueprint((2, 2, 3, 3, 8, 3, 12))];
         assert_eq!(parse_blueprints(&get_blueprint_2()), expected);
     }
 
     #[test]
     fn test_calculate_quality() {
         assert_eq!(
-            calculate_quality(&Blueprint::new((2, 2, 3, 3, 8, 3, 12)), 12),
+            calculate_quality(&standard_blueprint((2, 2, 3, 3, 8, 3, 12)), 12),
             24
         );
     }
@@ -284,6 +485,182 @@ mod tests {
         assert_eq!(solve_puzzle_a_state(blueprints, 24), 24);
     }
 
+    // Every choice, every minute, with no pruning.
+    fn brute_force(
+        b: &Blueprint,
+        goal: usize,
+        state: Inventory,
+        time_left: usize,
+        memo: &mut HashMap<(Inventory, usize), usize>,
+    ) -> usize {
+        if time_left == 0 {
+            return state.amounts[goal];
+        }
+        if let Some(known) = memo.get(&(state.clone(), time_left)) {
+            return *known;
+        }
+        let after = state.advance(1);
+        let mut best = brute_force(b, goal, after.clone(), time_left - 1, memo);
+        for (robot, costs) in b.costs.iter().enumerate() {
+            if state
+                .amounts
+                .iter()
+                .zip(costs)
+                .any(|(have, cost)| have < cost)
+            {
+                continue;
+            }
+            let mut next = after.clone();
+            for (amount, cost) in next.amounts.iter_mut().zip(costs) {
+                *amount -= cost;
+            }
+            next.robots[robot] += 1;
+            best = best.max(brute_force(b, goal, next, time_left - 1, memo));
+        }
+        memo.insert((state, time_left), best);
+        return best;
+    }
+
+    // Play out a plan a minute at a time, checking every robot can be afforded.
+    fn replay(b: &Blueprint, goal: usize, plan: &BuildPlan, minutes: usize) -> usize {
+        let mut state = Inventory::new(b.resources.len(), 1);
+        let mut builds = plan.builds.iter().peekable();
+        for minute in 1..=minutes {
+            let mut next = state.advance(1);
+            if let Some(build) = builds.next_if(|build| build.minute == minute) {
+                let robot = b.resource_index(&build.robot).unwrap();
+                let costs = &b.costs[robot];
+                assert!(state
+                    .amounts
+                    .iter()
+                    .zip(costs)
+                    .all(|(have, cost)| have >= cost));
+                for (amount, cost) in next.amounts.iter_mut().zip(costs) {
+                    *amount -= cost;
+                }
+                next.robots[robot] += 1;
+            }
+            state = next;
+        }
+        assert!(builds.next().is_none());
+        return state.amounts[goal];
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let blueprints = [
+            standard_blueprint((1, 4, 2, 3, 14, 2, 7)),
+            standard_blueprint((2, 2, 3, 3, 8, 3, 12)),
+            standard_blueprint((3, 1, 1, 1, 2, 1, 2)),
+            standard_blueprint((4, 3, 4, 2, 5, 4, 3)),
+            standard_blueprint((5, 2, 2, 2, 2, 2, 2)),
+        ];
+        for b in &blueprints {
+            for turns in [0, 1, 8, 11] {
+                let mut memo = HashMap::new();
+                let plan = best_build_order(b, "geode", turns);
+                assert_eq!(replay(b, 3, &plan, turns), plan.collected);
+                assert_eq!(
+                    plan.collected,
+                    brute_force(b, 3, Inventory::new(4, 1), turns, &mut memo),
+                    "blueprint {} for {} turns",
+                    b.id,
+                    turns
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_other_resources() {
+        let line = "Blueprint 3: Each wood robot costs 1 wood. Each plank robot costs 2 wood. Each nail robot costs 3 wood. Each chair robot costs 2 plank and 2 nail. Each table robot costs 1 wood and 3 chair.";
+        let b = Blueprint::parse(line).unwrap();
+        assert_eq!(b.resources.len(), 5);
+        for goal in ["chair", "table"] {
+            let index = b.resource_index(goal).unwrap();
+            for turns in [6, 8, 10] {
+                let mut memo = HashMap::new();
+                let plan = best_build_order(&b, goal, turns);
+                assert_eq!(replay(&b, index, &plan, turns), plan.collected);
+                assert_eq!(
+                    plan.collected,
+                    brute_force(&b, index, Inventory::new(5, 1), turns, &mut memo),
+                    "{} for {} turns",
+                    goal,
+                    turns
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_goal_robot_spends_goal() {
+        // Every robot here costs some of the goal, so the bound can't charge for it.
+        let ore = standard_blueprint((1, 4, 2, 3, 14, 2, 7));
+        for turns in [6, 10, 11, 12] {
+            let mut memo = HashMap::new();
+            let plan = best_build_order(&ore, "ore", turns);
+            assert_eq!(replay(&ore, 0, &plan, turns), plan.collected);
+            assert_eq!(
+                plan.collected,
+                brute_force(&ore, 0, Inventory::new(4, 1), turns, &mut memo),
+                "ore for {} turns",
+                turns
+            );
+        }
+        let wood = Blueprint::parse("Blueprint 9: Each wood robot costs 2 wood.").unwrap();
+        for turns in [6, 9, 13] {
+            let mut memo = HashMap::new();
+            let plan = best_build_order(&wood, "wood", turns);
+            assert_eq!(replay(&wood, 0, &plan, turns), plan.collected);
+            assert_eq!(
+                plan.collected,
+                brute_force(&wood, 0, Inventory::new(1, 1), turns, &mut memo),
+                "wood for {} turns",
+                turns
+            );
+        }
+        assert_eq!(best_build_order(&wood, "wood", 6).collected, 7);
+        assert_eq!(best_build_order(&wood, "wood", 13).collected, 42);
+        assert_eq!(best_build_order(&ore, "ore", 12).collected, 15);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Blueprint::parse("Blueprint x: Each ore robot costs 1 ore."),
+            Err(BlueprintError::BadHeader("Blueprint x".to_string()))
+        );
+        assert_eq!(
+            Blueprint::parse("Blueprint 1: Each ore robot costs one ore."),
+            Err(BlueprintError::BadClause(
+                "Each ore robot costs one ore".to_string()
+            ))
+        );
+        assert_eq!(
+            Blueprint::parse(
+                "Blueprint 1: Each ore robot costs 1 ore. Each ore robot costs 2 ore."
+            ),
+            Err(BlueprintError::DuplicateRobot("ore".to_string()))
+        );
+        assert_eq!(
+            Blueprint::parse("Blueprint 1: Each ore robot costs 1 gold.")
+                .unwrap_err()
+                .to_string(),
+            "no robot collects gold"
+        );
+    }
+
+    #[test]
+    fn test_pool_keeps_order() {
+        let items: Vec<usize> = (0..50).collect();
+        assert_eq!(
+            map_in_pool(&items, |i| i * 2),
+            (0..100).step_by(2).collect::<Vec<_>>()
+        );
+        assert!(map_in_pool(&Vec::<usize>::new(), |i| *i).is_empty());
+    }
+
     #[test]
     fn test_partial_b() {
         let vec1: Vec<String> = get_blueprint_1();
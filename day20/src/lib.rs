@@ -1,4 +1,5 @@
 extern crate filelib;
+extern crate mathlib;
 
 pub use filelib::load_no_blanks;
 use std::collections::VecDeque;
@@ -23,7 +24,7 @@ pub fn mix_numbers(input: Vec<i64>, iters: usize) -> Vec<i64> {
             let idx = queue.iter().position(|(j, _)| i == *j).unwrap();
             queue.rotate_left(idx);
             let (j, v) = queue.pop_front().unwrap();
-            let d = v.rem_euclid(queue.len() as i64) as usize;
+            let d = mathlib::modulus(*v, queue.len() as i64) as usize;
             queue.rotate_left(d);
             queue.push_front((j, v));
 
@@ -1,8 +1,59 @@
 extern crate filelib;
 
 pub use filelib::load_no_blanks;
+use num_rational::Ratio;
+use num_traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, One, Zero};
 use rustc_hash::FxHashMap;
-use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+/// Exact numbers for solving, so dividing never rounds.
+pub type Rational = Ratio<i128>;
+
+const ROOT: &str = "root";
+const HUMAN: &str = "humn";
+
+/// Why the monkeys couldn't be worked out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonkeyError {
+    /// A monkey refers to a monkey that doesn't exist.
+    MissingMonkey(String),
+    /// Two monkeys have the same name.
+    DuplicateMonkey(String),
+    /// Monkeys waiting on each other in a loop, in the order they wait.
+    Cycle(Vec<String>),
+    /// This monkey has to divide by zero.
+    DivideByZero(String),
+    /// This monkey's number got too big to work with.
+    Overflow(String),
+    /// The unknown shows up on both sides of this monkey, so it can't be undone.
+    UnknownOnBothSides(String),
+    /// The only answer isn't a whole number, it is this fraction instead.
+    NoIntegerSolution(Rational),
+    /// No number gives the wanted answer.
+    NoSolution,
+    /// More than one number gives the wanted answer.
+    ManySolutions,
+}
+
+impl Display for MonkeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            MonkeyError::MissingMonkey(name) => write!(f, "no monkey called {}", name),
+            MonkeyError::DuplicateMonkey(name) => write!(f, "more than one monkey called {}", name),
+            MonkeyError::Cycle(names) => {
+                write!(f, "monkeys wait on each other: {}", names.join(" -> "))
+            }
+            MonkeyError::DivideByZero(name) => write!(f, "{} divides by zero", name),
+            MonkeyError::Overflow(name) => write!(f, "{} yells a number that is too large", name),
+            MonkeyError::UnknownOnBothSides(name) => {
+                write!(f, "{} depends on the unknown through both sides", name)
+            }
+            MonkeyError::NoIntegerSolution(v) => write!(f, "only solution is {}", v),
+            MonkeyError::NoSolution => write!(f, "no value works"),
+            MonkeyError::ManySolutions => write!(f, "more than one value works"),
+        };
+    }
+}
 
 #[derive(Debug, Clone)]
 struct Monkey {
@@ -18,90 +69,443 @@ enum Value {
     MonkeyPointer(String),
 }
 
-#[derive(Debug, Clone)]
-enum Operation {
+/// What a monkey does with the numbers from the two monkeys it listens to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
     Add,
     Subtract,
     Multiply,
     Divide,
+    /// Yells 1 if both sides match, 0 otherwise.
     Equal,
 }
 
 impl Operation {
-    fn compute(&self, left_value: i64, right_value: i64) -> i64 {
+    // None if it overflows, or divides by zero.
+    fn compute(&self, left_value: i64, right_value: i64) -> Option<i64> {
         return match self {
-            Operation::Add => left_value + right_value,
-            Operation::Subtract => left_value - right_value,
-            Operation::Multiply => left_value * right_value,
-            Operation::Divide => left_value / right_value,
-            Operation::Equal => {
-                if left_value == right_value {
-                    1
-                } else {
-                    // We are going to use an approximation instead of solving for now
-                    // But we need the difference to approximate.
-                    left_value - right_value
-                }
-            }
+            Operation::Add => left_value.checked_add(right_value),
+            Operation::Subtract => left_value.checked_sub(right_value),
+            Operation::Multiply => left_value.checked_mul(right_value),
+            Operation::Divide => left_value.checked_div(right_value),
+            Operation::Equal => Some((left_value == right_value) as i64),
+        };
+    }
+
+    // Same as compute, but without rounding.
+    fn compute_exact(&self, left_value: &Rational, right_value: &Rational) -> Option<Rational> {
+        return match self {
+            Operation::Add => left_value.checked_add(right_value),
+            Operation::Subtract => left_value.checked_sub(right_value),
+            Operation::Multiply => left_value.checked_mul(right_value),
+            Operation::Divide => left_value.checked_div(right_value),
+            Operation::Equal => Some(Rational::from_integer((left_value == right_value) as i128)),
+        };
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Operation::Add => "+",
+            Operation::Subtract => "-",
+            Operation::Multiply => "*",
+            Operation::Divide => "/",
+            Operation::Equal => "=",
         };
+        return write!(f, "{}", symbol);
     }
 }
 
-fn solve_monkeys(monkies: Vec<Monkey>) -> i64 {
-    //println!("In monkies");
-    let mut known_values: FxHashMap<String, i64> = FxHashMap::default();
-    let mut queue: VecDeque<Monkey> = VecDeque::new();
-    // pre compute all Discrete Monkies
-    for monkey in monkies {
-        match monkey.left_value {
-            Value::Discrete(v) => {
-                //println!("Adding {} {}", monkey.name, v);
-                known_values.insert(monkey.name.clone(), v);
+/// A monkey's job written out, with everything that doesn't depend on the unknown worked out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    Number(Rational),
+    Unknown(String),
+    Operation(Operation, Box<Expression>, Box<Expression>),
+}
+
+impl Expression {
+    fn write_nested(&self, f: &mut Formatter<'_>, outermost: bool) -> std::fmt::Result {
+        return match self {
+            Expression::Number(v) => {
+                if v.is_integer() && *v >= Rational::zero() {
+                    write!(f, "{}", v)
+                } else {
+                    write!(f, "({})", v)
+                }
             }
-            Value::MonkeyPointer(_) => {
-                queue.push_back(monkey);
+            Expression::Unknown(name) => write!(f, "{}", name),
+            Expression::Operation(op, left, right) => {
+                if !outermost {
+                    write!(f, "(")?;
+                }
+                left.write_nested(f, false)?;
+                write!(f, " {} ", op)?;
+                right.write_nested(f, false)?;
+                if !outermost {
+                    write!(f, ")")?;
+                }
+                Ok(())
             }
         };
     }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return self.write_nested(f, true);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Job {
+    Number(i64),
+    Operation(Operation, usize, usize),
+}
 
-    // println!("queue: {:?}", queue);
+/// All the monkeys, with each one pointing at the monkeys it listens to.
+///
+/// Built once up front, so missing monkeys and loops are found before any maths is done.
+#[derive(Debug, Clone)]
+pub struct MonkeyGraph {
+    names: Vec<String>,
+    index: FxHashMap<String, usize>,
+    jobs: Vec<Job>,
+    // Every monkey comes after the monkeys it listens to.
+    order: Vec<usize>,
+}
+
+impl MonkeyGraph {
+    /// Read the monkeys from the puzzle input.
+    ///
+    /// If `root_monkey_equal` is set, root checks its sides are equal instead of its listed job.
+    /// ```
+    /// let input: Vec<String> = vec!["root: a + b", "a: b * two", "b: a - two", "two: 2"]
+    ///   .iter().map(|s| s.to_string()).collect();
+    /// let err = day21::MonkeyGraph::parse(&input, false).unwrap_err();
+    /// assert_eq!(err.to_string(), "monkeys wait on each other: a -> b -> a");
+    /// ```
+    pub fn parse(input: &Vec<String>, root_monkey_equal: bool) -> Result<MonkeyGraph, MonkeyError> {
+        return MonkeyGraph::new(parse_input(input, root_monkey_equal));
+    }
 
-    // Now go through the queue, whenever we have an operation we can't do yet, put to the end of the queue
-    while let Some(monkey) = queue.pop_front() {
-        let monkey_left = monkey.left_value.clone();
-        let monkey_right = monkey.right_value.clone();
-        let left = match monkey_left {
-            Value::MonkeyPointer(l) => l,
-            _ => panic!("Error in logic"),
+    fn new(monkies: Vec<Monkey>) -> Result<MonkeyGraph, MonkeyError> {
+        let mut index: FxHashMap<String, usize> = FxHashMap::default();
+        for (i, monkey) in monkies.iter().enumerate() {
+            if index.insert(monkey.name.clone(), i).is_some() {
+                return Err(MonkeyError::DuplicateMonkey(monkey.name.clone()));
+            }
+        }
+        let lookup = |name: &str| {
+            return index
+                .get(name)
+                .copied()
+                .ok_or_else(|| MonkeyError::MissingMonkey(name.to_string()));
         };
-        let l: &str = &left;
-        if !known_values.contains_key(l) {
-            queue.push_back(monkey.clone());
-            continue;
+        let mut jobs = vec![];
+        for monkey in &monkies {
+            let job = match (&monkey.left_value, &monkey.op, &monkey.right_value) {
+                (Value::Discrete(v), None, None) => Job::Number(*v),
+                (Value::MonkeyPointer(l), Some(op), Some(Value::MonkeyPointer(r))) => {
+                    Job::Operation(*op, lookup(l)?, lookup(r)?)
+                }
+                _ => panic!("Error in logic"),
+            };
+            jobs.push(job);
         }
-        let right = match monkey_right.unwrap() {
-            Value::MonkeyPointer(r) => r,
-            _ => panic!("Error in logic"),
+        let names = monkies.into_iter().map(|m| m.name).collect();
+        let mut graph = MonkeyGraph {
+            names: names,
+            index: index,
+            jobs: jobs,
+            order: vec![],
         };
-        let r: &str = &right;
-        if !known_values.contains_key(r) {
-            queue.push_back(monkey.clone());
-            continue;
+        graph.order = graph.topological_order()?;
+        return Ok(graph);
+    }
+
+    fn children(&self, monkey: usize) -> Option<(usize, usize)> {
+        return match self.jobs[monkey] {
+            Job::Number(_) => None,
+            Job::Operation(_, l, r) => Some((l, r)),
+        };
+    }
+
+    // Kahn's algorithm, a monkey is ready once everyone it listens to is.
+    fn topological_order(&self) -> Result<Vec<usize>, MonkeyError> {
+        let mut waiting_on = vec![0; self.jobs.len()];
+        let mut listeners: Vec<Vec<usize>> = vec![vec![]; self.jobs.len()];
+        for (monkey, waiting) in waiting_on.iter_mut().enumerate() {
+            if let Some((l, r)) = self.children(monkey) {
+                *waiting = 2;
+                listeners[l].push(monkey);
+                listeners[r].push(monkey);
+            }
+        }
+        let mut order: Vec<usize> = (0..self.jobs.len())
+            .filter(|m| waiting_on[*m] == 0)
+            .collect();
+        let mut next = 0;
+        while next < order.len() {
+            for listener in &listeners[order[next]] {
+                waiting_on[*listener] -= 1;
+                if waiting_on[*listener] == 0 {
+                    order.push(*listener);
+                }
+            }
+            next += 1;
+        }
+        if order.len() < self.jobs.len() {
+            let stuck = (0..self.jobs.len()).find(|m| waiting_on[*m] > 0).unwrap();
+            return Err(MonkeyError::Cycle(self.find_cycle(stuck, &waiting_on)));
+        }
+        return Ok(order);
+    }
+
+    // Every stuck monkey listens to at least one other stuck monkey, so following them loops.
+    fn find_cycle(&self, start: usize, waiting_on: &[usize]) -> Vec<String> {
+        let mut seen: FxHashMap<usize, usize> = FxHashMap::default();
+        let mut path = vec![];
+        let mut cur = start;
+        while !seen.contains_key(&cur) {
+            seen.insert(cur, path.len());
+            path.push(cur);
+            let (l, r) = self.children(cur).unwrap();
+            cur = if waiting_on[l] > 0 { l } else { r };
+        }
+        let mut cycle: Vec<String> = path[seen[&cur]..]
+            .iter()
+            .map(|m| self.names[*m].clone())
+            .collect();
+        cycle.push(self.names[cur].clone());
+        return cycle;
+    }
+
+    fn lookup(&self, name: &str) -> Result<usize, MonkeyError> {
+        return self
+            .index
+            .get(name)
+            .copied()
+            .ok_or_else(|| MonkeyError::MissingMonkey(name.to_string()));
+    }
+
+    // Monkeys `monkey` listens to, directly or not, in evaluation order.
+    fn order_from(&self, monkey: usize) -> Vec<usize> {
+        let mut needed = vec![false; self.jobs.len()];
+        let mut stack = vec![monkey];
+        while let Some(cur) = stack.pop() {
+            if needed[cur] {
+                continue;
+            }
+            needed[cur] = true;
+            if let Some((l, r)) = self.children(cur) {
+                stack.push(l);
+                stack.push(r);
+            }
         }
-        // We know both, solve this one
-        let op = match monkey.op {
-            Some(o) => o,
-            _ => panic!("Error in logic"),
+        return self.order.iter().copied().filter(|m| needed[*m]).collect();
+    }
+
+    /// Work out what a monkey yells, dividing like the monkeys do by rounding towards zero.
+    /// ```
+    /// let input: Vec<String> = vec!["root: a / b", "a: 7", "b: 2"]
+    ///   .iter().map(|s| s.to_string()).collect();
+    /// let graph = day21::MonkeyGraph::parse(&input, false).unwrap();
+    /// assert_eq!(graph.evaluate("root"), Ok(3));
+    /// assert_eq!(graph.evaluate("a"), Ok(7));
+    /// ```
+    pub fn evaluate(&self, name: &str) -> Result<i64, MonkeyError> {
+        let target = self.lookup(name)?;
+        let mut values = vec![0; self.jobs.len()];
+        for monkey in self.order_from(target) {
+            values[monkey] = match self.jobs[monkey] {
+                Job::Number(v) => v,
+                Job::Operation(op, l, r) => {
+                    if op == Operation::Divide && values[r] == 0 {
+                        return Err(MonkeyError::DivideByZero(self.names[monkey].clone()));
+                    }
+                    op.compute(values[l], values[r])
+                        .ok_or_else(|| MonkeyError::Overflow(self.names[monkey].clone()))?
+                }
+            };
+        }
+        return Ok(values[target]);
+    }
+
+    // Which monkeys in `order` depend on `unknown`.
+    fn depends_on(&self, order: &[usize], unknown: usize) -> Vec<bool> {
+        let mut depends = vec![false; self.jobs.len()];
+        for monkey in order {
+            depends[*monkey] = match self.children(*monkey) {
+                _ if *monkey == unknown => true,
+                Some((l, r)) => depends[l] || depends[r],
+                None => false,
+            };
+        }
+        return depends;
+    }
+
+    // Exact values of every monkey in `order` that doesn't depend on the unknown.
+    fn known_values(
+        &self,
+        order: &[usize],
+        depends: &[bool],
+    ) -> Result<Vec<Option<Rational>>, MonkeyError> {
+        let mut values: Vec<Option<Rational>> = vec![None; self.jobs.len()];
+        for monkey in order {
+            if depends[*monkey] {
+                continue;
+            }
+            values[*monkey] = Some(match self.jobs[*monkey] {
+                Job::Number(v) => Rational::from_integer(v as i128),
+                Job::Operation(op, l, r) => {
+                    let left = values[l].unwrap();
+                    let right = values[r].unwrap();
+                    if op == Operation::Divide && right.is_zero() {
+                        return Err(MonkeyError::DivideByZero(self.names[*monkey].clone()));
+                    }
+                    op.compute_exact(&left, &right)
+                        .ok_or_else(|| MonkeyError::Overflow(self.names[*monkey].clone()))?
+                }
+            });
+        }
+        return Ok(values);
+    }
+
+    /// Write out what a monkey yells in terms of `unknown`, working out everything else.
+    /// ```
+    /// let input: Vec<String> = vec!["root: a + b", "a: humn * c", "b: c + c", "c: 3", "humn: 1"]
+    ///   .iter().map(|s| s.to_string()).collect();
+    /// let graph = day21::MonkeyGraph::parse(&input, true).unwrap();
+    /// assert_eq!(graph.expression("root", "humn").unwrap().to_string(), "(humn * 3) = 6");
+    /// ```
+    pub fn expression(&self, name: &str, unknown: &str) -> Result<Expression, MonkeyError> {
+        let target = self.lookup(name)?;
+        let unknown_monkey = self.lookup(unknown)?;
+        let order = self.order_from(target);
+        let depends = self.depends_on(&order, unknown_monkey);
+        let values = self.known_values(&order, &depends)?;
+        if !depends[target] {
+            return Ok(Expression::Number(values[target].unwrap()));
+        }
+        // Only monkeys that depend on the unknown need writing out, the rest are numbers.
+        let mut expressions: FxHashMap<usize, Expression> = FxHashMap::default();
+        let written = |monkey: usize, expressions: &FxHashMap<usize, Expression>| {
+            return match values[monkey] {
+                Some(v) => Expression::Number(v),
+                None => expressions[&monkey].clone(),
+            };
         };
-        let left_value = known_values[l];
-        let right_value = known_values[r];
-        let name = monkey.name.clone();
-        let computed = op.compute(left_value, right_value);
-        //println!("Adding {} {}", name, computed);
-        known_values.insert(name, computed);
+        for monkey in order {
+            if !depends[monkey] {
+                continue;
+            }
+            let expression = match self.jobs[monkey] {
+                _ if monkey == unknown_monkey => Expression::Unknown(unknown.to_string()),
+                Job::Operation(op, l, r) => Expression::Operation(
+                    op,
+                    Box::new(written(l, &expressions)),
+                    Box::new(written(r, &expressions)),
+                ),
+                Job::Number(_) => panic!("Error in logic"),
+            };
+            expressions.insert(monkey, expression);
+        }
+        return Ok(expressions.remove(&target).unwrap());
+    }
+
+    /// Find what `unknown` has to yell so `name` yells `target`, ignoring the unknown's own job.
+    ///
+    /// For a monkey checking equality, a target of 1 means both sides have to match.
+    /// ```
+    /// let input: Vec<String> = vec!["root: a + b", "a: humn * c", "b: 4", "c: 3", "humn: 1"]
+    ///   .iter().map(|s| s.to_string()).collect();
+    /// let graph = day21::MonkeyGraph::parse(&input, false).unwrap();
+    /// assert_eq!(graph.solve("root", 11, "humn").unwrap().to_string(), "7/3");
+    /// ```
+    pub fn solve(&self, name: &str, target: i64, unknown: &str) -> Result<Rational, MonkeyError> {
+        let start = self.lookup(name)?;
+        let unknown_monkey = self.lookup(unknown)?;
+        let order = self.order_from(start);
+        let depends = self.depends_on(&order, unknown_monkey);
+        let values = self.known_values(&order, &depends)?;
+        if !depends[start] {
+            // Nothing to change, so it is either always right or never right
+            if values[start] == Some(Rational::from_integer(target as i128)) {
+                return Err(MonkeyError::ManySolutions);
+            }
+            return Err(MonkeyError::NoSolution);
+        }
+
+        // Walk down the path to the unknown, undoing each operation on the way.
+        let mut target = Rational::from_integer(target as i128);
+        let mut cur = start;
+        while cur != unknown_monkey {
+            let (op, l, r) = match self.jobs[cur] {
+                Job::Operation(op, l, r) => (op, l, r),
+                Job::Number(_) => panic!("Error in logic"),
+            };
+            if depends[l] && depends[r] {
+                return Err(MonkeyError::UnknownOnBothSides(self.names[cur].clone()));
+            }
+            let unknown_left = depends[l];
+            let (next, known) = if unknown_left {
+                (l, values[r].unwrap())
+            } else {
+                (r, values[l].unwrap())
+            };
+            let next_target = match op {
+                // u + k = t, k + u = t
+                Operation::Add => target.checked_sub(&known),
+                // u - k = t
+                Operation::Subtract if unknown_left => target.checked_add(&known),
+                // k - u = t
+                Operation::Subtract => known.checked_sub(&target),
+                // u * k = t, k * u = t
+                Operation::Multiply => {
+                    if known.is_zero() {
+                        if target.is_zero() {
+                            return Err(MonkeyError::ManySolutions);
+                        }
+                        return Err(MonkeyError::NoSolution);
+                    }
+                    target.checked_div(&known)
+                }
+                // u / k = t
+                Operation::Divide if unknown_left => {
+                    if known.is_zero() {
+                        return Err(MonkeyError::DivideByZero(self.names[cur].clone()));
+                    }
+                    target.checked_mul(&known)
+                }
+                // k / u = t
+                Operation::Divide => {
+                    if target.is_zero() {
+                        if known.is_zero() {
+                            return Err(MonkeyError::ManySolutions);
+                        }
+                        return Err(MonkeyError::NoSolution);
+                    }
+                    known.checked_div(&target)
+                }
+                // u = k
+                Operation::Equal => {
+                    if target.is_one() {
+                        Some(known)
+                    } else if target.is_zero() {
+                        // Anything but k works, so there is no single answer
+                        return Err(MonkeyError::ManySolutions);
+                    } else {
+                        return Err(MonkeyError::NoSolution);
+                    }
+                }
+            };
+            target = next_target.ok_or_else(|| MonkeyError::Overflow(self.names[cur].clone()))?;
+            cur = next;
+        }
+        return Ok(target);
     }
-    let final_value = "root".to_string();
-    return known_values[&final_value];
 }
 
 fn parse_input(lines: &Vec<String>, root_monkey_equal: bool) -> Vec<Monkey> {
@@ -115,7 +519,7 @@ fn parse_input(lines: &Vec<String>, root_monkey_equal: bool) -> Vec<Monkey> {
             if partial.contains(" ") {
                 let (lhs_pointer, rest_equation) = partial.split_once(" ").unwrap();
                 let (op_string, rhs_pointer) = rest_equation.split_once(" ").unwrap();
-                if name == "root" && root_monkey_equal {
+                if name == ROOT && root_monkey_equal {
                     op = Some(Operation::Equal);
                 } else {
                     op = match op_string {
@@ -152,9 +556,30 @@ fn parse_input(lines: &Vec<String>, root_monkey_equal: bool) -> Vec<Monkey> {
 /// assert_eq!(day21::puzzle_a(&vec1), 152);
 /// ```
 pub fn puzzle_a(input: &Vec<String>) -> i64 {
-    let monkey_list = parse_input(input, false);
-    //println!("input parsed");
-    return solve_monkeys(monkey_list);
+    let result = MonkeyGraph::parse(input, false).and_then(|graph| graph.evaluate(ROOT));
+    return match result {
+        Ok(v) => v,
+        Err(e) => panic!("Can't work out {}: {}", ROOT, e),
+    };
+}
+
+/// Find the number the human needs to yell, so both sides of root are equal.
+/// ```
+/// let vec1: Vec<String> = vec!["root: humn + half", "half: two / four", "two: 2", "four: 4", "humn: 5"]
+///   .iter().map(|s| s.to_string()).collect();
+/// let err = day21::solve_human(&vec1).unwrap_err();
+/// assert_eq!(err.to_string(), "only solution is 1/2");
+/// ```
+pub fn solve_human(input: &Vec<String>) -> Result<i64, MonkeyError> {
+    let graph = MonkeyGraph::parse(input, true)?;
+    let value = graph.solve(ROOT, 1, HUMAN)?;
+    if !value.is_integer() {
+        return Err(MonkeyError::NoIntegerSolution(value));
+    }
+    return value
+        .to_integer()
+        .try_into()
+        .map_err(|_| MonkeyError::Overflow(HUMAN.to_string()));
 }
 
 /// Solution to puzzle_b entry point
@@ -166,40 +591,173 @@ pub fn puzzle_a(input: &Vec<String>) -> i64 {
 /// assert_eq!(day21::puzzle_b(&vec1), 301);
 /// ```
 pub fn puzzle_b(input: &Vec<String>) -> i64 {
-    let mut monkey_list = parse_input(input, true);
-    let pos = monkey_list.iter().position(|m| m.name == "humn").unwrap();
-    let mut human_monkey = monkey_list.remove(pos);
-    let mut counter = match human_monkey.left_value.clone() {
-        Value::Discrete(n) => n,
-        _ => panic!("whoops"),
+    return match solve_human(input) {
+        Ok(v) => v,
+        Err(e) => panic!("Can't find a number for {}: {}", HUMAN, e),
     };
-    loop {
-        let cur_monkey = human_monkey.clone();
-        let mut cur_list = monkey_list.clone();
-        cur_list.push(cur_monkey);
-        let result = solve_monkeys(cur_list);
-        if result == 1 {
-            break;
-        } else {
-            // approximation algorithm, move massively toward the result, if we are below 100, add to it
-            // This will rapidly get us in the correct range, but it isn't a true solution.
-            if result < 100 {
-                counter += 1;
-            } else {
-                counter += (result / 100);
-            }
-        }
-        human_monkey.left_value = Value::Discrete(counter);
-    }
-    return counter;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn to_lines(lines: &[&str]) -> Vec<String> {
+        return lines.iter().map(|s| s.to_string()).collect();
+    }
+
     #[test]
     fn test_example() {
-        assert_eq!(1, 1);
+        let input = to_lines(&[
+            "root: pppw + sjmn",
+            "dbpl: 5",
+            "cczh: sllz + lgvd",
+            "zczc: 2",
+            "ptdq: humn - dvpt",
+            "dvpt: 3",
+            "lfqf: 4",
+            "humn: 5",
+            "ljgn: 2",
+            "sjmn: drzm * dbpl",
+            "sllz: 4",
+            "pppw: cczh / lfqf",
+            "lgvd: ljgn * ptdq",
+            "drzm: hmdt - zczc",
+            "hmdt: 32",
+        ]);
+        assert_eq!(solve_human(&input), Ok(301));
+    }
+
+    #[test]
+    fn test_example_expression() {
+        let input = to_lines(&[
+            "root: pppw + sjmn",
+            "dbpl: 5",
+            "cczh: sllz + lgvd",
+            "zczc: 2",
+            "ptdq: humn - dvpt",
+            "dvpt: 3",
+            "lfqf: 4",
+            "humn: 5",
+            "ljgn: 2",
+            "sjmn: drzm * dbpl",
+            "sllz: 4",
+            "pppw: cczh / lfqf",
+            "lgvd: ljgn * ptdq",
+            "drzm: hmdt - zczc",
+            "hmdt: 32",
+        ]);
+        let graph = MonkeyGraph::parse(&input, true).unwrap();
+        let expression = graph.expression(ROOT, HUMAN).unwrap();
+        assert_eq!(expression.to_string(), "((4 + (2 * (humn - 3))) / 4) = 150");
+        assert_eq!(graph.expression("sjmn", HUMAN).unwrap().to_string(), "150");
+        assert_eq!(graph.evaluate("lgvd"), Ok(4));
+    }
+
+    #[test]
+    fn test_shared_monkeys() {
+        // Both sides listen to the same monkey, which is fine as long as it isn't the unknown.
+        let input = to_lines(&["root: a * a", "a: b + b", "b: c - humn", "c: 1", "humn: 7"]);
+        let graph = MonkeyGraph::parse(&input, false).unwrap();
+        assert_eq!(graph.evaluate(ROOT), Ok(144));
+        assert_eq!(
+            graph.expression(ROOT, HUMAN).unwrap().to_string(),
+            "((1 - humn) + (1 - humn)) * ((1 - humn) + (1 - humn))"
+        );
+        assert_eq!(
+            graph.solve(ROOT, 144, HUMAN),
+            Err(MonkeyError::UnknownOnBothSides("root".to_string()))
+        );
+        assert_eq!(graph.solve("b", -6, HUMAN), Ok(Rational::from_integer(7)));
+    }
+
+    #[test]
+    fn test_graph_errors() {
+        let input = to_lines(&["root: a + b", "a: 1", "b: 2", "a: 3"]);
+        assert_eq!(
+            MonkeyGraph::parse(&input, false).unwrap_err(),
+            MonkeyError::DuplicateMonkey("a".to_string())
+        );
+        let input = to_lines(&["root: a + b", "a: 1"]);
+        assert_eq!(
+            MonkeyGraph::parse(&input, false).unwrap_err(),
+            MonkeyError::MissingMonkey("b".to_string())
+        );
+        let input = to_lines(&["root: root + a", "a: 1"]);
+        assert_eq!(
+            MonkeyGraph::parse(&input, false).unwrap_err(),
+            MonkeyError::Cycle(vec!["root".to_string(), "root".to_string()])
+        );
+        let input = to_lines(&["root: a / b", "a: 1", "b: c - c", "c: 4"]);
+        let graph = MonkeyGraph::parse(&input, false).unwrap();
+        assert_eq!(
+            graph.evaluate(ROOT),
+            Err(MonkeyError::DivideByZero("root".to_string()))
+        );
+        // Other monkeys are still fine
+        assert_eq!(graph.evaluate("b"), Ok(0));
+        let input = to_lines(&["root: a * a", "a: b * b", "b: 100000"]);
+        let graph = MonkeyGraph::parse(&input, false).unwrap();
+        assert_eq!(
+            graph.evaluate(ROOT).unwrap_err().to_string(),
+            "root yells a number that is too large"
+        );
+        assert_eq!(graph.evaluate("a"), Ok(10000000000));
+        assert_eq!(
+            graph.evaluate("z"),
+            Err(MonkeyError::MissingMonkey("z".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_human_on_the_right() {
+        // 100 / (humn - 5) = 4, so humn = 30
+        let input = to_lines(&[
+            "root: left + right",
+            "left: hundred / diff",
+            "hundred: 100",
+            "diff: humn - five",
+            "five: 5",
+            "right: 4",
+            "humn: 0",
+        ]);
+        assert_eq!(solve_human(&input), Ok(30));
+        // 5 - humn = 4 the other way round
+        let input = to_lines(&[
+            "root: four + diff",
+            "four: 4",
+            "diff: five - humn",
+            "five: 5",
+            "humn: 0",
+        ]);
+        assert_eq!(solve_human(&input), Ok(1));
+    }
+
+    #[test]
+    fn test_errors() {
+        let input = to_lines(&["root: humn + gone", "humn: 1"]);
+        assert_eq!(
+            solve_human(&input),
+            Err(MonkeyError::MissingMonkey("gone".to_string()))
+        );
+        let input = to_lines(&["root: both + two", "both: humn * humn", "two: 2", "humn: 1"]);
+        assert_eq!(
+            solve_human(&input),
+            Err(MonkeyError::UnknownOnBothSides("both".to_string()))
+        );
+        let input = to_lines(&[
+            "root: zeroed + zero",
+            "zeroed: humn * zero",
+            "zero: 0",
+            "humn: 1",
+        ]);
+        assert_eq!(solve_human(&input), Err(MonkeyError::ManySolutions));
+        let input = to_lines(&[
+            "root: zeroed + one",
+            "zeroed: humn * zero",
+            "zero: 0",
+            "one: 1",
+            "humn: 1",
+        ]);
+        assert_eq!(solve_human(&input), Err(MonkeyError::NoSolution));
     }
 }
@@ -1,12 +1,12 @@
 extern crate filelib;
 
+use cyclelib::find_cycle_hashed;
 pub use filelib::load_no_blanks;
 use gridlib::{Direction, Grid, GridCoordinate, GridTraversable};
 use rustc_hash::FxHashSet;
 use std::collections::VecDeque;
 
-// Your position, and cycle time
-// cycle time = round % (height * width);
+// Your position, and where the round is in the blizzard cycle
 type CacheKey = (GridCoordinate, usize);
 type Cache = FxHashSet<CacheKey>;
 
@@ -16,7 +16,7 @@ enum ValleyTile {
     Solid,
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
 struct Blizzard {
     dir: Direction,
     coord: GridCoordinate,
@@ -142,32 +142,24 @@ fn bfs_through(
     // First solution, imagine blizzards aren't moving
     let mut cached = Cache::default();
     let start_turn = 0;
-    let blizzard_cycle_max = (g.get_width() - 1) * (g.get_height() - 1);
     let mut queue = VecDeque::default();
     queue.push_back((vec![], start, start_turn));
     let mut min_time = usize::MAX;
     // Seen stops memory from ballooning, by stoping redunant adds.
     let mut seen = Cache::default();
 
-    let mut cached_blizzard_cycles = vec![];
-    let mut last_blizzard_cycle = blizzards.clone();
-    let mut first_coord_cache: FxHashSet<GridCoordinate> = FxHashSet::default();
-    for cycle in last_blizzard_cycle.iter() {
-        first_coord_cache.insert(cycle.coord);
-    }
-    cached_blizzard_cycles.push(first_coord_cache);
-    for _ in 1..blizzard_cycle_max {
-        let new_cycle: Vec<Blizzard> = last_blizzard_cycle
+    // Blizzards wrap around, so their positions eventually repeat.
+    let (cycle, blizzard_states) = find_cycle_hashed(blizzards, |last_blizzard_cycle| {
+        return last_blizzard_cycle
             .iter()
             .map(|b| b.step_clone(g))
-            .collect();
-        let mut coord_cache: FxHashSet<GridCoordinate> = FxHashSet::default();
-        for cycle in new_cycle.iter() {
-            coord_cache.insert(cycle.coord);
-        }
-        cached_blizzard_cycles.push(coord_cache);
-        last_blizzard_cycle = new_cycle;
-    }
+            .collect::<Vec<Blizzard>>();
+    });
+    let cached_blizzard_cycles: Vec<FxHashSet<GridCoordinate>> = blizzard_states
+        .iter()
+        .map(|state| state.iter().map(|b| b.coord).collect())
+        .collect();
+    let blizzard_cycle_spot = |turn: usize| cycle.equivalent_step(turn);
 
     let dirs = vec![
         Direction::NORTH,
@@ -183,7 +175,7 @@ fn bfs_through(
             break;
         }
 
-        let cycle_spot = cur_turn % blizzard_cycle_max;
+        let cycle_spot = blizzard_cycle_spot(cur_turn);
         if cached.contains(&(cur_loc, cycle_spot)) {
             // We have already seen this exact state
             // and if we have already been here and this is likely a later spot thats equivalent.
@@ -204,7 +196,7 @@ fn bfs_through(
         cached.insert((cur_loc, cycle_spot));
 
         // try moving
-        let new_blizzards = &cached_blizzard_cycles[(cur_turn + 1) % blizzard_cycle_max];
+        let new_blizzards = &cached_blizzard_cycles[blizzard_cycle_spot(cur_turn + 1)];
         let mut new_path = cur_path.clone();
         new_path.push(cur_loc);
         for possible_dir in &dirs {
@@ -223,7 +215,7 @@ fn bfs_through(
                     //println!("Thrown out for having a blizzard");
                     continue;
                 }
-                let seen_key = (new_coord, (cur_turn + 1) % blizzard_cycle_max);
+                let seen_key = (new_coord, blizzard_cycle_spot(cur_turn + 1));
                 if seen.contains(&seen_key) {
                     continue;
                 };
@@ -1,59 +1,36 @@
 extern crate filelib;
+extern crate mathlib;
 
 pub use filelib::load_no_blanks;
 
+use mathlib::numeral::Numeral;
+
 // Set a type alias in case I run out of space
-type ISnafu = i128;
+pub type ISnafu = i128;
 
 // Snafu is: Powers of 5, right to left, with some special symbols
 // digits however, are 2, 1, 0, minus, double minus (=). So 8 is two 5s, then minus two, so 2=.
 
-fn from_snafu(s: &Vec<String>) -> Vec<ISnafu> {
-    let mut ans = vec![];
-    let base: ISnafu = 5;
-    for line in s {
-        let highest_power = line.len() - 1;
-        let mut cur_number: ISnafu = 0;
-        // 2=
-        // 5^1 * 2 + double minus * -2
-        for (x, c) in line.chars().enumerate() {
-            let digit = match c {
-                '2' => 2,
-                '1' => 1,
-                '0' => 0,
-                '-' => -1,
-                '=' => -2,
-                _ => panic!("Bad input"),
-            };
-            let power: ISnafu = base.pow((highest_power - x) as u32);
-            cur_number += digit * power;
-        }
-        ans.push(cur_number);
-    }
-    return ans;
+/// Read each line as a SNAFU number
+/// ```
+/// let vec1: Vec<String> = vec!["2=-01", "1="].iter().map(|s| s.to_string()).collect();
+/// assert_eq!(day25::from_snafu(&vec1), vec![976, 3]);
+/// ```
+pub fn from_snafu(s: &Vec<String>) -> Vec<ISnafu> {
+    let snafu = Numeral::snafu();
+    return s
+        .iter()
+        .map(|line| snafu.parse::<ISnafu>(line).expect("Bad input"))
+        .collect();
 }
 
-fn to_snafu(u: ISnafu) -> String {
-    let mut n = "".to_string();
-
-    // to get a digit:
-    // (number + 2) % 5 then subtract 2 from result.
-    let mut cur_number = u;
-    while cur_number > 0 {
-        let i = (cur_number + 2) % 5 - 2;
-        let s = match i {
-            0 => '0',
-            1 => '1',
-            2 => '2',
-            -1 => '-',
-            -2 => '=',
-            _ => panic!("Digit to snafu got an unexpected digit"),
-        };
-        n.push(s);
-        cur_number = (cur_number - i) / 5;
-    }
-
-    return n.chars().rev().collect();
+/// Write a number as SNAFU
+/// ```
+/// assert_eq!(day25::to_snafu(976), "2=-01");
+/// assert_eq!(day25::to_snafu(0), "0");
+/// ```
+pub fn to_snafu(u: ISnafu) -> String {
+    return Numeral::snafu().format(u).unwrap();
 }
 
 /// Solution to puzzle_a entry point
@@ -62,9 +39,8 @@ fn to_snafu(u: ISnafu) -> String {
 /// assert_eq!(day25::puzzle_a(&vec1), "2=-1=0");
 /// ```
 pub fn puzzle_a(input: &Vec<String>) -> String {
-    let parsed = from_snafu(input);
-    let sum = parsed.iter().sum();
-    return to_snafu(sum);
+    // No need to convert, just add the digits up directly.
+    return Numeral::snafu().sum(input).expect("Bad input");
 }
 
 /// Solution to puzzle_b entry point
@@ -91,4 +67,20 @@ mod tests {
         let input = 976;
         assert_eq!(to_snafu(input), "2=-01");
     }
+
+    #[test]
+    fn test_to_snafu_zero_and_negative() {
+        assert_eq!(to_snafu(0), "0");
+        assert_eq!(to_snafu(-976), "=210-");
+    }
+
+    #[test]
+    fn test_sum_matches_integers() {
+        let input: Vec<String> = vec!["1=-0-2", "12111", "2=0=", "21", "2=01"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let total: ISnafu = from_snafu(&input).iter().sum();
+        assert_eq!(puzzle_a(&input), to_snafu(total));
+    }
 }
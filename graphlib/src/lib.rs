@@ -0,0 +1,260 @@
+use rustc_hash::FxHashMap;
+use std::hash::Hash;
+
+/// Directed graph with a cost on every edge, where nodes are looked up by their name.
+///
+/// Nodes are also given an index in the order they were added, which is what the
+/// distance tables use.
+#[derive(Debug, Clone)]
+pub struct WeightedGraph<N> {
+    nodes: Vec<N>,
+    index: FxHashMap<N, usize>,
+    edges: Vec<Vec<(usize, usize)>>,
+}
+
+impl<N: Hash + Eq + Clone> Default for WeightedGraph<N> {
+    fn default() -> Self {
+        return WeightedGraph::new();
+    }
+}
+
+impl<N: Hash + Eq + Clone> WeightedGraph<N> {
+    pub fn new() -> WeightedGraph<N> {
+        return WeightedGraph {
+            nodes: vec![],
+            index: FxHashMap::default(),
+            edges: vec![],
+        };
+    }
+
+    /// Index of the node, adding it if it isn't there yet.
+    pub fn add_node(&mut self, node: N) -> usize {
+        if let Some(i) = self.index.get(&node) {
+            return *i;
+        }
+        let i = self.nodes.len();
+        self.index.insert(node.clone(), i);
+        self.nodes.push(node);
+        self.edges.push(vec![]);
+        return i;
+    }
+
+    /// Add a one way edge, adding either node if needed.
+    pub fn add_edge(&mut self, from: N, to: N, cost: usize) {
+        let from = self.add_node(from);
+        let to = self.add_node(to);
+        self.edges[from].push((to, cost));
+    }
+
+    /// Add edges both ways with the same cost.
+    pub fn add_undirected_edge(&mut self, a: N, b: N, cost: usize) {
+        self.add_edge(a.clone(), b.clone(), cost);
+        self.add_edge(b, a, cost);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.nodes.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.nodes.is_empty();
+    }
+
+    pub fn index_of(&self, node: &N) -> Option<usize> {
+        return self.index.get(node).copied();
+    }
+
+    pub fn node(&self, index: usize) -> &N {
+        return &self.nodes[index];
+    }
+
+    pub fn nodes(&self) -> &[N] {
+        return &self.nodes;
+    }
+
+    /// (index, cost) of every edge leaving the node.
+    pub fn edges_from(&self, index: usize) -> &[(usize, usize)] {
+        return &self.edges[index];
+    }
+
+    /// Shortest distance between every pair of nodes, using Floyd–Warshall.
+    ///
+    /// `None` where there is no way to get from one to the other.
+    /// ```
+    /// let mut graph = graphlib::WeightedGraph::new();
+    /// graph.add_edge("a", "b", 4);
+    /// graph.add_edge("b", "c", 1);
+    /// graph.add_edge("a", "c", 7);
+    /// let distances = graph.all_distances();
+    /// assert_eq!(distances[0][2], Some(5));
+    /// assert_eq!(distances[2][0], None);
+    /// ```
+    pub fn all_distances(&self) -> Vec<Vec<Option<usize>>> {
+        let n = self.nodes.len();
+        let mut dist = vec![vec![None; n]; n];
+        for (from, edges) in self.edges.iter().enumerate() {
+            dist[from][from] = Some(0);
+            for (to, cost) in edges {
+                if dist[from][*to].is_none_or(|d| *cost < d) {
+                    dist[from][*to] = Some(*cost);
+                }
+            }
+        }
+        for via in 0..n {
+            let via_row = dist[via].clone();
+            for row in dist.iter_mut() {
+                let to_via = match row[via] {
+                    Some(d) => d,
+                    None => continue,
+                };
+                for (to, from_via) in via_row.iter().enumerate() {
+                    if let Some(from_via) = from_via {
+                        let through = to_via + from_via;
+                        if row[to].is_none_or(|d| through < d) {
+                            row[to] = Some(through);
+                        }
+                    }
+                }
+            }
+        }
+        return dist;
+    }
+
+    /// Shrink the graph down to just the nodes that matter.
+    ///
+    /// Keeps every node `keep` says yes to, in the same order, with an edge between each
+    /// pair costing the shortest distance between them in the full graph.
+    /// ```
+    /// let mut graph = graphlib::WeightedGraph::new();
+    /// graph.add_undirected_edge("a", "hall", 1);
+    /// graph.add_undirected_edge("hall", "b", 1);
+    /// let small = graph.compress(|n| *n != "hall");
+    /// assert_eq!(small.nodes(), ["a", "b"]);
+    /// assert_eq!(small.edges_from(0), [(1, 2)]);
+    /// ```
+    pub fn compress<F>(&self, keep: F) -> WeightedGraph<N>
+    where
+        F: Fn(&N) -> bool,
+    {
+        let dist = self.all_distances();
+        let kept: Vec<usize> = (0..self.nodes.len())
+            .filter(|i| keep(&self.nodes[*i]))
+            .collect();
+        let mut compressed = WeightedGraph::new();
+        for i in &kept {
+            compressed.add_node(self.nodes[*i].clone());
+        }
+        for (from_new, from) in kept.iter().enumerate() {
+            for (to_new, to) in kept.iter().enumerate() {
+                if from_new == to_new {
+                    continue;
+                }
+                if let Some(d) = dist[*from][*to] {
+                    compressed.edges[from_new].push((to_new, d));
+                }
+            }
+        }
+        return compressed;
+    }
+
+    /// Distance table for a graph where every node has at most one edge to each other node,
+    /// such as one from `compress`.
+    pub fn distance_table(&self) -> Vec<Vec<Option<usize>>> {
+        let n = self.nodes.len();
+        let mut dist = vec![vec![None; n]; n];
+        for (from, edges) in self.edges.iter().enumerate() {
+            dist[from][from] = Some(0);
+            for (to, cost) in edges {
+                dist[from][*to] = Some(*cost);
+            }
+        }
+        return dist;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Slow but obviously right: relax every edge n times.
+    fn bellman_ford(graph: &WeightedGraph<usize>, from: usize) -> Vec<Option<usize>> {
+        let mut dist = vec![None; graph.len()];
+        dist[from] = Some(0);
+        for _ in 0..graph.len() {
+            for (node, edges) in graph.edges.iter().enumerate() {
+                let d = match dist[node] {
+                    Some(d) => d,
+                    None => continue,
+                };
+                for (to, cost) in edges {
+                    if dist[*to].is_none_or(|old| d + cost < old) {
+                        dist[*to] = Some(d + cost);
+                    }
+                }
+            }
+        }
+        return dist;
+    }
+
+    fn pseudo_random_graph(seed: u64, nodes: usize, edges: usize) -> WeightedGraph<usize> {
+        let mut state = seed;
+        let mut next = |limit: usize| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            return (state >> 33) as usize % limit;
+        };
+        let mut graph = WeightedGraph::new();
+        for i in 0..nodes {
+            graph.add_node(i);
+        }
+        for _ in 0..edges {
+            let from = next(nodes);
+            let to = next(nodes);
+            let cost = next(10);
+            graph.add_edge(from, to, cost);
+        }
+        return graph;
+    }
+
+    #[test]
+    fn test_add_node_once() {
+        let mut graph = WeightedGraph::new();
+        assert!(graph.is_empty());
+        assert_eq!(graph.add_node("x"), 0);
+        assert_eq!(graph.add_node("y"), 1);
+        assert_eq!(graph.add_node("x"), 0);
+        assert_eq!(graph.len(), 2);
+        assert_eq!(graph.index_of(&"y"), Some(1));
+        assert_eq!(graph.index_of(&"z"), None);
+        assert_eq!(*graph.node(1), "y");
+    }
+
+    #[test]
+    fn test_distances_match_bellman_ford() {
+        for seed in 0..20 {
+            let graph = pseudo_random_graph(seed, 12, 30);
+            let dist = graph.all_distances();
+            for (from, row) in dist.iter().enumerate() {
+                assert_eq!(*row, bellman_ford(&graph, from));
+            }
+        }
+    }
+
+    #[test]
+    fn test_compress_keeps_distances() {
+        for seed in 0..20 {
+            let graph = pseudo_random_graph(seed, 12, 30);
+            let full = graph.all_distances();
+            let small = graph.compress(|n| n % 3 == 0);
+            assert_eq!(small.nodes(), [0, 3, 6, 9]);
+            // Already shortest, so going through other kept nodes can't help.
+            assert_eq!(small.distance_table(), small.all_distances());
+            for (i, from) in small.nodes().iter().enumerate() {
+                for (j, to) in small.nodes().iter().enumerate() {
+                    assert_eq!(small.distance_table()[i][j], full[*from][*to]);
+                }
+            }
+        }
+    }
+}
@@ -216,7 +216,7 @@ impl Iterator for GridIter {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum Direction {
     NORTH,
     EAST,
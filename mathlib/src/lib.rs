@@ -1,14 +1,234 @@
+use num_traits::{PrimInt, Signed};
+
+pub mod numeral;
+
 // modulus function.
-pub fn modulus(a: i32, b: i32) -> i32 {
-    // % is actually the remainder function, not the modulus function
-    // This is the workaround way to "fix" this.
-    return ((a % b) + b) % b;
+//
+// % is actually the remainder function, not the modulus function
+// so the result here always has the same sign as b.
+pub fn modulus<T: PrimInt>(a: T, b: T) -> T {
+    let r = a % b;
+    if r != T::zero() && ((r < T::zero()) != (b < T::zero())) {
+        return r + b;
+    }
+    return r;
 }
 
 pub fn modulusi64(a: i64, b: i64) -> i64 {
-    // % is actually the remainder function, not the modulus function
-    // This is the workaround way to "fix" this.
-    return ((a % b) + b) % b;
+    return modulus(a, b);
+}
+
+fn abs<T: PrimInt>(a: T) -> T {
+    if a < T::zero() {
+        return T::zero() - a;
+    }
+    return a;
+}
+
+/// Greatest common divisor, always positive (or 0 if both are 0).
+/// ```
+/// assert_eq!(mathlib::gcd(12, 18), 6);
+/// assert_eq!(mathlib::gcd(-4i64, 6), 2);
+/// assert_eq!(mathlib::gcd(0u8, 0), 0);
+/// ```
+pub fn gcd<T: PrimInt>(a: T, b: T) -> T {
+    let mut a = abs(a);
+    let mut b = abs(b);
+    while b != T::zero() {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    return a;
+}
+
+/// Lowest common multiple, always positive (or 0 if either is 0).
+///
+/// Overflows like ordinary arithmetic if the answer doesn't fit in `T`, panicking in
+/// debug builds and wrapping in release, so use `checked_lcm` if it might not.
+/// ```
+/// assert_eq!(mathlib::lcm(4, 6), 12);
+/// assert_eq!(mathlib::lcm(-3i32, 5), 15);
+/// ```
+pub fn lcm<T: PrimInt>(a: T, b: T) -> T {
+    if a == T::zero() || b == T::zero() {
+        return T::zero();
+    }
+    return abs(a / gcd(a, b) * b);
+}
+
+/// Lowest common multiple, or None if it doesn't fit in `T`.
+/// ```
+/// assert_eq!(mathlib::checked_lcm(4u8, 6), Some(12));
+/// assert_eq!(mathlib::checked_lcm(16u8, 17), None);
+/// ```
+pub fn checked_lcm<T: PrimInt>(a: T, b: T) -> Option<T> {
+    if a == T::zero() || b == T::zero() {
+        return Some(T::zero());
+    }
+    // The answer is a multiple of -MIN, which never fits.
+    if T::min_value() < T::zero() && (a == T::min_value() || b == T::min_value()) {
+        return None;
+    }
+    let multiple = (a / gcd(a, b)).checked_mul(&b)?;
+    if multiple < T::zero() {
+        return T::zero().checked_sub(&multiple);
+    }
+    return Some(multiple);
+}
+
+/// Greatest common divisor of every number, 0 for no numbers.
+/// ```
+/// assert_eq!(mathlib::gcd_all(vec![12u64, 18, 27]), 3);
+/// ```
+pub fn gcd_all<T: PrimInt, I: IntoIterator<Item = T>>(values: I) -> T {
+    return values.into_iter().fold(T::zero(), gcd);
+}
+
+/// Lowest common multiple of every number, 1 for no numbers.
+/// ```
+/// assert_eq!(mathlib::lcm_all(vec![23u64, 19, 13, 17]), 96577);
+/// assert_eq!(mathlib::lcm_all(vec![4, 6, 10]), 60);
+/// ```
+pub fn lcm_all<T: PrimInt, I: IntoIterator<Item = T>>(values: I) -> T {
+    return values.into_iter().fold(T::one(), lcm);
+}
+
+/// Lowest common multiple of every number, or None if it doesn't fit in `T`.
+/// ```
+/// assert_eq!(mathlib::checked_lcm_all(vec![23u64, 19, 13, 17]), Some(96577));
+/// assert_eq!(mathlib::checked_lcm_all(1u32..=30), None);
+/// ```
+pub fn checked_lcm_all<T: PrimInt, I: IntoIterator<Item = T>>(values: I) -> Option<T> {
+    return values
+        .into_iter()
+        .try_fold(T::one(), |acc, v| checked_lcm(acc, v));
+}
+
+/// Extended Euclidean algorithm.
+///
+/// Returns (g, x, y) where g is gcd(a, b) and a * x + b * y = g.
+/// ```
+/// let (g, x, y) = mathlib::extended_gcd(240, 46);
+/// assert_eq!(g, 2);
+/// assert_eq!(240 * x + 46 * y, 2);
+/// ```
+pub fn extended_gcd<T: PrimInt + Signed>(a: T, b: T) -> (T, T, T) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_s, mut s) = (T::one(), T::zero());
+    let (mut old_t, mut t) = (T::zero(), T::one());
+    while r != T::zero() {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+        (old_t, t) = (t, old_t - q * t);
+    }
+    if old_r < T::zero() {
+        return (-old_r, -old_s, -old_t);
+    }
+    return (old_r, old_s, old_t);
+}
+
+/// x such that a * x = 1 (mod m), if there is one.
+/// ```
+/// assert_eq!(mathlib::mod_inverse(3, 11), Some(4));
+/// assert_eq!(mathlib::mod_inverse(4, 8), None);
+/// ```
+pub fn mod_inverse<T: PrimInt + Signed>(a: T, m: T) -> Option<T> {
+    let (g, x, _) = extended_gcd(modulus(a, m), m);
+    if g != T::one() {
+        return None;
+    }
+    return Some(modulus(x, m));
+}
+
+// a * b % m without overflowing, a and b are already less than m.
+fn mul_mod(a: u128, b: u128, m: u128) -> u128 {
+    if let Some(v) = a.checked_mul(b) {
+        return v % m;
+    }
+    // Too big, fall back to double and add.
+    let mut result = 0;
+    let mut a = a;
+    let mut b = b;
+    while b > 0 {
+        if b & 1 == 1 {
+            result = add_mod(result, a, m);
+        }
+        a = add_mod(a, a, m);
+        b >>= 1;
+    }
+    return result;
+}
+
+fn add_mod(a: u128, b: u128, m: u128) -> u128 {
+    if a >= m - b {
+        return a - (m - b);
+    }
+    return a + b;
+}
+
+/// base ^ exp (mod m), for a positive m.
+///
+/// Does the work in 128 bits, so it doesn't overflow for any integer width.
+/// ```
+/// assert_eq!(mathlib::mod_pow(4, 13, 497), 445);
+/// assert_eq!(mathlib::mod_pow(-2i64, 3, 5), 2);
+/// assert_eq!(mathlib::mod_pow(u64::MAX - 1, u64::MAX, u64::MAX), u64::MAX - 1);
+/// ```
+pub fn mod_pow<T: PrimInt>(base: T, exp: T, m: T) -> T {
+    assert!(m > T::zero(), "mod_pow needs a positive modulus");
+    let m_wide = m.to_u128().unwrap();
+    let mut b = modulus(base, m).to_u128().unwrap();
+    let mut e = exp
+        .to_u128()
+        .expect("mod_pow needs a non-negative exponent");
+    let mut result = 1 % m_wide;
+    while e > 0 {
+        if e & 1 == 1 {
+            result = mul_mod(result, b, m_wide);
+        }
+        b = mul_mod(b, b, m_wide);
+        e >>= 1;
+    }
+    return T::from(result).unwrap();
+}
+
+/// Chinese Remainder Theorem.
+///
+/// Given pairs of (remainder, modulus), finds the smallest non-negative x with
+/// x = remainder (mod modulus) for every pair, and returns (x, lcm of the moduli).
+/// The moduli don't need to be coprime, returns `None` if the pairs disagree,
+/// a modulus is 0 or the answer doesn't fit in T.
+/// ```
+/// assert_eq!(mathlib::chinese_remainder(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+/// assert_eq!(mathlib::chinese_remainder(&[(1, 4), (3, 6)]), Some((9, 12)));
+/// assert_eq!(mathlib::chinese_remainder(&[(1, 4), (2, 6)]), None);
+/// assert_eq!(mathlib::chinese_remainder(&[(1, 0)]), None);
+/// assert_eq!(mathlib::chinese_remainder(&[(2, 3), (1, 0)]), None);
+/// ```
+pub fn chinese_remainder<T: PrimInt + Signed>(pairs: &[(T, T)]) -> Option<(T, T)> {
+    let mut x: i128 = 0;
+    let mut m: i128 = 1;
+    for (r, n) in pairs {
+        let r = r.to_i128()?;
+        let n = abs(n.to_i128()?);
+        if n == 0 {
+            return None;
+        }
+        // Solve x + m * k = r (mod n) for k
+        let (g, p, _) = extended_gcd(m, n);
+        let diff = r - x;
+        if diff % g != 0 {
+            return None;
+        }
+        let step = n / g;
+        let k = modulus((diff / g) % step * (p % step), step);
+        let new_m = m.checked_mul(step)?;
+        x = modulus(x.checked_add(m.checked_mul(k)?)?, new_m);
+        m = new_m;
+    }
+    return Some((T::from(x)?, T::from(m)?));
 }
 
 #[cfg(test)]
@@ -32,4 +252,109 @@ mod tests {
         let result = modulusi64(120i64, 3i64);
         assert_eq!(result, 0);
     }
+
+    #[test]
+    fn modulus_all_widths() {
+        assert_eq!(modulus(-7i8, 3), 2);
+        assert_eq!(modulus(-7i16, 3), 2);
+        assert_eq!(modulus(-7i128, 3), 2);
+        assert_eq!(modulus(7i64, -3), -2);
+        assert_eq!(modulus(250u8, 7), 5);
+        assert_eq!(modulus(u64::MAX, u64::MAX - 1), 1);
+        // No overflow, unlike ((a % b) + b) % b
+        assert_eq!(modulus(-1i32, i32::MAX), i32::MAX - 1);
+    }
+
+    #[test]
+    fn modulus_matches_rem_euclid() {
+        for a in -20i64..20 {
+            for b in 1i64..7 {
+                assert_eq!(modulus(a, b), a.rem_euclid(b));
+            }
+        }
+    }
+
+    #[test]
+    fn gcd_lcm_edges() {
+        assert_eq!(gcd(0, 5), 5);
+        assert_eq!(gcd(-5, 0), 5);
+        assert_eq!(lcm(0, 5), 0);
+        assert_eq!(gcd_all(Vec::<u32>::new()), 0);
+        assert_eq!(lcm_all(Vec::<u32>::new()), 1);
+        assert_eq!(lcm_all(1u64..=20), 232792560);
+    }
+
+    #[test]
+    fn checked_lcm_limits() {
+        assert_eq!(checked_lcm(0i32, 5), Some(0));
+        assert_eq!(checked_lcm(-3i32, 5), Some(15));
+        assert_eq!(checked_lcm(i8::MIN, 1), None);
+        assert_eq!(checked_lcm(u64::MAX, 1), Some(u64::MAX));
+        assert_eq!(checked_lcm(u64::MAX, 2), None);
+        assert_eq!(checked_lcm_all(1u64..=46), Some(lcm_all(1u64..=46)));
+        assert_eq!(checked_lcm_all(1u64..=47), None);
+        for a in 1u8..=255 {
+            for b in 1u8..=255 {
+                let wide = lcm(a as u32, b as u32);
+                assert_eq!(checked_lcm(a, b), u8::try_from(wide).ok());
+            }
+        }
+    }
+
+    #[test]
+    fn extended_gcd_identity() {
+        for a in -30i64..30 {
+            for b in -30i64..30 {
+                let (g, x, y) = extended_gcd(a, b);
+                assert_eq!(g, gcd(a, b));
+                assert_eq!(a * x + b * y, g);
+            }
+        }
+    }
+
+    #[test]
+    fn mod_inverse_all() {
+        for a in 1i32..13 {
+            let inv = mod_inverse(a, 13).unwrap();
+            assert_eq!(modulus(a * inv, 13), 1);
+        }
+        assert_eq!(mod_inverse(-3, 11), Some(7));
+    }
+
+    #[test]
+    fn mod_pow_matches_pow() {
+        for b in 0u64..10 {
+            for e in 0u64..10 {
+                assert_eq!(mod_pow(b, e, 1009), b.pow(e as u32) % 1009);
+            }
+        }
+        assert_eq!(mod_pow(5u32, 0, 1), 0);
+        assert_eq!(mod_pow(3u128, 200, u128::MAX), {
+            let mut v: u128 = 1;
+            for _ in 0..200 {
+                v = mul_mod(v, 3, u128::MAX);
+            }
+            v
+        });
+    }
+
+    #[test]
+    fn chinese_remainder_brute_force() {
+        let moduli = [4i64, 6, 9];
+        for a in 0..4 {
+            for b in 0..6 {
+                for c in 0..9 {
+                    let brute = (0..36).find(|x| x % 4 == a && x % 6 == b && x % 9 == c);
+                    let pairs = [(a, moduli[0]), (b, moduli[1]), (c, moduli[2])];
+                    assert_eq!(chinese_remainder(&pairs), brute.map(|x| (x, 36)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn chinese_remainder_overflow() {
+        assert_eq!(chinese_remainder(&[(1i8, 11), (2, 13)]), None);
+        assert_eq!(chinese_remainder::<i32>(&[]), Some((0, 1)));
+    }
 }
@@ -0,0 +1,490 @@
+use num_traits::PrimInt;
+use std::fmt::{Display, Formatter};
+
+const STANDARD_DIGITS: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NumeralError {
+    /// The alphabet can't be used, with the reason why.
+    BadAlphabet(String),
+    /// There were no digits to read.
+    Empty,
+    /// A character that isn't in the alphabet, and where it was.
+    BadDigit { position: usize, found: char },
+    /// The value doesn't fit in the integer type.
+    Overflow,
+    /// Standard numerals need a `-` sign for negative values, but this alphabet uses `-` as a digit.
+    NoNegatives,
+}
+
+impl Display for NumeralError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            NumeralError::BadAlphabet(reason) => write!(f, "bad alphabet: {}", reason),
+            NumeralError::Empty => write!(f, "no digits"),
+            NumeralError::BadDigit { position, found } => {
+                write!(f, "unexpected '{}' at position {}", found, position)
+            }
+            NumeralError::Overflow => write!(f, "value does not fit"),
+            NumeralError::NoNegatives => write!(f, "negative values can't be written"),
+        };
+    }
+}
+
+/// A positional number system.
+///
+/// The alphabet lists the digits from lowest to highest value, the first digit
+/// has the value `min_digit` and each one after is one more. So the base is the
+/// length of the alphabet.
+///
+/// A `min_digit` of 0 is a standard base, like decimal, where negative values
+/// get a leading `-`. A negative `min_digit` is a signed digit base, like SNAFU,
+/// where every integer can be written without a sign.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Numeral {
+    alphabet: Vec<char>,
+    min_digit: i64,
+}
+
+impl Numeral {
+    pub fn new(alphabet: &str, min_digit: i64) -> Result<Numeral, NumeralError> {
+        let chars: Vec<char> = alphabet.chars().collect();
+        if chars.len() < 2 {
+            return Err(NumeralError::BadAlphabet(
+                "needs at least two digits".to_string(),
+            ));
+        }
+        for (i, c) in chars.iter().enumerate() {
+            if chars[..i].contains(c) {
+                return Err(NumeralError::BadAlphabet(format!("'{}' is repeated", c)));
+            }
+        }
+        let max_digit = min_digit + chars.len() as i64 - 1;
+        if min_digit != 0 && (min_digit > 0 || max_digit <= 0) {
+            return Err(NumeralError::BadAlphabet(
+                "digits must start at 0, or have both negative and positive digits".to_string(),
+            ));
+        }
+        return Ok(Numeral {
+            alphabet: chars,
+            min_digit: min_digit,
+        });
+    }
+
+    /// Standard base, using 0-9 then a-z.
+    /// ```
+    /// let hex = mathlib::numeral::Numeral::standard(16).unwrap();
+    /// assert_eq!(hex.format(-255i32).unwrap(), "-ff");
+    /// ```
+    pub fn standard(base: usize) -> Result<Numeral, NumeralError> {
+        if base > STANDARD_DIGITS.len() {
+            return Err(NumeralError::BadAlphabet(format!(
+                "no default digits past base {}",
+                STANDARD_DIGITS.len()
+            )));
+        }
+        return Numeral::new(&STANDARD_DIGITS[..base], 0);
+    }
+
+    /// Balanced base, where the middle of an odd length alphabet is zero.
+    /// ```
+    /// let ternary = mathlib::numeral::Numeral::balanced("T01").unwrap();
+    /// assert_eq!(ternary.format(-5i32).unwrap(), "T11");
+    /// ```
+    pub fn balanced(alphabet: &str) -> Result<Numeral, NumeralError> {
+        let len = alphabet.chars().count() as i64;
+        if len % 2 == 0 {
+            return Err(NumeralError::BadAlphabet(
+                "balanced bases need an odd number of digits".to_string(),
+            ));
+        }
+        return Numeral::new(alphabet, -(len - 1) / 2);
+    }
+
+    /// Balanced base 5 from day 25, `=` is -2 and `-` is -1.
+    /// ```
+    /// let snafu = mathlib::numeral::Numeral::snafu();
+    /// assert_eq!(snafu.parse::<i64>("2=-01").unwrap(), 976);
+    /// assert_eq!(snafu.format(976i64).unwrap(), "2=-01");
+    /// ```
+    pub fn snafu() -> Numeral {
+        return Numeral::balanced("=-012").unwrap();
+    }
+
+    pub fn base(&self) -> i64 {
+        return self.alphabet.len() as i64;
+    }
+
+    fn max_digit(&self) -> i64 {
+        return self.min_digit + self.base() - 1;
+    }
+
+    fn is_standard(&self) -> bool {
+        return self.min_digit == 0;
+    }
+
+    fn digit_value(&self, c: char) -> Option<i64> {
+        let index = self.alphabet.iter().position(|d| *d == c)?;
+        return Some(index as i64 + self.min_digit);
+    }
+
+    fn digit_char(&self, v: i64) -> char {
+        return self.alphabet[(v - self.min_digit) as usize];
+    }
+
+    // Signed digit values, most significant first.
+    // Negative standard numerals have every digit negated.
+    fn read_digits(&self, s: &str) -> Result<Vec<i64>, NumeralError> {
+        let mut chars: Vec<(usize, char)> = s.chars().enumerate().collect();
+        let mut sign = 1;
+        if self.is_standard() && !self.alphabet.contains(&'-') {
+            if let Some((_, '-')) = chars.first() {
+                sign = -1;
+                chars.remove(0);
+            }
+        }
+        if chars.is_empty() {
+            return Err(NumeralError::Empty);
+        }
+        return chars
+            .iter()
+            .map(|(position, c)| match self.digit_value(*c) {
+                Some(v) => Ok(v * sign),
+                None => Err(NumeralError::BadDigit {
+                    position: *position,
+                    found: *c,
+                }),
+            })
+            .collect();
+    }
+
+    // Most significant first, no leading zeros, and as above for negatives.
+    fn write_digits(&self, digits: &[i64]) -> Result<String, NumeralError> {
+        let start = digits.iter().position(|d| *d != 0);
+        let digits = match start {
+            Some(i) => &digits[i..],
+            None => return Ok(self.digit_char(0).to_string()),
+        };
+        let mut s = String::new();
+        if digits[0] < 0 && self.is_standard() {
+            if self.alphabet.contains(&'-') {
+                return Err(NumeralError::NoNegatives);
+            }
+            s.push('-');
+            s.extend(digits.iter().map(|d| self.digit_char(-d)));
+        } else {
+            s.extend(digits.iter().map(|d| self.digit_char(*d)));
+        }
+        return Ok(s);
+    }
+
+    // Digit for the value, and how much to carry.
+    fn split_digit(&self, v: i64) -> (i64, i64) {
+        let base = self.base();
+        let mut d = v.rem_euclid(base);
+        let mut carry = v.div_euclid(base);
+        if d > self.max_digit() {
+            d -= base;
+            carry += 1;
+        }
+        return (d, carry);
+    }
+
+    // Turn digits of any size (least significant first) into proper digits (most significant first).
+    fn normalize(&self, mut raw: Vec<i64>) -> Vec<i64> {
+        let mut flip = false;
+        if self.is_standard() {
+            // With a leading minus, it's easier to work on the positive version
+            if let Some(top) = raw.iter().rev().find(|d| **d != 0) {
+                flip = *top < 0;
+            }
+            if flip {
+                raw.iter_mut().for_each(|d| *d = -*d);
+            }
+        }
+        let mut digits = vec![];
+        let mut carry = 0;
+        let mut i = 0;
+        while i < raw.len() || carry != 0 {
+            let (d, c) = self.split_digit(raw.get(i).unwrap_or(&0) + carry);
+            digits.push(d);
+            carry = c;
+            i += 1;
+        }
+        if flip {
+            digits.iter_mut().for_each(|d| *d = -*d);
+        }
+        digits.reverse();
+        return digits;
+    }
+
+    /// Read a number, failing if it doesn't fit in T.
+    /// ```
+    /// let decimal = mathlib::numeral::Numeral::standard(10).unwrap();
+    /// assert_eq!(decimal.parse::<i8>("-128"), Ok(-128));
+    /// assert_eq!(decimal.parse::<i8>("128"), Err(mathlib::numeral::NumeralError::Overflow));
+    /// ```
+    pub fn parse<T: PrimInt>(&self, s: &str) -> Result<T, NumeralError> {
+        let base = T::from(self.base()).ok_or(NumeralError::Overflow)?;
+        let mut acc = T::zero();
+        for d in self.read_digits(s)? {
+            // acc * base + d, but borrowing from acc when the signs differ so
+            // the middle step can't overflow when the answer doesn't.
+            let next = if d < 0 && acc > T::zero() {
+                let top = T::from(self.base() + d).ok_or(NumeralError::Overflow)?;
+                (acc - T::one())
+                    .checked_mul(&base)
+                    .and_then(|v| v.checked_add(&top))
+            } else if d > 0 && acc < T::zero() {
+                let bottom = T::from(self.base() - d).ok_or(NumeralError::Overflow)?;
+                (acc + T::one())
+                    .checked_mul(&base)
+                    .and_then(|v| v.checked_sub(&bottom))
+            } else if d < 0 {
+                let magnitude = T::from(-d).ok_or(NumeralError::Overflow)?;
+                acc.checked_mul(&base)
+                    .and_then(|v| v.checked_sub(&magnitude))
+            } else {
+                let magnitude = T::from(d).ok_or(NumeralError::Overflow)?;
+                acc.checked_mul(&base)
+                    .and_then(|v| v.checked_add(&magnitude))
+            };
+            acc = next.ok_or(NumeralError::Overflow)?;
+        }
+        return Ok(acc);
+    }
+
+    /// Write a number, including 0 and negatives.
+    /// ```
+    /// let snafu = mathlib::numeral::Numeral::snafu();
+    /// assert_eq!(snafu.format(0i32).unwrap(), "0");
+    /// assert_eq!(snafu.format(-8i32).unwrap(), "=2");
+    /// ```
+    pub fn format<T: PrimInt>(&self, value: T) -> Result<String, NumeralError> {
+        let base = T::from(self.base()).ok_or(NumeralError::Overflow)?;
+        let mut digits = vec![];
+        let mut v = value;
+        while v != T::zero() {
+            // Truncating division, then shift the digit into range.
+            let mut d = (v % base).to_i64().unwrap();
+            v = v / base;
+            if self.is_standard() && value < T::zero() {
+                // Leave negative, the sign is added at the end.
+            } else if d < self.min_digit {
+                d += self.base();
+                v = v - T::one();
+            } else if d > self.max_digit() {
+                d -= self.base();
+                v = v + T::one();
+            }
+            digits.push(d);
+        }
+        digits.reverse();
+        return self.write_digits(&digits);
+    }
+
+    /// Add two numerals digit by digit, without converting to an integer.
+    /// ```
+    /// let snafu = mathlib::numeral::Numeral::snafu();
+    /// assert_eq!(snafu.add("1=-0-2", "12111").unwrap(), "1-111=");
+    /// ```
+    pub fn add(&self, a: &str, b: &str) -> Result<String, NumeralError> {
+        return self.sum(vec![a, b]);
+    }
+
+    /// Subtract b from a digit by digit.
+    /// ```
+    /// let decimal = mathlib::numeral::Numeral::standard(10).unwrap();
+    /// assert_eq!(decimal.sub("12", "130").unwrap(), "-118");
+    /// ```
+    pub fn sub(&self, a: &str, b: &str) -> Result<String, NumeralError> {
+        let negated = self.neg(b)?;
+        return self.add(a, &negated);
+    }
+
+    /// Flip the sign of a numeral.
+    pub fn neg(&self, a: &str) -> Result<String, NumeralError> {
+        let digits: Vec<i64> = self.read_digits(a)?.iter().map(|d| -d).collect();
+        if self.is_standard() {
+            return self.write_digits(&digits);
+        }
+        // Signed digits may not be symmetric, so carry as needed.
+        return self.write_digits(&self.normalize(digits.into_iter().rev().collect()));
+    }
+
+    /// Add up any number of numerals digit by digit.
+    /// ```
+    /// let snafu = mathlib::numeral::Numeral::snafu();
+    /// assert_eq!(snafu.sum(vec!["1=", "122", "2=01"]).unwrap(), "20=1");
+    /// assert_eq!(snafu.sum(Vec::<&str>::new()).unwrap(), "0");
+    /// ```
+    pub fn sum<I, S>(&self, values: I) -> Result<String, NumeralError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut raw: Vec<i64> = vec![];
+        for value in values {
+            let digits = self.read_digits(value.as_ref())?;
+            for (i, d) in digits.iter().rev().enumerate() {
+                if i >= raw.len() {
+                    raw.push(0);
+                }
+                raw[i] += d;
+            }
+            // Keep the digits small, so this can't overflow for long lists.
+            let mut normal = self.normalize(raw);
+            normal.reverse();
+            raw = normal;
+        }
+        return self.write_digits(&self.normalize(raw));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small LCG, so the round trip tests cover a spread of values without extra dependencies.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> i64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            return (self.0 >> 1) as i64 * if self.0 & 1 == 1 { -1 } else { 1 };
+        }
+    }
+
+    fn all_numerals() -> Vec<Numeral> {
+        return vec![
+            Numeral::snafu(),
+            Numeral::standard(2).unwrap(),
+            Numeral::standard(10).unwrap(),
+            Numeral::standard(36).unwrap(),
+            Numeral::balanced("T01").unwrap(),
+            Numeral::balanced("abcdefghi").unwrap(),
+            // Lopsided signed digits: -1 to 4 in base 6
+            Numeral::new("z01234", -1).unwrap(),
+        ];
+    }
+
+    #[test]
+    fn test_bad_alphabets() {
+        assert!(Numeral::new("0", 0).is_err());
+        assert!(Numeral::new("001", 0).is_err());
+        assert!(Numeral::new("012", 1).is_err());
+        assert!(Numeral::new("012", -2).is_err());
+        assert!(Numeral::balanced("0123").is_err());
+        assert!(Numeral::standard(37).is_err());
+    }
+
+    #[test]
+    fn test_bad_digits() {
+        let snafu = Numeral::snafu();
+        assert_eq!(snafu.parse::<i64>(""), Err(NumeralError::Empty));
+        assert_eq!(
+            snafu.parse::<i64>("12x"),
+            Err(NumeralError::BadDigit {
+                position: 2,
+                found: 'x'
+            })
+        );
+        let decimal = Numeral::standard(10).unwrap();
+        assert_eq!(decimal.parse::<i64>("-"), Err(NumeralError::Empty));
+        assert_eq!(decimal.parse::<u32>("-1"), Err(NumeralError::Overflow));
+    }
+
+    #[test]
+    fn test_no_negatives() {
+        let dashes = Numeral::new("-+", 0).unwrap();
+        assert_eq!(dashes.format(2i32).unwrap(), "+-");
+        assert_eq!(dashes.format(-2i32), Err(NumeralError::NoNegatives));
+    }
+
+    #[test]
+    fn test_snafu_examples() {
+        let snafu = Numeral::snafu();
+        let examples: Vec<(i64, &str)> = vec![
+            (1, "1"),
+            (2, "2"),
+            (3, "1="),
+            (4, "1-"),
+            (5, "10"),
+            (10, "20"),
+            (15, "1=0"),
+            (20, "1-0"),
+            (2022, "1=11-2"),
+            (12345, "1-0---0"),
+            (314159265, "1121-1110-1=0"),
+        ];
+        for (v, s) in examples {
+            assert_eq!(snafu.format(v).unwrap(), s);
+            assert_eq!(snafu.parse::<i64>(s).unwrap(), v);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_extremes() {
+        for numeral in all_numerals() {
+            for v in [i64::MIN, i64::MIN + 1, -1, 0, 1, i64::MAX - 1, i64::MAX] {
+                let s = numeral.format(v).unwrap();
+                assert_eq!(numeral.parse::<i64>(&s), Ok(v), "{:?} {}", numeral, s);
+            }
+            for v in [u8::MIN, u8::MAX] {
+                let s = numeral.format(v).unwrap();
+                assert_eq!(numeral.parse::<u8>(&s), Ok(v));
+            }
+            let too_big = numeral.format(i64::MAX).unwrap();
+            assert_eq!(numeral.parse::<i32>(&too_big), Err(NumeralError::Overflow));
+        }
+    }
+
+    #[test]
+    fn test_round_trip_random() {
+        let mut rng = Lcg(2022);
+        for numeral in all_numerals() {
+            for _ in 0..500 {
+                let v = rng.next();
+                let s = numeral.format(v).unwrap();
+                assert_eq!(numeral.parse::<i64>(&s), Ok(v));
+                // Small values too, which are more likely to hit carries near zero
+                let small = v % 1000;
+                let s = numeral.format(small).unwrap();
+                assert_eq!(numeral.parse::<i64>(&s), Ok(small));
+            }
+        }
+    }
+
+    #[test]
+    fn test_arithmetic_matches_integers() {
+        let mut rng = Lcg(25);
+        for numeral in all_numerals() {
+            for _ in 0..300 {
+                let a = rng.next() % 1_000_000_000;
+                let b = rng.next() % 1_000_000_000;
+                let sa = numeral.format(a).unwrap();
+                let sb = numeral.format(b).unwrap();
+                let sum = numeral.add(&sa, &sb).unwrap();
+                assert_eq!(numeral.parse::<i64>(&sum), Ok(a + b));
+                let diff = numeral.sub(&sa, &sb).unwrap();
+                assert_eq!(numeral.parse::<i64>(&diff), Ok(a - b));
+                assert_eq!(numeral.neg(&sa).unwrap(), numeral.format(-a).unwrap());
+            }
+            let one = numeral.format(1i32).unwrap();
+            assert_eq!(
+                numeral.sub(&one, &one).unwrap(),
+                numeral.format(0i32).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_sum_keeps_canonical_form() {
+        let decimal = Numeral::standard(10).unwrap();
+        assert_eq!(decimal.sum(vec!["999", "1", "-1000", "0"]).unwrap(), "0");
+        assert_eq!(decimal.add("-0", "007").unwrap(), "7");
+    }
+}
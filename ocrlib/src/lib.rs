@@ -0,0 +1,429 @@
+extern crate gridlib;
+
+use gridlib::{Grid, GridCoordinate};
+use std::fmt::{Display, Formatter};
+
+// The 4 wide, 6 tall letters drawn on the CRT and in other puzzles.
+const SMALL_GLYPHS: [(char, [&str; 6]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+// The 6 wide, 10 tall letters the stars line up into.
+const LARGE_GLYPHS: [(char, [&str; 10]); 15] = [
+    (
+        'A',
+        [
+            "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'B',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+            "#....#", "#####.",
+        ],
+    ),
+    (
+        'C',
+        [
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#....#", ".####.",
+        ],
+    ),
+    (
+        'E',
+        [
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'F',
+        [
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'G',
+        [
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+            "#...##", ".###.#",
+        ],
+    ),
+    (
+        'H',
+        [
+            "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'J',
+        [
+            "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+            "#...#.", ".###..",
+        ],
+    ),
+    (
+        'K',
+        [
+            "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+            "#...#.", "#....#",
+        ],
+    ),
+    (
+        'L',
+        [
+            "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'N',
+        [
+            "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+            "#...##", "#....#",
+        ],
+    ),
+    (
+        'P',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'R',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'X',
+        [
+            "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'Z',
+        [
+            "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+];
+
+/// A fixed width font, with a gap of `spacing` blank columns after every letter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Font {
+    pub width: usize,
+    pub height: usize,
+    pub spacing: usize,
+    glyphs: Vec<(char, Vec<bool>)>,
+}
+
+impl Font {
+    /// Build a font from letters drawn with `#` for lit and anything else for dark.
+    ///
+    /// Panics if a drawing isn't `width` by `height`.
+    pub fn new(width: usize, height: usize, spacing: usize, glyphs: &[(char, &[&str])]) -> Font {
+        let glyphs = glyphs
+            .iter()
+            .map(|(letter, rows)| {
+                let pixels: Vec<bool> = rows
+                    .iter()
+                    .flat_map(|r| r.chars().map(|c| c == '#'))
+                    .collect();
+                if rows.len() != height || pixels.len() != width * height {
+                    panic!(
+                        "Can't use the drawing of {} in a {}x{} font",
+                        letter, width, height
+                    );
+                }
+                return (*letter, pixels);
+            })
+            .collect();
+        return Font {
+            width: width,
+            height: height,
+            spacing: spacing,
+            glyphs: glyphs,
+        };
+    }
+
+    /// The 4x6 letters, one blank column apart.
+    pub fn small() -> Font {
+        let glyphs: Vec<(char, &[&str])> = SMALL_GLYPHS
+            .iter()
+            .map(|(c, rows)| (*c, &rows[..]))
+            .collect();
+        return Font::new(4, 6, 1, &glyphs);
+    }
+
+    /// The 6x10 letters, two blank columns apart.
+    pub fn large() -> Font {
+        let glyphs: Vec<(char, &[&str])> = LARGE_GLYPHS
+            .iter()
+            .map(|(c, rows)| (*c, &rows[..]))
+            .collect();
+        return Font::new(6, 10, 2, &glyphs);
+    }
+
+    /// The letter with exactly these pixels, row by row.
+    pub fn letter(&self, pixels: &[bool]) -> Option<char> {
+        return self
+            .glyphs
+            .iter()
+            .find(|(_, p)| p == pixels)
+            .map(|(c, _)| *c);
+    }
+
+    /// The pixels of a letter, row by row.
+    pub fn glyph(&self, letter: char) -> Option<&[bool]> {
+        return self
+            .glyphs
+            .iter()
+            .find(|(c, _)| *c == letter)
+            .map(|(_, p)| p.as_slice());
+    }
+
+    /// Every letter the font knows.
+    pub fn letters(&self) -> Vec<char> {
+        return self.glyphs.iter().map(|(c, _)| *c).collect();
+    }
+
+    // Columns from the start of one letter to the start of the next.
+    fn stride(&self) -> usize {
+        return self.width + self.spacing;
+    }
+}
+
+/// A letter cell that didn't match anything in the font.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownGlyph {
+    /// Which letter it was, counting from 0.
+    pub index: usize,
+    /// Top left corner of the cell in the grid.
+    pub position: GridCoordinate,
+    /// What it looked like, as `#` and `.` rows.
+    pub drawing: Vec<String>,
+}
+
+impl Display for UnknownGlyph {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "letter {} at {}:", self.index, self.position)?;
+        for row in &self.drawing {
+            writeln!(f, "{}", row)?;
+        }
+        return Ok(());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcrError {
+    /// The grid is shorter than the font, or narrower than one letter.
+    TooSmall { width: usize, height: usize },
+    /// Letters that couldn't be read, along with everything else as best as it could be read,
+    /// with `?` standing in for each unknown letter.
+    UnknownGlyphs {
+        partial: String,
+        unknown: Vec<UnknownGlyph>,
+    },
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            OcrError::TooSmall { width, height } => {
+                write!(
+                    f,
+                    "a {}x{} grid is too small to hold letters",
+                    width, height
+                )
+            }
+            OcrError::UnknownGlyphs { partial, unknown } => {
+                writeln!(f, "can't read every letter of '{}'", partial)?;
+                for glyph in unknown {
+                    write!(f, "{}", glyph)?;
+                }
+                Ok(())
+            }
+        };
+    }
+}
+
+/// Read the letters drawn along the top of a grid, starting in the top left corner.
+///
+/// Blank letter cells come out as spaces, except at the end, where they are dropped.
+/// ```
+/// let rows = ["#..#..###", "#..#...#.", "####...#.", "#..#...#.", "#..#...#.", "#..#..###"];
+/// let pixels: Vec<bool> = rows.iter().flat_map(|r| r.chars().map(|c| c == '#')).collect();
+/// let grid = gridlib::Grid::new(9, 6, pixels);
+/// assert_eq!(ocrlib::read_text(&grid, &ocrlib::Font::small()), Ok("HI".to_string()));
+/// ```
+pub fn read_text(grid: &Grid<bool>, font: &Font) -> Result<String, OcrError> {
+    let (width, height) = (grid.get_width(), grid.get_height());
+    if height < font.height || width < font.width {
+        return Err(OcrError::TooSmall {
+            width: width,
+            height: height,
+        });
+    }
+    let pixels = grid.data_copy();
+    let cells = (width - font.width) / font.stride() + 1;
+    let mut text = String::new();
+    let mut unknown = vec![];
+    for index in 0..cells {
+        let left = index * font.stride();
+        let cell: Vec<bool> = (0..font.height)
+            .flat_map(|y| (left..left + font.width).map(move |x| (x, y)))
+            .map(|(x, y)| pixels[x + y * width])
+            .collect();
+        if cell.iter().all(|lit| !lit) {
+            text.push(' ');
+            continue;
+        }
+        match font.letter(&cell) {
+            Some(letter) => text.push(letter),
+            None => {
+                text.push('?');
+                unknown.push(UnknownGlyph {
+                    index: index,
+                    position: GridCoordinate::new(left, 0),
+                    drawing: cell
+                        .chunks(font.width)
+                        .map(|row| row.iter().map(|lit| if *lit { '#' } else { '.' }).collect())
+                        .collect(),
+                });
+            }
+        }
+    }
+    let text = text.trim_end().to_string();
+    if !unknown.is_empty() {
+        return Err(OcrError::UnknownGlyphs {
+            partial: text,
+            unknown: unknown,
+        });
+    }
+    return Ok(text);
+}
+
+/// Read the letters using whichever font fits the height of the grid best.
+pub fn read_letters(grid: &Grid<bool>) -> Result<String, OcrError> {
+    let font = if grid.get_height() >= Font::large().height {
+        Font::large()
+    } else {
+        Font::small()
+    };
+    return read_text(grid, &font);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Draw the text in the font, with `extra` blank rows underneath.
+    fn draw(text: &str, font: &Font, extra: usize) -> Grid<bool> {
+        let letters: Vec<char> = text.chars().collect();
+        let width = letters.len() * font.stride() - font.spacing;
+        let height = font.height + extra;
+        let mut pixels = vec![false; width * height];
+        for (i, letter) in letters.iter().enumerate() {
+            let glyph = match font.glyph(*letter) {
+                Some(glyph) => glyph.to_vec(),
+                None => vec![false; font.width * font.height],
+            };
+            for (j, lit) in glyph.iter().enumerate() {
+                let x = i * font.stride() + j % font.width;
+                pixels[x + (j / font.width) * width] = *lit;
+            }
+        }
+        return Grid::new(width, height, pixels);
+    }
+
+    #[test]
+    fn test_every_letter_reads_back() {
+        for font in [Font::small(), Font::large()] {
+            let text: String = font.letters().iter().collect();
+            assert_eq!(read_text(&draw(&text, &font, 0), &font), Ok(text.clone()));
+            assert_eq!(read_letters(&draw(&text, &font, 2)), Ok(text));
+        }
+    }
+
+    #[test]
+    fn test_glyphs_are_distinct() {
+        for font in [Font::small(), Font::large()] {
+            for a in font.letters() {
+                let glyph = font.glyph(a).unwrap();
+                assert_eq!(font.letter(glyph), Some(a));
+            }
+        }
+    }
+
+    #[test]
+    fn test_spaces_and_trailing_blanks() {
+        let font = Font::small();
+        assert_eq!(
+            read_text(&draw("HI OK ", &font, 0), &font),
+            Ok("HI OK".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unknown_glyphs_are_reported() {
+        let font = Font::small();
+        let mut grid = draw("ABC", &font, 0);
+        gridlib::GridTraversable::set_value(&mut grid, GridCoordinate::new(8, 5), true);
+        let err = read_text(&grid, &font).unwrap_err();
+        let expected = UnknownGlyph {
+            index: 1,
+            position: GridCoordinate::new(5, 0),
+            drawing: ["###.", "#..#", "###.", "#..#", "#..#", "####"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        };
+        assert_eq!(
+            err,
+            OcrError::UnknownGlyphs {
+                partial: "A?C".to_string(),
+                unknown: vec![expected]
+            }
+        );
+        assert!(err
+            .to_string()
+            .starts_with("can't read every letter of 'A?C'\nletter 1 at"));
+    }
+
+    #[test]
+    fn test_too_small() {
+        let grid = Grid::new(3, 6, vec![false; 18]);
+        assert_eq!(
+            read_text(&grid, &Font::small()),
+            Err(OcrError::TooSmall {
+                width: 3,
+                height: 6
+            })
+        );
+    }
+}
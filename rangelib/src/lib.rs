@@ -0,0 +1,410 @@
+use num_traits::PrimInt;
+use std::fmt::{Display, Formatter};
+
+/// Inclusive range of integers, start <= end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interval<T: PrimInt> {
+    pub start: T,
+    pub end: T,
+}
+
+impl<T: PrimInt> Interval<T> {
+    pub fn new(start: T, end: T) -> Interval<T> {
+        assert!(start <= end, "Interval start must not be after its end");
+        return Interval {
+            start: start,
+            end: end,
+        };
+    }
+
+    /// Number of integers covered.
+    pub fn len(&self) -> T {
+        return self.end - self.start + T::one();
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        return self.start <= value && value <= self.end;
+    }
+
+    /// ```
+    /// use rangelib::Interval;
+    /// assert!(Interval::new(2, 8).contains_interval(&Interval::new(3, 7)));
+    /// assert!(!Interval::new(3, 7).contains_interval(&Interval::new(2, 8)));
+    /// ```
+    pub fn contains_interval(&self, other: &Interval<T>) -> bool {
+        return self.start <= other.start && other.end <= self.end;
+    }
+
+    /// ```
+    /// use rangelib::Interval;
+    /// assert!(Interval::new(5, 7).overlaps(&Interval::new(7, 9)));
+    /// assert!(!Interval::new(2, 4).overlaps(&Interval::new(6, 8)));
+    /// ```
+    pub fn overlaps(&self, other: &Interval<T>) -> bool {
+        return self.start <= other.end && other.start <= self.end;
+    }
+
+    pub fn intersection(&self, other: &Interval<T>) -> Option<Interval<T>> {
+        if !self.overlaps(other) {
+            return None;
+        }
+        return Some(Interval::new(
+            self.start.max(other.start),
+            self.end.min(other.end),
+        ));
+    }
+
+    // Overlapping, or right next to each other, so they can be one interval.
+    fn touches(&self, other: &Interval<T>) -> bool {
+        let after_self = self.end.checked_add(&T::one());
+        let after_other = other.end.checked_add(&T::one());
+        return after_self.is_none_or(|a| other.start <= a)
+            && after_other.is_none_or(|a| self.start <= a);
+    }
+}
+
+impl<T: PrimInt + Display> Display for Interval<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "{}-{}", self.start, self.end);
+    }
+}
+
+/// A set of integers, stored as sorted intervals.
+///
+/// Intervals are always merged, so no two intervals overlap or touch.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct RangeSet<T: PrimInt> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T: PrimInt> RangeSet<T> {
+    pub fn new() -> RangeSet<T> {
+        return RangeSet { intervals: vec![] };
+    }
+
+    /// Build from intervals in any order, merging as needed in O(n log n).
+    /// ```
+    /// use rangelib::{Interval, RangeSet};
+    /// let set = RangeSet::from_intervals(vec![Interval::new(5, 9), Interval::new(1, 3), Interval::new(4, 4)]);
+    /// assert_eq!(set.intervals(), &[Interval::new(1, 9)]);
+    /// ```
+    pub fn from_intervals<I: IntoIterator<Item = Interval<T>>>(intervals: I) -> RangeSet<T> {
+        let mut sorted: Vec<Interval<T>> = intervals.into_iter().collect();
+        sorted.sort();
+        let mut merged: Vec<Interval<T>> = Vec::with_capacity(sorted.len());
+        for interval in sorted {
+            match merged.last_mut() {
+                Some(last) if last.touches(&interval) => {
+                    last.end = last.end.max(interval.end);
+                }
+                _ => merged.push(interval),
+            }
+        }
+        return RangeSet { intervals: merged };
+    }
+
+    pub fn intervals(&self) -> &[Interval<T>] {
+        return &self.intervals;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.intervals.is_empty();
+    }
+
+    pub fn min(&self) -> Option<T> {
+        return self.intervals.first().map(|i| i.start);
+    }
+
+    pub fn max(&self) -> Option<T> {
+        return self.intervals.last().map(|i| i.end);
+    }
+
+    /// Total number of integers in the set.
+    pub fn len(&self) -> T {
+        return self
+            .intervals
+            .iter()
+            .fold(T::zero(), |total, i| total + i.len());
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let index = self.intervals.partition_point(|i| i.end < value);
+        return index < self.intervals.len() && self.intervals[index].contains(value);
+    }
+
+    pub fn contains_interval(&self, interval: &Interval<T>) -> bool {
+        let index = self.intervals.partition_point(|i| i.end < interval.start);
+        return index < self.intervals.len() && self.intervals[index].contains_interval(interval);
+    }
+
+    pub fn overlaps(&self, interval: &Interval<T>) -> bool {
+        let index = self.intervals.partition_point(|i| i.end < interval.start);
+        return index < self.intervals.len() && self.intervals[index].overlaps(interval);
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        // Everything that touches the new interval is in one run, so replace that run.
+        let first = self
+            .intervals
+            .partition_point(|i| !i.touches(&interval) && i.end < interval.start);
+        let mut merged = interval;
+        let mut last = first;
+        while last < self.intervals.len() && self.intervals[last].touches(&interval) {
+            merged.start = merged.start.min(self.intervals[last].start);
+            merged.end = merged.end.max(self.intervals[last].end);
+            last += 1;
+        }
+        self.intervals.splice(first..last, [merged]);
+    }
+
+    pub fn remove(&mut self, interval: Interval<T>) {
+        *self = self.subtract(&RangeSet::from(interval));
+    }
+
+    /// Everything in either set.
+    pub fn union(&self, other: &RangeSet<T>) -> RangeSet<T> {
+        return RangeSet::from_intervals(
+            self.intervals.iter().chain(other.intervals.iter()).copied(),
+        );
+    }
+
+    /// Everything in both sets.
+    /// ```
+    /// use rangelib::{Interval, RangeSet};
+    /// let a = RangeSet::from_intervals(vec![Interval::new(0, 5), Interval::new(10, 15)]);
+    /// let b = RangeSet::from(Interval::new(3, 12));
+    /// assert_eq!(a.intersection(&b).intervals(), &[Interval::new(3, 5), Interval::new(10, 12)]);
+    /// ```
+    pub fn intersection(&self, other: &RangeSet<T>) -> RangeSet<T> {
+        let mut result = vec![];
+        let mut i = 0;
+        let mut j = 0;
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let a = self.intervals[i];
+            let b = other.intervals[j];
+            if let Some(both) = a.intersection(&b) {
+                result.push(both);
+            }
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        return RangeSet { intervals: result };
+    }
+
+    /// Everything in this set, but not in other.
+    /// ```
+    /// use rangelib::{Interval, RangeSet};
+    /// let a = RangeSet::from(Interval::new(0, 10));
+    /// let b = RangeSet::from_intervals(vec![Interval::new(2, 3), Interval::new(8, 20)]);
+    /// assert_eq!(a.subtract(&b).intervals(), &[Interval::new(0, 1), Interval::new(4, 7)]);
+    /// ```
+    pub fn subtract(&self, other: &RangeSet<T>) -> RangeSet<T> {
+        let mut result = vec![];
+        let mut j = 0;
+        for interval in self.intervals.iter() {
+            let mut remaining = Some(*interval);
+            // Skip anything that ends before this interval starts
+            while j < other.intervals.len() && other.intervals[j].end < interval.start {
+                j += 1;
+            }
+            let mut k = j;
+            while let Some(cur) = remaining {
+                if k >= other.intervals.len() || other.intervals[k].start > cur.end {
+                    break;
+                }
+                let cut = other.intervals[k];
+                if cut.start > cur.start {
+                    result.push(Interval::new(cur.start, cut.start - T::one()));
+                }
+                remaining = if cut.end < cur.end {
+                    Some(Interval::new(cut.end + T::one(), cur.end))
+                } else {
+                    None
+                };
+                k += 1;
+            }
+            if let Some(cur) = remaining {
+                result.push(cur);
+            }
+        }
+        return RangeSet { intervals: result };
+    }
+
+    /// The missing pieces between the lowest and highest values.
+    /// ```
+    /// use rangelib::{Interval, RangeSet};
+    /// let a = RangeSet::from_intervals(vec![Interval::new(0, 2), Interval::new(5, 6), Interval::new(9, 9)]);
+    /// assert_eq!(a.gaps().intervals(), &[Interval::new(3, 4), Interval::new(7, 8)]);
+    /// ```
+    pub fn gaps(&self) -> RangeSet<T> {
+        let gaps = self
+            .intervals
+            .windows(2)
+            .map(|pair| Interval::new(pair[0].end + T::one(), pair[1].start - T::one()))
+            .collect();
+        return RangeSet { intervals: gaps };
+    }
+
+    /// Everything in bounds that is not in this set.
+    pub fn gaps_within(&self, bounds: Interval<T>) -> RangeSet<T> {
+        return RangeSet::from(bounds).subtract(self);
+    }
+}
+
+impl<T: PrimInt> From<Interval<T>> for RangeSet<T> {
+    fn from(interval: Interval<T>) -> RangeSet<T> {
+        return RangeSet {
+            intervals: vec![interval],
+        };
+    }
+}
+
+impl<T: PrimInt> FromIterator<Interval<T>> for RangeSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> RangeSet<T> {
+        return RangeSet::from_intervals(iter);
+    }
+}
+
+impl<T: PrimInt + Display> Display for RangeSet<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<String> = self.intervals.iter().map(|i| i.to_string()).collect();
+        return write!(f, "{}", parts.join(","));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Brute force version, to check against.
+    fn members(set: &RangeSet<i32>) -> Vec<i32> {
+        return (-5..40).filter(|v| set.contains(*v)).collect();
+    }
+
+    fn sample_sets() -> Vec<RangeSet<i32>> {
+        return vec![
+            RangeSet::new(),
+            RangeSet::from(Interval::new(0, 0)),
+            RangeSet::from(Interval::new(-3, 30)),
+            RangeSet::from_intervals(vec![Interval::new(0, 4), Interval::new(10, 12)]),
+            RangeSet::from_intervals(vec![
+                Interval::new(3, 3),
+                Interval::new(5, 11),
+                Interval::new(20, 25),
+            ]),
+            RangeSet::from_intervals(vec![Interval::new(4, 6), Interval::new(12, 35)]),
+        ];
+    }
+
+    #[test]
+    fn test_merge_touching() {
+        let set = RangeSet::from_intervals(vec![
+            Interval::new(1, 2),
+            Interval::new(3, 4),
+            Interval::new(8, 10),
+            Interval::new(6, 6),
+        ]);
+        assert_eq!(
+            set.intervals(),
+            &[
+                Interval::new(1, 4),
+                Interval::new(6, 6),
+                Interval::new(8, 10)
+            ]
+        );
+        assert_eq!(set.len(), 8);
+        assert_eq!(set.to_string(), "1-4,6-6,8-10");
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut set: RangeSet<i32> = RangeSet::new();
+        set.insert(Interval::new(10, 12));
+        set.insert(Interval::new(1, 2));
+        set.insert(Interval::new(20, 22));
+        set.insert(Interval::new(5, 5));
+        assert_eq!(set.intervals().len(), 4);
+        set.insert(Interval::new(3, 11));
+        assert_eq!(
+            set.intervals(),
+            &[Interval::new(1, 12), Interval::new(20, 22)]
+        );
+        set.insert(Interval::new(13, 19));
+        assert_eq!(set.intervals(), &[Interval::new(1, 22)]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut set = RangeSet::from(Interval::new(0u32, 10));
+        set.remove(Interval::new(0, 0));
+        set.remove(Interval::new(5, 6));
+        set.remove(Interval::new(10, 20));
+        assert_eq!(set.intervals(), &[Interval::new(1, 4), Interval::new(7, 9)]);
+    }
+
+    #[test]
+    fn test_operations_match_brute_force() {
+        for a in sample_sets() {
+            let am = members(&a);
+            assert_eq!(a.len() as usize, am.len());
+            for b in sample_sets() {
+                let bm = members(&b);
+                let union: Vec<i32> = (-5..40)
+                    .filter(|v| am.contains(v) || bm.contains(v))
+                    .collect();
+                let both: Vec<i32> = am.iter().copied().filter(|v| bm.contains(v)).collect();
+                let only_a: Vec<i32> = am.iter().copied().filter(|v| !bm.contains(v)).collect();
+                assert_eq!(members(&a.union(&b)), union);
+                assert_eq!(members(&a.intersection(&b)), both);
+                assert_eq!(members(&a.subtract(&b)), only_a);
+                // results should also be fully merged
+                assert_eq!(
+                    a.union(&b),
+                    RangeSet::from_intervals(a.union(&b).intervals().to_vec())
+                );
+                for i in b.intervals() {
+                    let covered = (i.start..=i.end).all(|v| am.contains(&v));
+                    let touched = (i.start..=i.end).any(|v| am.contains(&v));
+                    assert_eq!(a.contains_interval(i), covered);
+                    assert_eq!(a.overlaps(i), touched);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_gaps() {
+        for a in sample_sets() {
+            let am = members(&a);
+            let bounds = Interval::new(-5, 39);
+            let outside: Vec<i32> = (-5..40).filter(|v| !am.contains(v)).collect();
+            assert_eq!(members(&a.gaps_within(bounds)), outside);
+            let inner: Vec<i32> = outside
+                .iter()
+                .copied()
+                .filter(|v| Some(*v) > a.min() && Some(*v) < a.max())
+                .collect();
+            assert_eq!(members(&a.gaps()), inner);
+        }
+    }
+
+    #[test]
+    fn test_extremes() {
+        let set =
+            RangeSet::from_intervals(vec![Interval::new(u8::MAX, u8::MAX), Interval::new(0, 254)]);
+        assert_eq!(set.intervals(), &[Interval::new(0, u8::MAX)]);
+        let mut set = RangeSet::from(Interval::new(i64::MIN, i64::MAX));
+        set.remove(Interval::new(i64::MIN, -1));
+        assert_eq!(set.min(), Some(0));
+        assert_eq!(set.max(), Some(i64::MAX));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_backwards_interval() {
+        Interval::new(5, 4);
+    }
+}